@@ -237,9 +237,12 @@ lazy_static::lazy_static! {{
         let size: usize = {symbol_len};
         let mut map = SymbolMap::with_capacity(size);
         sym::init_symbols(&mut map);
+        let block = Block::new_global();
+        let error_props = crate::core::error::standard_error_props(&block);
         ObjectMap {{
             map,
-            block: Block::new_global(),
+            block,
+            error_props,
         }}
     }});
 }}
@@ -1,4 +1,7 @@
+use crate::core::env::sym;
+use crate::core::error::LispError;
 use crate::core::object::{Gc, IntoObject, Number, Object};
+use anyhow::{ensure, Result};
 use float_cmp::ApproxEq;
 use fn_macros::defun;
 use std::cmp::{PartialEq, PartialOrd};
@@ -159,8 +162,24 @@ pub(crate) fn mul(numbers: &[Gc<Number>]) -> NumberValue {
 }
 
 #[defun(name = "/")]
-pub(crate) fn div(number: Gc<Number>, divisors: &[Gc<Number>]) -> NumberValue {
-    divisors.iter().fold(number.val(), |acc, x| acc / x.val())
+pub(crate) fn div(number: Gc<Number>, divisors: &[Gc<Number>]) -> Result<NumberValue> {
+    divisors.iter().try_fold(number.val(), |acc, x| {
+        let divisor = x.val();
+        ensure!(
+            !int_division_by_zero(acc, divisor),
+            LispError::new(sym::ARITH_ERROR, [])
+        );
+        Ok(acc / divisor)
+    })
+}
+
+/// Integer division by zero is an error, while float division gives an infinity
+/// or NaN.
+fn int_division_by_zero(dividend: NumberValue, divisor: NumberValue) -> bool {
+    matches!(
+        (dividend, divisor),
+        (NumberValue::Int(_), NumberValue::Int(0))
+    )
 }
 
 #[defun(name = "1+")]
@@ -233,9 +252,19 @@ fn logand(int_or_markers: &[Gc<i64>]) -> i64 {
     int_or_markers.iter().fold(-1, |accum, x| accum & x.untag())
 }
 
+#[defun(name = "%")]
+pub(crate) fn remainder(x: i64, y: i64) -> Result<i64> {
+    ensure!(y != 0, LispError::new(sym::ARITH_ERROR, []));
+    Ok(x.wrapping_rem(y))
+}
+
 #[defun(name = "mod")]
-pub(crate) fn modulo(x: Gc<Number>, y: Gc<Number>) -> NumberValue {
-    x.val() % y.val()
+pub(crate) fn modulo(x: Gc<Number>, y: Gc<Number>) -> Result<NumberValue> {
+    ensure!(
+        !int_division_by_zero(x.val(), y.val()),
+        LispError::new(sym::ARITH_ERROR, [])
+    );
+    Ok(x.val() % y.val())
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -301,8 +330,14 @@ mod test {
         let roots = &RootSet::default();
         let cx = &Context::new(roots);
 
-        assert_eq!(div(cx.add_as(12.0), &[]), NumberValue::Float(12.0));
-        assert_eq!(div(12.into(), &[5.into(), 2.into()]), NumberValue::Int(1));
+        assert_eq!(div(cx.add_as(12.0), &[]).unwrap(), NumberValue::Float(12.0));
+        assert_eq!(
+            div(12.into(), &[5.into(), 2.into()]).unwrap(),
+            NumberValue::Int(1)
+        );
+        assert!(div(12.into(), &[0.into()]).is_err());
+        assert!(div(cx.add_as(12.0), &[0.into()]).is_ok());
+        assert!(modulo(12.into(), 0.into()).is_err());
     }
 
     #[test]
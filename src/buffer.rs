@@ -1,10 +1,116 @@
-use crate::core::object::GcObj;
+use crate::core::{
+    env::Env,
+    error::{Type, TypeError},
+    gc::{Context, Rt},
+    object::{nil, GcObj, LispBuffer, Object},
+};
+use anyhow::{anyhow, Result};
 use fn_macros::defun;
 
+/// Resolve `buffer_or_name` to a live buffer object. Names that don't match an
+/// existing buffer return `None`.
+fn get_buffer_internal<'ob>(
+    buffer_or_name: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Option<&'ob LispBuffer>> {
+    match buffer_or_name.untag() {
+        Object::Buffer(buffer) => Ok(Some(buffer)),
+        Object::String(name) => Ok(env.get_buffer(name.try_into()?, cx)),
+        _ => Err(TypeError::new(Type::String, buffer_or_name).into()),
+    }
+}
+
+fn buffer_or_error<'ob>(
+    buffer_or_name: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispBuffer> {
+    get_buffer_internal(buffer_or_name, env, cx)?
+        .ok_or_else(|| anyhow!("No such buffer {buffer_or_name}"))
+}
+
 #[defun]
-fn set_buffer(buffer_or_name: GcObj) -> GcObj {
-    // TODO: implement
-    buffer_or_name
+fn get_buffer<'ob>(
+    buffer_or_name: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let buffer = get_buffer_internal(buffer_or_name, env, cx)?;
+    Ok(buffer.map_or_else(nil, Into::into))
+}
+
+#[defun]
+fn get_buffer_create<'ob>(
+    buffer_or_name: GcObj<'ob>,
+    _inhibit_buffer_hooks: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispBuffer> {
+    match get_buffer_internal(buffer_or_name, env, cx)? {
+        Some(buffer) => Ok(buffer),
+        None => {
+            let name: &str = buffer_or_name.try_into()?;
+            Ok(env.create_buffer(name, cx))
+        }
+    }
+}
+
+#[defun]
+pub(crate) fn current_buffer<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> &'ob LispBuffer {
+    env.current_buffer(cx)
+}
+
+#[defun]
+pub(crate) fn set_buffer<'ob>(
+    buffer_or_name: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispBuffer> {
+    let buffer = buffer_or_error(buffer_or_name, env, cx)?;
+    env.set_buffer(buffer)?;
+    Ok(buffer)
+}
+
+#[defun]
+fn buffer_name<'ob>(
+    buffer: Option<&LispBuffer>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> GcObj<'ob> {
+    let buffer = match buffer {
+        Some(x) => x,
+        None => env.current_buffer(cx),
+    };
+    match buffer.name() {
+        Some(name) => cx.add(name),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn buffer_live_p(object: GcObj) -> bool {
+    match object.untag() {
+        Object::Buffer(buffer) => buffer.is_live(),
+        _ => false,
+    }
+}
+
+#[defun]
+fn kill_buffer(buffer_or_name: Option<GcObj>, env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = match buffer_or_name {
+        Some(x) => buffer_or_error(x, env, cx)?,
+        None => env.current_buffer(cx),
+    };
+    Ok(env.kill_buffer(buffer, cx))
+}
+
+#[defun]
+fn buffer_list<'ob>(_frame: Option<()>, env: &mut Rt<Env>, cx: &'ob Context) -> GcObj<'ob> {
+    // make sure the initial buffer exists
+    env.current_buffer(cx);
+    let buffers: Vec<GcObj> = env.buffers.iter().map(|x| x.bind(cx).into()).collect();
+    crate::fns::slice_into_list(&buffers, None, cx)
 }
 
 #[defun]
@@ -12,3 +118,34 @@ fn set_buffer_modified_p(flag: GcObj) -> GcObj {
     // TODO: implement
     flag
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::root;
+
+    #[test]
+    fn test_buffer_list() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let scratch = current_buffer(env, cx);
+        assert_eq!(scratch.name().unwrap(), "*scratch*");
+        let name = cx.add("foo");
+        let foo = get_buffer_create(name, None, env, cx).unwrap();
+        assert_eq!(foo.name().unwrap(), "foo");
+        let again = get_buffer_create(name, None, env, cx).unwrap();
+        assert_eq!(foo, again);
+        assert_eq!(buffer_list(None, env, cx), list![scratch, foo; cx]);
+
+        set_buffer(name, env, cx).unwrap();
+        assert_eq!(env.current_buffer(cx), foo);
+        assert!(kill_buffer(None, env, cx).unwrap());
+        assert!(!foo.is_live());
+        assert_eq!(buffer_name(Some(foo), env, cx), nil());
+        assert_eq!(env.current_buffer(cx), scratch);
+        assert!(set_buffer(foo.into(), env, cx).is_err());
+        assert_eq!(get_buffer(name, env, cx).unwrap(), nil());
+    }
+}
@@ -1,14 +1,19 @@
 //! The main bytecode interpeter.
 use crate::core::env::{sym, Env, Symbol};
-use crate::core::error::{ErrorType, EvalError, EvalResult};
+use crate::core::error::{
+    condition_matches, ErrorType, EvalError, EvalResult, LispError, Type, TypeError,
+};
 use crate::core::gc::{Context, IntoRoot, Rt, Trace};
-use crate::core::object::{nil, ByteFn, Gc, GcObj, LispString, LispVec, Object, WithLifetime};
+use crate::core::object::{
+    nil, ByteFn, Function, Gc, GcObj, LispHashTable, LispString, LispVec, Object, WithLifetime,
+};
 use crate::root;
 use anyhow::{bail, Result};
 use bstr::ByteSlice;
 use fn_macros::{defun, Trace};
 use std::ops::{DerefMut, Index, IndexMut, RangeTo};
 
+mod disassemble;
 mod opcode;
 
 /// An program counter. This is implemented as a bound checked range pointer.
@@ -199,20 +204,33 @@ impl Rt<LispStack> {
     }
 }
 
-#[derive(Debug, Trace)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HandlerKind {
+    ConditionCase,
+    Catch,
+}
+
+#[derive(Debug, Clone, Copy, Trace)]
 struct Handler<'ob> {
+    #[no_trace]
+    kind: HandlerKind,
     #[no_trace]
     jump_code: u16,
     #[no_trace]
     stack_size: usize,
+    #[no_trace]
+    binding_depth: usize,
+    /// The conditions of a `condition-case` or the tag of a `catch`
     condition: GcObj<'ob>,
 }
 
 impl<'ob> IntoRoot<Handler<'static>> for Handler<'ob> {
     unsafe fn into_root(self) -> Handler<'static> {
         Handler {
+            kind: self.kind,
             jump_code: self.jump_code,
             stack_size: self.stack_size,
+            binding_depth: self.binding_depth,
             condition: self.condition.into_root(),
         }
     }
@@ -235,14 +253,17 @@ struct Routine<'brw> {
     /// The current call frame.
     frame: CallFrame<'brw>,
     handlers: &'brw mut Rt<Vec<Handler<'static>>>,
+    /// Depth of the binding stack when this routine was entered. Anything
+    /// bound above it is unwound if an error escapes the routine.
+    binding_depth: usize,
 }
 
 impl<'brw, 'ob> Routine<'brw> {
     fn varref(&mut self, idx: u16, env: &Rt<Env>, cx: &'ob Context) -> Result<()> {
         let symbol = self.frame.get_const(idx as usize, cx);
         if let Object::Symbol(sym) = symbol.untag() {
-            let Some(var) = env.vars.get(sym) else {bail!("Void Variable: {sym}")};
-            self.stack.push(var.bind(cx));
+            let Some(var) = env.var(sym, cx) else {bail!(LispError::new(sym::VOID_VARIABLE, [sym.into()]))};
+            self.stack.push(var);
             Ok(())
         } else {
             unreachable!("Varref was not a symbol: {:?}", symbol);
@@ -266,8 +287,73 @@ impl<'brw, 'ob> Routine<'brw> {
         env.varbind(sym, value, cx);
     }
 
-    fn unbind(&self, idx: u16, env: &mut Rt<Env>, cx: &'ob Context) {
-        env.unbind(idx, cx);
+    /// Undo the last `count` entries on the binding stack, calling the handlers
+    /// of any `unwind-protect` among them.
+    fn unbind(&mut self, count: u16, env: &mut Rt<Env>, cx: &mut Context) -> Result<(), EvalError> {
+        for _ in 0..count {
+            match env.pop_unwind_protect(cx) {
+                Some(handler) => {
+                    root!(handler, cx);
+                    Self::call_unwind_handler(handler, env, cx)?;
+                }
+                None => env.unbind(1, cx),
+            }
+        }
+        Ok(())
+    }
+
+    /// Unbind everything above `depth` on the binding stack.
+    fn unbind_to(
+        &mut self,
+        depth: usize,
+        env: &mut Rt<Env>,
+        cx: &mut Context,
+    ) -> Result<(), EvalError> {
+        while env.binding_depth() > depth {
+            self.unbind(1, env, cx)?;
+        }
+        Ok(())
+    }
+
+    /// Run the handler of an `unwind-protect`. Like in Emacs this is a function
+    /// that is called with no arguments, or a list of forms that are evaluated
+    /// in older code that uses dynamic binding.
+    fn call_unwind_handler(
+        handler: &Rt<GcObj>,
+        env: &mut Rt<Env>,
+        cx: &mut Context,
+    ) -> Result<(), EvalError> {
+        if crate::data::functionp(handler.bind(cx)) {
+            let func: Gc<Function> = handler.bind(cx).try_into()?;
+            root!(func, cx);
+            root!(args, Vec::new(), cx);
+            func.call(args, env, cx, None)?;
+        } else {
+            let form = crate::cons!(sym::PROGN, handler.bind(cx); cx);
+            root!(form, cx);
+            crate::interpreter::eval(form, None, env, cx)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the top `count` elements of the stack with their concatenation.
+    fn concat(&mut self, count: u16, cx: &'ob Context) -> Result<()> {
+        let slice = Rt::bind_slice(&self.stack[..count], cx);
+        let string = crate::fns::concat(slice, cx)?;
+        let len = self.stack.len();
+        self.stack.truncate(len - count as usize);
+        self.stack.push::<GcObj>(string.into());
+        Ok(())
+    }
+
+    /// Remove the innermost handler. A `catch` is also removed from the catch
+    /// stack, so that `throw` no longer sees it.
+    fn pop_handler(&mut self, env: &mut Rt<Env>, cx: &'ob Context) -> Option<Handler<'ob>> {
+        let handler: Handler = self.handlers.pop_obj(cx)?;
+        if handler.kind == HandlerKind::Catch {
+            env.catch_stack.pop();
+        }
+        Some(handler)
     }
 
     #[inline(always)]
@@ -278,14 +364,8 @@ impl<'brw, 'ob> Routine<'brw> {
     /// Prepare ethe arguments for lisp function call. This means filling all
     /// needed stack slots with `nil` and moving all the `&rest` arguments into
     /// a list.
-    fn prepare_lisp_args(
-        &mut self,
-        func: &ByteFn,
-        arg_cnt: u16,
-        name: &str,
-        cx: &'ob Context,
-    ) -> Result<u16> {
-        let fill_args = func.args.num_of_fill_args(arg_cnt, name)?;
+    fn prepare_lisp_args(&mut self, func: &ByteFn, arg_cnt: u16, cx: &'ob Context) -> Result<u16> {
+        let fill_args = func.args.num_of_fill_args(arg_cnt, Object::ByteFn(func))?;
         self.stack.fill_extra_args(fill_args);
         let total_args = arg_cnt + fill_args;
         let rest_size = total_args - (func.args.required + func.args.optional);
@@ -309,18 +389,16 @@ impl<'brw, 'ob> Routine<'brw> {
         env: &mut Rt<Env>,
         cx: &'ob mut Context,
     ) -> Result<(), EvalError> {
-        let sym = match self.stack[arg_cnt as usize].get(cx) {
-            Object::Symbol(x) => x,
-            x => unreachable!("Expected symbol for call found {:?}", x),
+        // The callee is a symbol, or a function object from `funcall`
+        let callee = self.stack[arg_cnt as usize].bind(cx);
+        let Ok(func) = Gc::<Function>::try_from(callee) else {
+            bail_err!(LispError::new(sym::INVALID_FUNCTION, [callee.into()]))
         };
-
-        let Some(func) = sym.follow_indirect(cx) else {bail_err!("Void Function: {sym}")};
         let slice = &self.stack[..arg_cnt];
         let args = Rt::bind_slice(slice, cx).to_vec();
-        let name = sym.name().to_owned();
         root!(args, cx);
         root!(func, cx);
-        let result = rebind!(func.call(args, env, cx, Some(&name))?, cx);
+        let result = rebind!(func.call(args, env, cx, None)?, cx);
         self.stack.remove_top(arg_cnt);
         self.stack[0].set(result);
         cx.garbage_collect(false);
@@ -329,58 +407,73 @@ impl<'brw, 'ob> Routine<'brw> {
 
     fn run(&mut self, env: &mut Rt<Env>, cx: &'ob mut Context) -> EvalResult<'ob> {
         'main: loop {
-            let err = match self.execute_bytecode(env, cx) {
+            let mut err = match self.execute_bytecode(env, cx) {
                 Ok(x) => return Ok(rebind!(x, cx)),
-                Err(e) => e,
+                Err(e) => e.into_signal(env, cx),
             };
-
-            // we will fix this once we can handle different error types
-            #[allow(clippy::never_loop)]
-            while let Some(handler) = self.handlers.pop_obj(cx) {
-                match handler.condition.untag() {
-                    Object::Symbol(sym::ERROR) => {}
-                    Object::Cons(cons) => {
-                        for x in cons.elements() {
-                            let x = x?;
-                            // TODO: Handle different error symbols
-                            if x != sym::DEBUG && x != sym::ERROR {
-                                bail_err!("non-error conditions {x} not yet supported")
-                            }
-                        }
+            // Handlers are removed from the innermost out, and the bindings
+            // made inside each one are undone before it is checked, which runs
+            // the handlers of any `unwind-protect` in the same order as Emacs.
+            // If one of those signals or throws, that replaces the error and
+            // the search continues from where it was.
+            'search: loop {
+                // The tag of a throw or the symbol of an error, and the value
+                // that is passed to a matching handler
+                let (tag, value) = match err.signal_data(env, cx) {
+                    Some((symbol, data)) => (symbol, cons!(symbol, data; cx)),
+                    None => {
+                        let ErrorType::Throw(id) = err.error else {unreachable!("Expected a throw")};
+                        let Some((tag, value)) = env.get_exception(id) else {unreachable!("Exception not found")};
+                        (tag.bind(cx), value.bind(cx))
                     }
-                    x => bail_err!("Invalid condition handler: {x}"),
-                }
-
-                let error = if let EvalError {
-                    error: ErrorType::Signal(id),
-                    ..
-                } = err
-                {
-                    let Some((sym, data)) = env.get_exception(id) else {unreachable!("Exception not found")};
-                    cons!(sym, data; cx)
-                } else {
-                    // TODO: Need to remove the anyhow branch once
-                    // full errors are implemented
-                    cons!(sym::ERROR, format!("{err}"); cx)
                 };
-                self.stack.truncate(handler.stack_size);
-                self.stack.push(error);
-                self.frame.pc.goto(handler.jump_code);
-                continue 'main;
+                let is_throw = matches!(err.error, ErrorType::Throw(_));
+                root!(tag, cx);
+                root!(value, cx);
+                while let Some(handler) = self.handlers.last().map(|x| x.bind(cx)) {
+                    if let Err(e) = self.unbind_to(handler.binding_depth, env, cx) {
+                        err = e.into_signal(env, cx);
+                        continue 'search;
+                    }
+                    let handler = self.pop_handler(env, cx).unwrap();
+                    let matches = match handler.kind {
+                        HandlerKind::Catch => is_throw && handler.condition == tag.bind(cx),
+                        HandlerKind::ConditionCase => {
+                            !is_throw && condition_matches(handler.condition, tag.bind(cx), env, cx)
+                        }
+                    };
+                    if matches {
+                        self.stack.truncate(handler.stack_size);
+                        self.stack.push(value.bind(cx));
+                        self.frame.pc.goto(handler.jump_code);
+                        continue 'main;
+                    }
+                }
+                if let Err(e) = self.unbind_to(self.binding_depth, env, cx) {
+                    err = e.into_signal(env, cx);
+                    continue 'search;
+                }
+                return Err(err);
             }
-            return Err(err);
         }
     }
 
     #[allow(clippy::too_many_lines)]
     /// The main bytecode execution loop.
     fn execute_bytecode(&mut self, env: &mut Rt<Env>, cx: &'ob mut Context) -> EvalResult<'ob> {
-        use crate::{alloc, arith, data, fns};
+        use crate::{
+            alloc, arith, buffer, casefiddle, cmds, data, editfns, fns, indent, marker, search,
+            syntax,
+        };
         use opcode::OpCode as op;
         loop {
-            let op = match self.frame.pc.next().try_into() {
+            let op = match op::try_from(self.frame.pc.next()) {
                 Ok(x) => x,
-                Err(e) => panic!("Invalid Bytecode: {e}"),
+                Err(e) => {
+                    let byte_offset =
+                        self.frame.pc.pc as i64 - self.frame.pc.range.start as i64 - 1;
+                    bail_err!("Invalid byte opcode: op={}, ptr={byte_offset}", e.number);
+                }
             };
 
             if Self::debug_enabled() {
@@ -471,34 +564,47 @@ impl<'brw, 'ob> Routine<'brw> {
                     let idx = self.frame.pc.arg2();
                     self.call(idx, env, cx)?;
                 }
-                op::Unbind0 => self.unbind(0, env, cx),
-                op::Unbind1 => self.unbind(1, env, cx),
-                op::Unbind2 => self.unbind(2, env, cx),
-                op::Unbind3 => self.unbind(3, env, cx),
-                op::Unbind4 => self.unbind(4, env, cx),
-                op::Unbind5 => self.unbind(5, env, cx),
+                op::Unbind0 => self.unbind(0, env, cx)?,
+                op::Unbind1 => self.unbind(1, env, cx)?,
+                op::Unbind2 => self.unbind(2, env, cx)?,
+                op::Unbind3 => self.unbind(3, env, cx)?,
+                op::Unbind4 => self.unbind(4, env, cx)?,
+                op::Unbind5 => self.unbind(5, env, cx)?,
                 op::UnbindN => {
                     let idx = self.frame.pc.arg1();
-                    self.unbind(idx, env, cx);
+                    self.unbind(idx, env, cx)?;
                 }
                 op::UnbindN2 => {
                     let idx = self.frame.pc.arg2();
-                    self.unbind(idx, env, cx);
+                    self.unbind(idx, env, cx)?;
                 }
                 op::PopHandler => {
-                    self.handlers.pop();
+                    self.pop_handler(env, cx);
                 }
                 op::PushCondtionCase => {
                     // pop before getting stack size
                     let condition = self.stack.pop(cx);
                     let handler = Handler {
+                        kind: HandlerKind::ConditionCase,
                         jump_code: self.frame.pc.arg2(),
                         stack_size: self.stack.len(),
+                        binding_depth: env.binding_depth(),
                         condition,
                     };
                     self.handlers.push(handler);
                 }
-                op::PushCatch => todo!("PushCatch bytecode"),
+                op::PushCatch => {
+                    let tag = self.stack.pop(cx);
+                    let handler = Handler {
+                        kind: HandlerKind::Catch,
+                        jump_code: self.frame.pc.arg2(),
+                        stack_size: self.stack.len(),
+                        binding_depth: env.binding_depth(),
+                        condition: tag,
+                    };
+                    self.handlers.push(handler);
+                    env.catch_stack.push(tag);
+                }
                 op::Nth => {
                     let list = self.stack.pop(cx);
                     let top = self.stack.top();
@@ -607,10 +713,17 @@ impl<'brw, 'ob> Routine<'brw> {
                     let top = self.stack.top();
                     top.set(data::get(top.bind_as(cx)?, prop.try_into()?, env, cx));
                 }
-                op::Substring => todo!("Substring bytecode"),
-                op::Concat2 => todo!("Concat2 bytecode"),
-                op::Concat3 => todo!("Concat3 bytecode"),
-                op::Concat4 => todo!("Concat4 bytecode"),
+                op::Substring => {
+                    let to = self.stack.pop(cx);
+                    let from = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let string = top.bind(cx).try_into()?;
+                    let result = fns::substring(string, from.try_into()?, to.try_into()?, cx)?;
+                    top.set::<GcObj>(result.into());
+                }
+                op::Concat2 => self.concat(2, cx)?,
+                op::Concat3 => self.concat(3, cx)?,
+                op::Concat4 => self.concat(4, cx)?,
                 op::Sub1 => {
                     let top = self.stack.top();
                     top.set(cx.add(arith::sub_one(top.bind_as(cx)?)));
@@ -644,7 +757,12 @@ impl<'brw, 'ob> Routine<'brw> {
                     let top = self.stack.top();
                     top.set(arith::greater_than_or_eq(top.bind_as(cx)?, v1));
                 }
-                op::Diff => todo!("Diff bytecode"),
+                op::Diff => {
+                    let arg1 = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let args = &[arg1.try_into()?];
+                    top.set(cx.add(arith::sub(Some(top.bind_as(cx)?), args)));
+                }
                 op::Negate => {
                     let top = self.stack.top();
                     top.set(cx.add(arith::sub(top.bind_as(cx)?, &[])));
@@ -673,34 +791,126 @@ impl<'brw, 'ob> Routine<'brw> {
                     let args = &[top.bind_as(cx)?, arg1.try_into()?];
                     top.set(cx.add(arith::mul(args)));
                 }
-                op::Point => todo!("Point bytecode"),
-                op::GotoChar => todo!("GotoChar bytecode"),
-                op::Insert => todo!("Insert bytecode"),
-                op::PointMax => todo!("PointMax bytecode"),
-                op::PointMin => todo!("PointMin bytecode"),
-                op::CharAfter => todo!("CharAfter bytecode"),
-                op::FollowingChar => todo!("FollowingChar bytecode"),
-                op::PrecedingChar => todo!("PrecedingChar bytecode"),
-                op::CurrentColumn => todo!("CurrentColumn bytecode"),
-                op::IndentTo => todo!("IndentTo bytecode"),
-                op::EndOfLineP => todo!("EndOfLineP bytecode"),
-                op::EndOfBufferP => todo!("EndOfBufferP bytecode"),
-                op::BeginningOfLineP => todo!("BeginningOfLineP bytecode"),
-                op::BeginningOfBufferP => todo!("BeginningOfBufferP bytecode"),
-                op::CurrentBuffer => todo!("CurrentBuffer bytecode"),
-                op::SetBuffer => todo!("SetBuffer bytecode"),
-                op::SaveCurrentBuffer1 => todo!("SaveCurrentBuffer1 bytecode"),
-                op::ForwardChar => todo!("ForwardChar bytecode"),
-                op::ForwardWord => todo!("ForwardWord bytecode"),
-                op::SkipCharsForward => todo!("SkipCharsForward bytecode"),
-                op::SkipCharsBackward => todo!("SkipCharsBackward bytecode"),
-                op::ForwardLine => todo!("ForwardLine bytecode"),
-                op::CharSyntax => todo!("CharSyntax bytecode"),
-                op::BufferSubstring => todo!("BufferSubstring bytecode"),
-                op::DeleteRegion => todo!("DeleteRegion bytecode"),
-                op::NarrowToRegion => todo!("NarrowToRegion bytecode"),
-                op::Widen => todo!("Widen bytecode"),
-                op::EndOfLine => todo!("EndOfLine bytecode"),
+                op::Point => {
+                    let point = editfns::point(env, cx)?;
+                    self.stack.push(cx.add(point));
+                }
+                op::GotoChar => {
+                    let top = self.stack.top();
+                    top.set(editfns::goto_char(top.bind(cx), env, cx)?);
+                }
+                op::Insert => {
+                    let top = self.stack.top();
+                    top.set(editfns::insert(&[top.bind(cx)], env, cx)?);
+                }
+                op::PointMax => {
+                    let max = editfns::point_max(env, cx)?;
+                    self.stack.push(cx.add(max));
+                }
+                op::PointMin => {
+                    let min = editfns::point_min(env, cx)?;
+                    self.stack.push(cx.add(min));
+                }
+                op::CharAfter => {
+                    let top = self.stack.top();
+                    top.set(editfns::char_after(top.bind_as(cx)?, env, cx)?);
+                }
+                op::FollowingChar => {
+                    let chr = editfns::following_char(env, cx)?;
+                    self.stack.push(cx.add(chr));
+                }
+                op::PrecedingChar => {
+                    let chr = editfns::preceding_char(env, cx)?;
+                    self.stack.push(cx.add(chr));
+                }
+                op::CurrentColumn => {
+                    let column = indent::current_column(env, cx)?;
+                    self.stack.push(cx.add(column));
+                }
+                op::IndentTo => {
+                    let top = self.stack.top();
+                    let column = indent::indent_to(top.bind_as(cx)?, None, env, cx)?;
+                    top.set(cx.add(column));
+                }
+                op::EndOfLineP => {
+                    let eolp = editfns::eolp(env, cx)?;
+                    self.stack.push(cx.add(eolp));
+                }
+                op::EndOfBufferP => {
+                    let eobp = editfns::eobp(env, cx)?;
+                    self.stack.push(cx.add(eobp));
+                }
+                op::BeginningOfLineP => {
+                    let bolp = editfns::bolp(env, cx)?;
+                    self.stack.push(cx.add(bolp));
+                }
+                op::BeginningOfBufferP => {
+                    let bobp = editfns::bobp(env, cx)?;
+                    self.stack.push(cx.add(bobp));
+                }
+                op::CurrentBuffer => {
+                    let buffer = buffer::current_buffer(env, cx);
+                    self.stack.push(cx.add(buffer));
+                }
+                op::SetBuffer => {
+                    let top = self.stack.top();
+                    top.set(cx.add(buffer::set_buffer(top.bind(cx), env, cx)?));
+                }
+                op::SaveCurrentBuffer1 => env.save_current_buffer(cx),
+                op::ForwardChar => {
+                    let top = self.stack.top();
+                    top.set(cmds::forward_char(top.bind_as(cx)?, env, cx)?);
+                }
+                op::ForwardWord => {
+                    let top = self.stack.top();
+                    top.set(syntax::forward_word(top.bind_as(cx)?, env, cx)?);
+                }
+                op::SkipCharsForward => {
+                    let lim = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let moved =
+                        syntax::skip_chars_forward(top.bind_as(cx)?, lim.try_into()?, env, cx)?;
+                    top.set(cx.add(moved));
+                }
+                op::SkipCharsBackward => {
+                    let lim = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let moved =
+                        syntax::skip_chars_backward(top.bind_as(cx)?, lim.try_into()?, env, cx)?;
+                    top.set(cx.add(moved));
+                }
+                op::ForwardLine => {
+                    let top = self.stack.top();
+                    top.set(cx.add(cmds::forward_line(top.bind_as(cx)?, env, cx)?));
+                }
+                op::CharSyntax => {
+                    let top = self.stack.top();
+                    top.set(cx.add(syntax::char_syntax(top.bind_as(cx)?)?));
+                }
+                op::BufferSubstring => {
+                    let end = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let string = editfns::buffer_substring(top.bind(cx), end, env, cx)?;
+                    top.set(GcObj::from(string));
+                }
+                op::DeleteRegion => {
+                    let end = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    top.set(editfns::delete_region(top.bind(cx), end, env, cx)?);
+                }
+                op::NarrowToRegion => {
+                    let end = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    top.set(editfns::narrow_to_region(top.bind(cx), end, env, cx)?);
+                }
+                op::Widen => {
+                    let result = editfns::widen(env, cx)?;
+                    self.stack.push(result);
+                }
+                op::EndOfLine => {
+                    let top = self.stack.top();
+                    top.set(cmds::end_of_line(top.bind_as(cx)?, env, cx)?);
+                }
                 op::ConstantN2 => {
                     let idx = self.frame.pc.arg2();
                     self.stack.push(self.frame.get_const(idx.into(), cx));
@@ -769,16 +979,53 @@ impl<'brw, 'ob> Routine<'brw> {
                     let top = self.stack[0].bind(cx);
                     self.stack.push(top);
                 }
-                op::SaveExcursion => todo!("SaveExcursion bytecode"),
-                op::SaveRestriction => todo!("SaveRestriction bytecode"),
-                op::UnwindProtect => todo!("UnwindProtect bytecode"),
-                op::SetMarker => todo!("SetMarker bytecode"),
-                op::MatchBeginning => todo!("MatchBeginning bytecode"),
-                op::MatchEnd => todo!("MatchEnd bytecode"),
-                op::Upcase => todo!("Upcase bytecode"),
-                op::Downcase => todo!("Downcase bytecode"),
-                op::StringEqlSign => todo!("StringEqlSign bytecode"),
-                op::StringLessThan => todo!("StringLessThan bytecode"),
+                op::SaveExcursion => env.save_excursion(cx)?,
+                op::SaveRestriction => env.save_restriction(cx)?,
+                op::UnwindProtect => {
+                    let handler = self.stack.pop(cx);
+                    env.push_unwind_protect(handler);
+                }
+                op::SetMarker => {
+                    let buffer = self.stack.pop(cx);
+                    let position = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let marker = marker::set_marker(
+                        top.bind_as(cx)?,
+                        position,
+                        buffer.try_into()?,
+                        env,
+                        cx,
+                    )?;
+                    top.set(cx.add(marker));
+                }
+                op::MatchBeginning => {
+                    let top = self.stack.top();
+                    top.set(search::match_beginning(top.bind(cx).try_into()?, env, cx)?);
+                }
+                op::MatchEnd => {
+                    let top = self.stack.top();
+                    top.set(search::match_end(top.bind(cx).try_into()?, env, cx)?);
+                }
+                op::Upcase => {
+                    let top = self.stack.top();
+                    top.set(casefiddle::upcase(top.bind(cx), cx)?);
+                }
+                op::Downcase => {
+                    let top = self.stack.top();
+                    top.set(casefiddle::downcase(top.bind(cx), cx)?);
+                }
+                op::StringEqlSign => {
+                    let rhs = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let equal = search::string_equal(top.bind(cx).try_into()?, rhs.try_into()?);
+                    top.set::<GcObj>(equal.into());
+                }
+                op::StringLessThan => {
+                    let rhs = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let less = search::string_lessp(top.bind(cx).try_into()?, rhs.try_into()?);
+                    top.set::<GcObj>(less.into());
+                }
                 op::Equal => {
                     let rhs = self.stack.pop(cx);
                     let top = self.stack.top();
@@ -831,8 +1078,18 @@ impl<'brw, 'ob> Routine<'brw> {
                     let top = self.stack.top();
                     top.set(fns::nconc(&[top.bind_as(cx)?, list2.try_into()?])?);
                 }
-                op::Quo => todo!("Quo bytecode"),
-                op::Rem => todo!("Rem bytecode"),
+                op::Quo => {
+                    let arg1 = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let args = &[arg1.try_into()?];
+                    top.set(cx.add(arith::div(top.bind_as(cx)?, args)?));
+                }
+                op::Rem => {
+                    let arg1 = self.stack.pop(cx);
+                    let top = self.stack.top();
+                    let x = top.bind(cx).try_into()?;
+                    top.set(cx.add(arith::remainder(x, arg1.try_into()?)?));
+                }
                 op::Numberp => {
                     let top = self.stack.top();
                     top.set(data::numberp(top.bind(cx)));
@@ -849,13 +1106,23 @@ impl<'brw, 'ob> Routine<'brw> {
                     self.stack.truncate(len - (size as usize - 1));
                     self.stack.top().set(list);
                 }
-                op::ConcatN => todo!("ConcatN bytecode"),
-                op::InsertN => todo!("InsertN bytecode"),
+                op::ConcatN => {
+                    let count = self.frame.pc.arg1();
+                    self.concat(count, cx)?;
+                }
+                op::InsertN => {
+                    let size = self.frame.pc.arg1();
+                    let args = Rt::bind_slice(&self.stack[..size], cx);
+                    let result = editfns::insert(args, env, cx)?;
+                    let len = self.stack.len();
+                    self.stack.truncate(len - (size as usize - 1));
+                    self.stack.top().set(result);
+                }
                 op::Switch => {
-                    let Object::HashTable(table) = self.stack.pop(cx).untag() else {unreachable!("switch table was not a hash table")};
+                    let table: Gc<&LispHashTable> = self.stack.pop(cx).try_into()?;
                     let cond = self.stack.pop(cx);
-                    if let Some(offset) = table.borrow().get(&cond) {
-                        let Object::Int(offset) = offset.get().untag() else {unreachable!("switch value was not a int")};
+                    if let Some(offset) = table.untag().borrow().get(&cond) {
+                        let offset: i64 = offset.get().try_into()?;
                         self.frame.pc.goto(offset as u16);
                     }
                 }
@@ -951,13 +1218,27 @@ fn byte_code<'ob>(
     )?;
     root!(fun, cx);
     root!(args, Vec::new(), cx);
-    Ok(call(fun, args, "unnamed", env, cx)?)
+    Ok(call(fun, args, env, cx)?)
+}
+
+/// Return a listing of the instructions in the byte-code function `function`,
+/// which can also be a symbol whose definition is one. Each line has the offset
+/// of an instruction, its opcode and operand, and the constant it refers to.
+#[defun]
+fn disassemble_byte_code(function: GcObj, cx: &Context) -> Result<String> {
+    let func = match function.untag() {
+        Object::Symbol(sym) => sym.follow_indirect(cx).map(Gc::untag),
+        _ => function.try_into().ok().map(|x: Gc<Function>| x.untag()),
+    };
+    match func {
+        Some(Function::ByteFn(func)) => Ok(disassemble::disassemble(func)),
+        _ => Err(TypeError::new(Type::ByteFn, function).into()),
+    }
 }
 
 pub(crate) fn call<'ob>(
     func: &Rt<&'static ByteFn>,
     args: &mut Rt<Vec<GcObj<'static>>>,
-    name: &str,
     env: &mut Rt<Env>,
     cx: &'ob mut Context,
 ) -> EvalResult<'ob> {
@@ -969,8 +1250,9 @@ pub(crate) fn call<'ob>(
         call_frames: vec![],
         frame: CallFrame::new(func, 0, cx),
         handlers,
+        binding_depth: env.binding_depth(),
     };
-    rout.prepare_lisp_args(func.bind(cx), arg_cnt, name, cx)?;
+    rout.prepare_lisp_args(func.bind(cx), arg_cnt, cx)?;
     rout.run(env, cx)
 }
 
@@ -978,7 +1260,7 @@ pub(crate) fn call<'ob>(
 #[cfg(test)]
 mod test {
     use crate::core::{
-        env::sym,
+        env::{intern, sym},
         gc::RootSet,
         object::{HashTable, IntoObject, LispVec},
     };
@@ -1041,7 +1323,7 @@ mod test {
         cx: &mut Context,
     ) {
         root!(env, Env::default(), cx);
-        let val = rebind!(call(bytecode, args, "test", env, cx).unwrap(), cx);
+        let val = rebind!(call(bytecode, args, env, cx).unwrap(), cx);
         let expect = expect.bind(cx);
         assert_eq!(val, expect);
     }
@@ -1254,5 +1536,784 @@ mod test {
         );
         check_bytecode!(bytecode, [3], 7, cx);
         check_bytecode!(bytecode, [sym::FLOOR], "floor", cx);
+
+        // (lambda (y) (condition-case nil
+        //            (floor)
+        //              ((arith-error wrong-type-argument) (+ y 4))))
+        let conditions = list![sym::ARITH_ERROR, sym::WRONG_TYPE_ARGUMENT; cx];
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Constant0,
+                PushCondtionCase,
+                0x09,
+                0x0,
+                Constant1,
+                StackRef1,
+                Call1,
+                PopHandler,
+                Return,
+                Discard,
+                Duplicate,
+                Constant2,
+                Plus,
+                Return
+            ],
+            [conditions, sym::SYMBOL_NAME, 4],
+            cx
+        );
+        check_bytecode!(bytecode, [3], 7, cx);
+
+        // (lambda (y) (condition-case nil
+        //            (floor)
+        //              (arith-error (+ y 4))))
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Constant0,
+                PushCondtionCase,
+                0x09,
+                0x0,
+                Constant1,
+                StackRef1,
+                Call1,
+                PopHandler,
+                Return,
+                Discard,
+                Duplicate,
+                Constant2,
+                Plus,
+                Return
+            ],
+            [sym::ARITH_ERROR, sym::SYMBOL_NAME, 4],
+            cx
+        );
+        root!(env, Env::default(), cx);
+        root!(args, move(vec![cx.add(3)]), cx);
+        assert!(call(bytecode, args, env, cx).is_err());
+    }
+
+    #[test]
+    fn test_string_and_arith_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+
+        // (lambda (s) (upcase (substring s 1 -1)))
+        make_bytecode!(
+            bytecode,
+            257,
+            [StackRef0, Constant0, Constant1, Substring, Upcase, Return],
+            [1, -1],
+            cx
+        );
+        check_bytecode!(bytecode, ["hello"], "ELL", cx);
+
+        // (lambda (a b) (list (concat a b "!") (concat a b a b "!") (string< a b)))
+        make_bytecode!(
+            bytecode,
+            514,
+            [
+                StackRef1,
+                StackRef1,
+                Constant0,
+                Concat3,
+                StackRef2,
+                StackRef2,
+                StackRef4,
+                StackRef4,
+                Constant0,
+                ConcatN,
+                5,
+                StackRef3,
+                StackRef3,
+                StringLessThan,
+                List3,
+                Return
+            ],
+            ["!"],
+            cx
+        );
+        let list = list!["ab!", "abab!", true; cx];
+        root!(list, cx);
+        check_bytecode!(bytecode, ["a", "b"], list, cx);
+
+        // (lambda (a b) (list (- a b) (/ a b) (% a b)))
+        make_bytecode!(
+            bytecode,
+            514,
+            [
+                StackRef1, StackRef1, Diff, StackRef2, StackRef2, Quo, StackRef3, StackRef3, Rem,
+                List3, Return
+            ],
+            [],
+            cx
+        );
+        let list = list![5, 3, 1; cx];
+        root!(list, cx);
+        check_bytecode!(bytecode, [7, 2], list, cx);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // (lambda (x) (if x (foo x) "bar"))
+        make_bytecode!(
+            bytecode,
+            257,
+            [StackRef0, GotoIfNil, 8, 0, Constant0, StackRef1, Call1, Return, Constant1, Return],
+            [crate::core::env::intern("foo", cx), "bar"],
+            cx
+        );
+        let listing = disassemble_byte_code(bytecode.bind(cx).into(), cx).unwrap();
+        let expect = "\
+byte code: 1 required, 0 optional, depth 0
+     0  StackRef0           0
+     1  GotoIfNil           8
+     4  Constant0           0     ; foo
+     5  StackRef1           1
+     6  Call1               1
+     7  Return
+>    8  Constant1           1     ; \"bar\"
+     9  Return
+";
+        assert_eq!(listing, expect);
+
+        make_bytecode!(bytecode, 0, [Goto, 9, 0, Constant5, VarRefN], [], cx);
+        let listing = disassemble_byte_code(bytecode.bind(cx).into(), cx).unwrap();
+        let expect = "\
+byte code: 0 required, 0 optional, depth 0
+     0  Goto                9     ; jump out of range
+     3  Constant5           5     ; constant out of range
+     4  VarRefN with a truncated operand
+";
+        assert_eq!(listing, expect);
+        assert!(disassemble_byte_code(cx.add(1), cx).is_err());
+    }
+
+    /// Check that `op` gives `expect` when run on `args`, which are pushed on
+    /// the stack in order.
+    macro_rules! check_op { (
+        $op:expr,
+        [$($args:expr),* $(,)?],
+        $expect:expr,
+        $cx:ident $(,)?
+    ) => ({
+            let args: Vec<GcObj> = vec![$($cx.add($args)),*];
+            let expect = $cx.add($expect);
+            let count = args.len() as u8;
+            let mut opcodes = vec![OpCode::StackRef0 as u8 + count.saturating_sub(1); args.len()];
+            opcodes.extend([$op as u8, OpCode::Return as u8]);
+            let opcodes = opcodes.into_obj($cx).untag();
+            let constants: &LispVec = Vec::<GcObj>::new().into_obj($cx).untag();
+            let arglist = u64::from(count) + (u64::from(count) << 8);
+            let bytecode =
+                crate::alloc::make_byte_code(arglist, opcodes, constants, 0, None, None, &[], $cx)
+                    .unwrap();
+            root!(bytecode, $cx);
+            root!(args, $cx);
+            root!(expect, $cx);
+            check_bytecode_internal(args, bytecode, expect, $cx);
+        })
+    }
+
+    #[test]
+    fn test_list_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_op!(Nth, [1, list![1, 2, 3; cx]], 2, cx);
+        check_op!(Nthcdr, [2, list![1, 2, 3; cx]], list![3; cx], cx);
+        check_op!(Elt, [list![1, 2, 3; cx], 0], 1, cx);
+        check_op!(Car, [list![1, 2, 3; cx]], 1, cx);
+        check_op!(Cdr, [list![1, 2, 3; cx]], list![2, 3; cx], cx);
+        check_op!(CarSafe, [5], false, cx);
+        check_op!(CdrSafe, [list![1, 2, 3; cx]], list![2, 3; cx], cx);
+        check_op!(Cons, [1, 2], cons!(1, 2; cx), cx);
+        check_op!(List1, [1], list![1; cx], cx);
+        check_op!(List2, [1, 2], list![1, 2; cx], cx);
+        check_op!(List3, [1, 2, 3], list![1, 2, 3; cx], cx);
+        check_op!(List4, [1, 2, 3, 4], list![1, 2, 3, 4; cx], cx);
+        check_op!(Length, [list![1, 2, 3; cx]], 3, cx);
+        check_op!(Length, ["abc"], 3, cx);
+        check_op!(Memq, [2, list![1, 2, 3; cx]], list![2, 3; cx], cx);
+        check_op!(Member, ["b", list!["a", "b"; cx]], list!["b"; cx], cx);
+        check_op!(
+            Assq,
+            [3, list![cons!(1, 2; cx), cons!(3, 4; cx); cx]],
+            cons!(3, 4; cx),
+            cx
+        );
+        check_op!(Nreverse, [list![1, 2, 3; cx]], list![3, 2, 1; cx], cx);
+        check_op!(Setcar, [list![1, 2; cx], 5], 5, cx);
+        check_op!(Setcdr, [list![1, 2; cx], 5], 5, cx);
+        check_op!(Nconc, [list![1; cx], list![2; cx]], list![1, 2; cx], cx);
+        check_op!(Aref, [vec![cx.add(1), cx.add(2)], 1], 2, cx);
+        check_op!(Aset, [vec![cx.add(1), cx.add(2)], 0, 7], 7, cx);
+        check_op!(Aref, ["abc", 2], 'c' as i64, cx);
+    }
+
+    #[test]
+    fn test_predicate_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_op!(Symbolp, [sym::NIL], true, cx);
+        check_op!(Symbolp, [1], false, cx);
+        check_op!(Consp, [cons!(1, 2; cx)], true, cx);
+        check_op!(Consp, [sym::NIL], false, cx);
+        check_op!(Stringp, ["a"], true, cx);
+        check_op!(Stringp, [sym::NIL], false, cx);
+        check_op!(Listp, [sym::NIL], true, cx);
+        check_op!(Listp, [1], false, cx);
+        check_op!(Not, [sym::NIL], true, cx);
+        check_op!(Not, [1], false, cx);
+        check_op!(Eq, [sym::NIL, sym::NIL], true, cx);
+        check_op!(Eq, [1, 2], false, cx);
+        check_op!(Equal, [list![1, "a"; cx], list![1, "a"; cx]], true, cx);
+        check_op!(Equal, ["a", "b"], false, cx);
+        check_op!(Numberp, [1.5], true, cx);
+        check_op!(Numberp, ["1"], false, cx);
+        check_op!(Integerp, [1], true, cx);
+        check_op!(Integerp, [1.5], false, cx);
+    }
+
+    #[test]
+    fn test_arith_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_op!(Sub1, [5], 4, cx);
+        check_op!(Add1, [1.5], 2.5, cx);
+        check_op!(Negate, [3], -3, cx);
+        check_op!(EqlSign, [2, 2.0], true, cx);
+        check_op!(GreaterThan, [3, 2], true, cx);
+        check_op!(LessThan, [3, 2], false, cx);
+        check_op!(LessThanOrEqual, [2, 2], true, cx);
+        check_op!(GreaterThanOrEqual, [1, 2], false, cx);
+        check_op!(Plus, [1, 2], 3, cx);
+        check_op!(Diff, [1, 2], -1, cx);
+        check_op!(Multiply, [3, 4], 12, cx);
+        check_op!(Quo, [7, 2], 3, cx);
+        check_op!(Quo, [7.0, 2], 3.5, cx);
+        check_op!(Rem, [7, 2], 1, cx);
+        check_op!(Max, [1, 3], 3, cx);
+        check_op!(Min, [1, 3], 1, cx);
+    }
+
+    #[test]
+    fn test_string_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_op!(Substring, ["hello", 1, 3], "el", cx);
+        check_op!(Concat2, ["a", "b"], "ab", cx);
+        check_op!(Concat3, ["a", "b", "c"], "abc", cx);
+        check_op!(Concat4, ["a", "b", "c", "d"], "abcd", cx);
+        check_op!(Upcase, ["abc"], "ABC", cx);
+        check_op!(Downcase, ['A' as i64], 'a' as i64, cx);
+        check_op!(StringEqlSign, ["abc", "abc"], true, cx);
+        check_op!(StringLessThan, ["abc", "abd"], true, cx);
+        check_op!(CharSyntax, [' ' as i64], ' ' as i64, cx);
+    }
+
+    #[test]
+    fn test_symbol_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        lazy_static::initialize(&crate::core::env::INTERNED_SYMBOLS);
+        check_op!(Set, [intern("bytecode-test-var", cx), 5], 5, cx);
+        check_op!(SymbolValue, [intern("bytecode-test-var", cx)], false, cx);
+        check_op!(Get, [sym::ERROR, sym::ERROR_MESSAGE], "error", cx);
+        check_op!(
+            Fset,
+            [intern("bytecode-test-func", cx), sym::CAR],
+            intern("bytecode-test-func", cx),
+            cx
+        );
+        check_op!(
+            SymbolFunction,
+            [intern("bytecode-test-func", cx)],
+            sym::CAR,
+            cx
+        );
+
+        // (lambda () (set 'bytecode-test-var 5) (symbol-value 'bytecode-test-var))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Constant1,
+                Set,
+                Discard,
+                Constant0,
+                SymbolValue,
+                Return
+            ],
+            [intern("bytecode-test-var", cx), 5],
+            cx
+        );
+        check_bytecode!(bytecode, [], 5, cx);
+    }
+
+    #[test]
+    fn test_call_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        lazy_static::initialize(&crate::core::env::INTERNED_SYMBOLS);
+
+        // (lambda (f) (funcall f 3))
+        make_bytecode!(
+            bytecode,
+            257,
+            [StackRef0, Constant0, Call1, Return],
+            [3],
+            cx
+        );
+        check_bytecode!(bytecode, [sym::ADD_ONE], 4, cx);
+        // (closure (t) (x) (* x 3))
+        let x = intern("x", cx);
+        let body = list![sym::MUL, x, 3; cx];
+        let closure = list![sym::CLOSURE, list![true; cx], list![x; cx], body; cx];
+        root!(closure, cx);
+        check_bytecode!(bytecode, [closure], 9, cx);
+
+        // (lambda () (funcall #[(x) ...] 3))
+        make_bytecode!(callee, 257, [Duplicate, Plus, Return], [], cx);
+        make_bytecode!(
+            bytecode,
+            0,
+            [Constant0, Constant1, Call1, Return],
+            [callee.bind(cx), 3],
+            cx
+        );
+        check_bytecode!(bytecode, [], 6, cx);
+
+        // (lambda () (funcall (symbol-function '+) 1 2))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                SymbolFunction,
+                Constant1,
+                Constant2,
+                Call2,
+                Return
+            ],
+            [sym::ADD, 1, 2],
+            cx
+        );
+        check_bytecode!(bytecode, [], 3, cx);
+
+        // (lambda () (list (+) (+ 1) (+ 1 2 3 4) (+ 1 2 3 4 5)))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0, Call0, Constant0, Constant1, Call1, Constant0, Constant1, Constant2,
+                Constant3, Constant4, Call4, Constant0, Constant1, Constant2, Constant3, Constant4,
+                Constant5, Call5, List4, Return
+            ],
+            [sym::ADD, 1, 2, 3, 4, 5],
+            cx
+        );
+        let list = list![0, 1, 10, 15; cx];
+        root!(list, cx);
+        check_bytecode!(bytecode, [], list, cx);
+
+        // (lambda (f) (condition-case nil (funcall f) (invalid-function 7)))
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Constant0,
+                PushCondtionCase,
+                0x08,
+                0x00,
+                StackRef0,
+                Call0,
+                PopHandler,
+                Return,
+                Discard,
+                Constant1,
+                Return
+            ],
+            [sym::INVALID_FUNCTION, 7],
+            cx
+        );
+        check_bytecode!(bytecode, [5], 7, cx);
+        check_bytecode!(bytecode, ["foo"], 7, cx);
+        root!(env, Env::default(), cx);
+        root!(args, move(vec![nil()]), cx);
+        assert!(call(bytecode, args, env, cx).is_err());
+    }
+
+    #[test]
+    fn test_throw_catch() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+
+        // (lambda (tag) (catch 1 (throw tag 2)))
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Constant0, PushCatch, 0x09, 0x0, Constant1, StackRef1, Constant2, Call2,
+                PopHandler, Return
+            ],
+            [1, sym::THROW, 2],
+            cx
+        );
+        check_bytecode!(bytecode, [1], 2, cx);
+        root!(env, Env::default(), cx);
+        root!(args, move(vec![cx.add(3)]), cx);
+        assert!(call(bytecode, args, env, cx).is_err());
+        assert!(env.catch_stack.is_empty());
+
+        // (lambda () (catch 1 (condition-case nil (throw 1 2) (error 3))))
+        let err = list![sym::ERROR; cx];
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                PushCatch,
+                0x0E,
+                0x0,
+                Constant1,
+                PushCondtionCase,
+                0x0F,
+                0x0,
+                Constant2,
+                Constant0,
+                Constant3,
+                Call2,
+                PopHandler,
+                PopHandler,
+                Return,
+                Discard,
+                Constant4,
+                PopHandler,
+                Return
+            ],
+            [1, err, sym::THROW, 2, 3],
+            cx
+        );
+        check_bytecode!(bytecode, [], 2, cx);
+    }
+
+    #[test]
+    fn test_unwind_protect() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let var = crate::core::env::intern("unwind-test-var", cx);
+        root!(var, cx);
+
+        // (lambda () (setq unwind-test-var 5))
+        make_bytecode!(
+            unwind,
+            0,
+            [Constant0, Duplicate, VarSet1, Return],
+            [5, var.bind(cx)],
+            cx
+        );
+        // (lambda (x) (unwind-protect (car x) (setq unwind-test-var 5)))
+        make_bytecode!(
+            bytecode,
+            257,
+            [Constant0, UnwindProtect, StackRef0, Car, Unbind1, Return],
+            [unwind.bind(cx)],
+            cx
+        );
+        root!(args, move(vec![list![1, 2; cx]]), cx);
+        let result = call(bytecode, args, env, cx).unwrap();
+        assert_eq!(result, 1);
+        assert_eq!(env.var(var.bind(cx), cx).unwrap(), 5);
+
+        env.set_var(var.bind(cx), 0.into()).unwrap();
+        root!(args, move(vec![cx.add(5)]), cx);
+        assert!(call(bytecode, args, env, cx).is_err());
+        assert_eq!(env.var(var.bind(cx), cx).unwrap(), 5);
+        assert_eq!(env.binding_depth(), 0);
+
+        // (lambda () (catch 1 (unwind-protect (throw 1 2) (setq unwind-test-var 6))))
+        // with the handler as a list of forms, like in dynamic binding code
+        let handler = list![list![sym::SETQ, var.bind(cx), 6; cx]; cx];
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                PushCatch,
+                0x0C,
+                0x0,
+                Constant1,
+                UnwindProtect,
+                Constant2,
+                Constant0,
+                Constant3,
+                Call2,
+                Unbind1,
+                PopHandler,
+                Return
+            ],
+            [1, handler, sym::THROW, 2],
+            cx
+        );
+        root!(args, Vec::new(), cx);
+        let result = call(bytecode, args, env, cx).unwrap();
+        assert_eq!(result, 2);
+        assert_eq!(env.var(var.bind(cx), cx).unwrap(), 6);
+        assert_eq!(env.binding_depth(), 0);
+    }
+
+    #[test]
+    fn test_buffer_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // (lambda (x)
+        //   (insert x)
+        //   (goto-char 2)
+        //   (forward-char)
+        //   (insert "-")
+        //   (buffer-substring (point-min) (point-max)))
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Duplicate,
+                Insert,
+                Discard,
+                Constant0,
+                GotoChar,
+                Discard,
+                Constant1,
+                ForwardChar,
+                Discard,
+                Constant2,
+                Insert,
+                Discard,
+                PointMin,
+                PointMax,
+                BufferSubstring,
+                Return
+            ],
+            [2, false, "-"],
+            cx
+        );
+        check_bytecode!(bytecode, ["abc"], "ab-c", cx);
+
+        // (lambda () (insert "foo\nbar") (forward-line -1) (end-of-line) (point))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Insert,
+                Discard,
+                Constant1,
+                ForwardLine,
+                Discard,
+                Constant2,
+                EndOfLine,
+                Discard,
+                Point,
+                Return
+            ],
+            ["foo\nbar", -1, false],
+            cx
+        );
+        check_bytecode!(bytecode, [], 4, cx);
+
+        // (lambda () (insert "hello") (delete-region 1 3) (char-after 1))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Insert,
+                Discard,
+                Constant1,
+                Constant2,
+                DeleteRegion,
+                Discard,
+                Constant1,
+                CharAfter,
+                Return
+            ],
+            ["hello", 1, 3],
+            cx
+        );
+        check_bytecode!(bytecode, [], 'l' as i64, cx);
+
+        // (lambda () (insert "a" "b" "c") (skip-chars-backward "bc") (eobp))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Constant1,
+                Constant2,
+                InsertN,
+                3,
+                Discard,
+                Constant3,
+                Constant4,
+                SkipCharsBackward,
+                Discard,
+                EndOfBufferP,
+                Return
+            ],
+            ["a", "b", "c", "bc", false],
+            cx
+        );
+        check_bytecode!(bytecode, [], false, cx);
+    }
+
+    #[test]
+    fn test_markers() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // (lambda (m)
+        //   (insert "abc")
+        //   (set-marker m 2)
+        //   (goto-char 1)
+        //   (insert "x")
+        //   (goto-char m)
+        //   (point))
+        make_bytecode!(
+            bytecode,
+            257,
+            [
+                Constant0, Insert, Discard, Duplicate, Constant1, Constant2, SetMarker, Discard,
+                Constant3, GotoChar, Discard, Constant4, Insert, Discard, Duplicate, GotoChar,
+                Discard, Point, Return
+            ],
+            ["abc", 2, false, 1, "x"],
+            cx
+        );
+        check_bytecode!(bytecode, [crate::core::object::LispMarker::new()], 3, cx);
+    }
+
+    #[test]
+    fn test_save_restriction() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // (lambda ()
+        //   (insert "hello world")
+        //   (narrow-to-region 3 8)
+        //   (save-restriction
+        //     (widen)
+        //     (goto-char 1)
+        //     (insert "ab"))
+        //   (buffer-substring (point-min) (point-max)))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Insert,
+                Discard,
+                Constant1,
+                Constant2,
+                NarrowToRegion,
+                Discard,
+                SaveRestriction,
+                Widen,
+                Discard,
+                Constant3,
+                GotoChar,
+                Discard,
+                Constant4,
+                Insert,
+                Discard,
+                Unbind1,
+                PointMin,
+                PointMax,
+                BufferSubstring,
+                Return
+            ],
+            ["hello world", 3, 8, 1, "ab"],
+            cx
+        );
+        check_bytecode!(bytecode, [], "llo w", cx);
+    }
+
+    #[test]
+    fn test_save_excursion() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let err = cons!(sym::ERROR; cx);
+        // (lambda ()
+        //   (insert "hello")
+        //   (condition-case nil
+        //       (save-excursion (goto-char 2) (forward-char -5))
+        //     (error nil))
+        //   (point))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0,
+                Insert,
+                Discard,
+                Constant1,
+                PushCondtionCase,
+                0x0F,
+                0x0,
+                SaveExcursion,
+                Constant2,
+                GotoChar,
+                Discard,
+                Constant3,
+                ForwardChar,
+                Unbind1,
+                PopHandler,
+                Discard,
+                Point,
+                Return
+            ],
+            ["hello", err, 2, -5],
+            cx
+        );
+        check_bytecode!(bytecode, [], 6, cx);
+
+        // (lambda ()
+        //   (save-current-buffer (set-buffer (get-buffer-create "foo")))
+        //   (buffer-name))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                SaveCurrentBuffer1,
+                Constant0,
+                Constant1,
+                Call1,
+                SetBuffer,
+                Discard,
+                Unbind1,
+                Constant2,
+                Call0,
+                Return
+            ],
+            [sym::GET_BUFFER_CREATE, "foo", sym::BUFFER_NAME],
+            cx
+        );
+        check_bytecode!(bytecode, [], "*scratch*", cx);
     }
 }
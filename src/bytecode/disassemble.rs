@@ -0,0 +1,198 @@
+//! A disassembler for byte-code functions.
+use super::opcode::OpCode;
+use crate::core::object::ByteFn;
+use bstr::ByteSlice;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// The operand of an instruction, decoded by what it refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operand {
+    /// The instruction has no operand.
+    None,
+    /// An index into the constant vector.
+    Constant(u16),
+    /// The offset in the code of the instruction to jump to.
+    Jump(u16),
+    /// Any other number, like a stack index or an argument count.
+    Number(u16),
+}
+
+/// A decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// The offset of the opcode in the code.
+    pub(crate) offset: usize,
+    pub(crate) op: OpCode,
+    pub(crate) operand: Operand,
+}
+
+/// Decode the operand of `op` from the bytes that follow it. Returns the
+/// operand and the number of bytes it took, or `None` if the code ends before
+/// the operand does. Opcodes like `VarRef3` have the operand as part of the
+/// opcode, and take no bytes.
+fn decode_operand(op: OpCode, rest: &[u8]) -> Option<(Operand, usize)> {
+    use OpCode as op;
+    let byte = || rest.first().map(|&x| u16::from(x));
+    let word = || rest.get(..2).map(|x| u16::from_le_bytes([x[0], x[1]]));
+    let code = op as u8;
+    let implicit = |first: OpCode, count: u8| {
+        let idx = code.checked_sub(first as u8).filter(|&x| x < count);
+        idx.map(u16::from)
+    };
+    let operand = match op {
+        op::StackRefN
+        | op::StackSetN
+        | op::CallN
+        | op::UnbindN
+        | op::ListN
+        | op::ConcatN
+        | op::InsertN
+        | op::DiscardN => (Operand::Number(byte()?), 1),
+        op::StackRefN2 | op::StackSetN2 | op::CallN2 | op::UnbindN2 => {
+            (Operand::Number(word()?), 2)
+        }
+        op::VarRefN | op::VarSetN | op::VarBindN => (Operand::Constant(byte()?), 1),
+        op::VarRefN2 | op::VarSetN2 | op::VarBindN2 | op::ConstantN2 => {
+            (Operand::Constant(word()?), 2)
+        }
+        op::Goto
+        | op::GotoIfNil
+        | op::GotoIfNonNil
+        | op::GotoIfNilElsePop
+        | op::GotoIfNonNilElsePop
+        | op::PushCondtionCase
+        | op::PushCatch => (Operand::Jump(word()?), 2),
+        _ => {
+            let number = implicit(op::StackRef0, 6)
+                .or_else(|| implicit(op::Call0, 6))
+                .or_else(|| implicit(op::Unbind0, 6));
+            let constant = implicit(op::VarRef0, 6)
+                .or_else(|| implicit(op::VarSet0, 6))
+                .or_else(|| implicit(op::VarBind0, 6))
+                .or_else(|| implicit(op::Constant0, 64));
+            match (number, constant) {
+                (Some(x), _) => (Operand::Number(x), 0),
+                (_, Some(x)) => (Operand::Constant(x), 0),
+                _ => (Operand::None, 0),
+            }
+        }
+    };
+    Some(operand)
+}
+
+/// Decode the instructions in `code`. Decoding stops at a byte that is not an
+/// opcode or at an operand that runs past the end of the code, and then the
+/// offset where it stopped is returned as the error along with the
+/// instructions before it.
+pub(crate) fn decode(code: &[u8]) -> (Vec<Instruction>, Result<(), usize>) {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let Ok(op) = OpCode::try_from(code[offset]) else {
+            return (instructions, Err(offset));
+        };
+        let Some((operand, len)) = decode_operand(op, &code[offset + 1..]) else {
+            return (instructions, Err(offset));
+        };
+        instructions.push(Instruction { offset, op, operand });
+        offset += 1 + len;
+    }
+    (instructions, Ok(()))
+}
+
+/// Disassemble `func` into a listing with one instruction per line, giving its
+/// offset, opcode and operand. Constants are printed after the instructions
+/// that refer to them, and the instructions that are jumped to are marked with
+/// a `>`.
+pub(crate) fn disassemble(func: &ByteFn) -> String {
+    let code = func.codes().as_bytes();
+    let constants = func.constants();
+    let (instructions, result) = decode(code);
+    let targets: HashSet<usize> = instructions
+        .iter()
+        .filter_map(|x| match x.operand {
+            Operand::Jump(offset) => Some(offset.into()),
+            _ => None,
+        })
+        .collect();
+
+    let args = &func.args;
+    let rest = if args.rest { ", &rest" } else { "" };
+    let mut out = format!(
+        "byte code: {} required, {} optional{rest}, depth {}\n",
+        args.required, args.optional, func.depth
+    );
+    for inst in &instructions {
+        let mark = if targets.contains(&inst.offset) { '>' } else { ' ' };
+        let line = format!("{mark}{:>5}  {:?}", inst.offset, inst.op);
+        let _ = match inst.operand {
+            Operand::None => writeln!(out, "{line}"),
+            Operand::Number(x) if matches!(inst.op, OpCode::DiscardN) && x & 0x80 != 0 => {
+                writeln!(out, "{line:<28}{x:<6}; keep the top of the stack")
+            }
+            Operand::Constant(x) => match constants.get(usize::from(x)) {
+                Some(obj) => writeln!(out, "{line:<28}{x:<6}; {}", obj.get()),
+                None => writeln!(out, "{line:<28}{x:<6}; constant out of range"),
+            },
+            Operand::Jump(x) if usize::from(x) >= code.len() => {
+                writeln!(out, "{line:<28}{x:<6}; jump out of range")
+            }
+            Operand::Number(x) | Operand::Jump(x) => writeln!(out, "{line:<28}{x}"),
+        };
+    }
+    if let Err(offset) = result {
+        let _ = match OpCode::try_from(code[offset]) {
+            Ok(op) => writeln!(out, " {offset:>5}  {op:?} with a truncated operand"),
+            Err(_) => writeln!(out, " {offset:>5}  invalid opcode {}", code[offset]),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        use OpCode::*;
+        let code = [
+            StackRef2 as u8,
+            VarRefN as u8,
+            7,
+            GotoIfNil as u8,
+            9,
+            0,
+            Call1 as u8,
+            Constant3 as u8,
+            Discard as u8,
+            Return as u8,
+        ];
+        let (instructions, result) = decode(&code);
+        assert_eq!(result, Ok(()));
+        let decoded: Vec<_> = instructions
+            .iter()
+            .map(|x| (x.offset, x.op, x.operand))
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                (0, StackRef2, Operand::Number(2)),
+                (1, VarRefN, Operand::Constant(7)),
+                (3, GotoIfNil, Operand::Jump(9)),
+                (6, Call1, Operand::Number(1)),
+                (7, Constant3, Operand::Constant(3)),
+                (8, Discard, Operand::None),
+                (9, Return, Operand::None),
+            ]
+        );
+
+        let (instructions, result) = decode(&[Duplicate as u8, 52, Return as u8]);
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(result, Err(1));
+        let (instructions, result) = decode(&[Goto as u8, 0]);
+        assert_eq!(instructions, Vec::new());
+        assert_eq!(result, Err(0));
+    }
+}
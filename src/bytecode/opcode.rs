@@ -1,7 +1,7 @@
 use num_enum::TryFromPrimitive;
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug, TryFromPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive)]
 #[repr(u8)]
 pub(crate) enum OpCode {
     StackRef0 = 0,
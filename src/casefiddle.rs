@@ -0,0 +1,67 @@
+//! Case conversion.
+use crate::core::{
+    error::{Type, TypeError},
+    gc::Context,
+    object::{GcObj, Object},
+};
+use anyhow::Result;
+use fn_macros::defun;
+
+/// Convert `obj`, which is a character or a string, with `convert`. A character
+/// is left as it is if it does not convert to exactly one character, like `ß`
+/// which is uppercased to `SS`, while strings can change length.
+fn casify<'ob>(
+    obj: GcObj,
+    convert: fn(&str) -> String,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    match obj.untag() {
+        Object::Int(x) => {
+            let Some(chr) = u32::try_from(x).ok().and_then(char::from_u32) else {
+                return Ok(cx.add(x));
+            };
+            let converted = convert(chr.encode_utf8(&mut [0; 4]));
+            let mut chars = converted.chars();
+            match (chars.next(), chars.next()) {
+                (Some(new), None) => Ok(cx.add(new as i64)),
+                _ => Ok(cx.add(x)),
+            }
+        }
+        Object::String(string) => {
+            let string: &str = string.try_into()?;
+            Ok(cx.add(convert(string)))
+        }
+        _ => Err(TypeError::new(Type::CharOrString, obj).into()),
+    }
+}
+
+/// Convert `obj` to upper case. A string is returned as a new string.
+#[defun]
+pub(crate) fn upcase<'ob>(obj: GcObj, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    casify(obj, str::to_uppercase, cx)
+}
+
+/// Convert `obj` to lower case. A string is returned as a new string.
+#[defun]
+pub(crate) fn downcase<'ob>(obj: GcObj, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    casify(obj, str::to_lowercase, cx)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+
+    #[test]
+    fn test_case() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        assert_eq!(upcase(cx.add('a' as i64), cx).unwrap(), 'A' as i64);
+        assert_eq!(downcase(cx.add('Λ' as i64), cx).unwrap(), 'λ' as i64);
+        assert_eq!(upcase(cx.add('ß' as i64), cx).unwrap(), 'ß' as i64);
+        assert_eq!(upcase(cx.add(-1), cx).unwrap(), -1);
+        assert_eq!(upcase(cx.add("straße"), cx).unwrap(), "STRASSE");
+        assert_eq!(downcase(cx.add("Foo Bar"), cx).unwrap(), "foo bar");
+        assert!(upcase(cx.add(1.5), cx).is_err());
+    }
+}
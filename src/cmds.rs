@@ -0,0 +1,136 @@
+use crate::core::{
+    env::Env,
+    gc::{Context, Rt},
+    object::{nil, Buffer, GcObj},
+};
+use anyhow::{bail, Result};
+use fn_macros::defun;
+
+/// Move point `n` lines forward (backward if `n` is negative) to the start of
+/// a line, and return the number of lines that could not be moved. This
+/// follows the conventions of `forward-line`.
+pub(crate) fn forward_line_internal(text: &mut Buffer, n: i64) -> Result<i64> {
+    let opoint = text.point();
+    let line = text.lines.line_at(opoint) as i64;
+    if n > 0 {
+        // the newline that ends the line we are moving to the end of
+        let target = usize::try_from(line + n - 1).unwrap();
+        if let Some(newline) = text.lines.newline(target) {
+            if newline < text.zv() {
+                text.set_point(newline + 1)?;
+                return Ok(0);
+            }
+        }
+        let moved = text.lines.line_at(text.zv()) as i64 - line;
+        let zv = text.zv();
+        text.set_point(zv)?;
+        let mut remaining = n - moved;
+        // a partial line at the end of the buffer counts as a line moved
+        if remaining > 0 && zv != opoint && text.char_at(zv - 1) != Some('\n') {
+            remaining -= 1;
+        }
+        Ok(remaining)
+    } else {
+        // the newline before the start of the line we are moving to
+        if let Ok(target) = usize::try_from(line + n - 1) {
+            if let Some(newline) = text.lines.newline(target) {
+                if newline >= text.begv() {
+                    text.set_point(newline + 1)?;
+                    return Ok(0);
+                }
+            }
+        }
+        let moved = line - text.lines.line_at(text.begv()) as i64;
+        text.set_point(text.begv())?;
+        // reaching the beginning of the buffer counts as a line moved
+        Ok(-(-n - moved).max(0))
+    }
+}
+
+/// Return the position of the end of the line that point is on, without
+/// moving point.
+pub(crate) fn end_of_line_pos(text: &Buffer, pos: usize) -> usize {
+    text.line_end(pos).min(text.zv())
+}
+
+#[defun]
+pub(crate) fn forward_char<'ob>(
+    n: Option<i64>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let new_pos = text.point() as i64 + n.unwrap_or(1);
+    let (min, max) = (text.begv() as i64, text.zv() as i64);
+    text.set_point(new_pos.clamp(min, max) as usize)?;
+    if new_pos < min {
+        bail!("Beginning of buffer");
+    } else if new_pos > max {
+        bail!("End of buffer");
+    }
+    Ok(nil())
+}
+
+#[defun]
+fn backward_char<'ob>(n: Option<i64>, env: &mut Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    forward_char(Some(-n.unwrap_or(1)), env, cx)
+}
+
+#[defun]
+pub(crate) fn forward_line(n: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<i64> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    forward_line_internal(&mut buffer.text, n.unwrap_or(1))
+}
+
+#[defun]
+fn beginning_of_line<'ob>(
+    n: Option<i64>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    forward_line_internal(&mut buffer.text, n.unwrap_or(1) - 1)?;
+    Ok(nil())
+}
+
+#[defun]
+pub(crate) fn end_of_line<'ob>(
+    n: Option<i64>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let n = n.unwrap_or(1);
+    if n != 1 {
+        forward_line_internal(text, n - 1)?;
+    }
+    let pos = end_of_line_pos(text, text.point());
+    text.set_point(pos)?;
+    Ok(nil())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_forward_line() {
+        let text = &mut Buffer::new("foo\nbar\nbaz");
+        assert_eq!(forward_line_internal(text, 1).unwrap(), 0);
+        assert_eq!(text.point(), 4);
+        assert_eq!(forward_line_internal(text, 0).unwrap(), 0);
+        assert_eq!(text.point(), 4);
+        assert_eq!(forward_line_internal(text, 5).unwrap(), 3);
+        assert_eq!(text.point(), 11);
+        assert_eq!(forward_line_internal(text, -1).unwrap(), 0);
+        assert_eq!(text.point(), 4);
+        assert_eq!(forward_line_internal(text, -3).unwrap(), -2);
+        assert_eq!(text.point(), 0);
+        text.set_point(6).unwrap();
+        assert_eq!(end_of_line_pos(text, text.point()), 7);
+        assert_eq!(forward_line_internal(text, 0).unwrap(), 0);
+        assert_eq!(text.point(), 4);
+    }
+}
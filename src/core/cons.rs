@@ -1,5 +1,7 @@
 use super::gc::{Block, GcManaged, GcMark, Trace};
-use super::object::{CloneIn, Gc, GcObj, IntoObject, Object, RawObj};
+use super::object::{
+    print_object, CloneIn, Gc, GcObj, IntoObject, Object, PrintOptions, RawObj, TagType,
+};
 use anyhow::{anyhow, Result};
 use std::cell::Cell;
 use std::fmt::{self, Debug, Display, Write};
@@ -95,24 +97,7 @@ impl Trace for Cons {
 
 impl Display for Cons {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_char('(')?;
-        let mut cons = self;
-
-        loop {
-            write!(f, "{}", cons.car())?;
-            match cons.cdr().untag() {
-                Object::Cons(tail) => {
-                    cons = tail;
-                    f.write_char(' ')?;
-                }
-                Object::NIL => break,
-                x => {
-                    write!(f, " . {x}")?;
-                    break;
-                }
-            }
-        }
-        f.write_char(')')
+        print_object(self.tag().into(), PrintOptions::default(), f)
     }
 }
 
@@ -1,15 +1,98 @@
 #![allow(unstable_name_collisions)]
-use super::gc::{Block, Context, Rt};
-use super::object::{CloneIn, Function, Gc, GcObj};
+use super::error::LispError;
+use super::gc::{Block, Context, IntoRoot, Rt, Trace};
+use super::object::{
+    CloneIn, Excursion, Function, Gc, GcObj, LispBuffer, RawObj, Restriction, WithLifetime,
+};
 use crate::hashmap::HashMap;
-use anyhow::{anyhow, Result};
+use anyhow::{bail, ensure, Result};
 use fn_macros::Trace;
 use std::sync::Mutex;
 
 mod symbol;
 pub(crate) use symbol::*;
 
-#[derive(Debug, Default, Trace)]
+/// An entry on the binding stack. Each entry is undone by [`Rt<Env>::unbind`],
+/// so forms like `save-restriction` are unwound in order with `let` bindings.
+#[derive(Debug)]
+pub(crate) enum Binding<'ob> {
+    /// A dynamic variable binding and the value it shadowed
+    Var(Symbol<'ob>, Option<GcObj<'ob>>),
+    /// A binding of a variable that was local to a buffer and the value it
+    /// shadowed in that buffer, if it was not void
+    BufferVar(Symbol<'ob>, &'ob LispBuffer, Option<GcObj<'ob>>),
+    /// The restriction of a buffer saved by `save-restriction`
+    Restriction(&'ob LispBuffer, Restriction),
+    /// The current buffer and its point saved by `save-excursion`
+    Excursion(&'ob LispBuffer, Excursion),
+    /// The current buffer saved by `save-current-buffer`
+    CurrentBuffer(&'ob LispBuffer),
+    /// The handler of an `unwind-protect` in byte-compiled code, which is
+    /// called by the bytecode VM when it unwinds past this entry
+    UnwindProtect(GcObj<'ob>),
+}
+
+impl Trace for Binding<'_> {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        match self {
+            Binding::Var(sym, val) => {
+                sym.trace(stack);
+                val.trace(stack);
+            }
+            Binding::BufferVar(sym, buffer, val) => {
+                sym.trace(stack);
+                buffer.trace(stack);
+                val.trace(stack);
+            }
+            Binding::Restriction(buffer, _)
+            | Binding::Excursion(buffer, _)
+            | Binding::CurrentBuffer(buffer) => buffer.trace(stack),
+            Binding::UnwindProtect(handler) => handler.trace(stack),
+        }
+    }
+}
+
+impl IntoRoot<Binding<'static>> for Binding<'_> {
+    unsafe fn into_root(self) -> Binding<'static> {
+        self.with_lifetime()
+    }
+}
+
+impl<'old, 'new> WithLifetime<'new> for Binding<'old> {
+    type Out = Binding<'new>;
+
+    unsafe fn with_lifetime(self) -> Self::Out {
+        std::mem::transmute::<Binding<'old>, Binding<'new>>(self)
+    }
+}
+
+impl Rt<Binding<'static>> {
+    /// True if this is a `let` binding of the default value of `var`.
+    fn binds_default(&self, var: Symbol) -> bool {
+        // SAFETY: `Rt` is transparent and the reference does not outlive self
+        let binding = unsafe { &*std::ptr::from_ref(self).cast::<Binding<'static>>() };
+        matches!(binding, Binding::Var(sym, _) if *sym == var)
+    }
+
+    fn is_unwind_protect(&self) -> bool {
+        // SAFETY: `Rt` is transparent and the reference does not outlive self
+        let binding = unsafe { &*std::ptr::from_ref(self).cast::<Binding<'static>>() };
+        matches!(binding, Binding::UnwindProtect(_))
+    }
+
+    fn set_unbound_var(&mut self, var: Symbol, value: GcObj) {
+        // SAFETY: `Rt` is transparent and the new value is rooted by the
+        // binding stack that owns this entry.
+        let binding = unsafe { &mut *std::ptr::from_mut(self).cast::<Binding<'static>>() };
+        if let Binding::Var(sym, val @ None) = binding {
+            if *sym == var {
+                *val = Some(unsafe { value.into_root() });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Trace)]
 pub(crate) struct Env {
     pub(crate) vars: HashMap<Symbol<'static>, GcObj<'static>>,
     pub(crate) props: HashMap<Symbol<'static>, Vec<(Symbol<'static>, GcObj<'static>)>>,
@@ -17,18 +100,126 @@ pub(crate) struct Env {
     exception: (GcObj<'static>, GcObj<'static>),
     #[no_trace]
     exception_id: u32,
-    binding_stack: Vec<(Symbol<'static>, Option<GcObj<'static>>)>,
+    binding_stack: Vec<Binding<'static>>,
     pub(crate) match_data: GcObj<'static>,
+    /// All live buffers, in the order returned by `buffer-list`
+    pub(crate) buffers: Vec<&'static LispBuffer>,
+    current_buffer: Option<&'static LispBuffer>,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        let props = INTERNED_SYMBOLS.lock().unwrap().error_props.clone();
+        Self {
+            vars: HashMap::default(),
+            props,
+            catch_stack: Vec::new(),
+            exception: Default::default(),
+            exception_id: 0,
+            binding_stack: Vec::new(),
+            match_data: GcObj::default(),
+            buffers: Vec::new(),
+            current_buffer: None,
+        }
+    }
+}
+
+/// Variables whose value is always stored in each buffer. They are local in
+/// every buffer.
+fn is_per_buffer(sym: Symbol) -> bool {
+    sym == sym::BUFFER_UNDO_LIST
+}
+
+/// The value of `sym` in `buffer`, or `None` if it is not local to the buffer
+/// or its local value is void.
+fn buffer_var<'ob>(buffer: &LispBuffer, sym: Symbol, cx: &'ob Context) -> Option<GcObj<'ob>> {
+    match sym {
+        sym::BUFFER_UNDO_LIST => Some(buffer.undo_list(cx)),
+        _ => buffer.local_var(sym, cx),
+    }
+}
+
+/// Set the value of `sym` in `buffer`, making it local if it was not already.
+fn set_buffer_var(buffer: &LispBuffer, sym: Symbol, value: GcObj) {
+    match sym {
+        sym::BUFFER_UNDO_LIST => buffer.set_undo_list(value),
+        _ => buffer.set_local_var(sym, value),
+    }
+}
+
+pub(crate) fn is_local_var(buffer: &LispBuffer, sym: Symbol) -> bool {
+    buffer.is_live() && (is_per_buffer(sym) || buffer.has_local_var(sym))
 }
 
 impl Rt<Env> {
+    /// The value of the dynamic variable `sym`. If it is local to the current
+    /// buffer, that value is used instead of the default value.
+    pub(crate) fn var<'ob>(&self, sym: Symbol, cx: &'ob Context) -> Option<GcObj<'ob>> {
+        if let Some(buffer) = self.current_buffer.as_ref() {
+            let buffer = buffer.bind(cx);
+            if is_local_var(buffer, sym) {
+                return buffer_var(buffer, sym, cx);
+            }
+        }
+        self.default_value(sym, cx)
+    }
+
+    /// The default value of `sym`, which is seen in buffers where it is not
+    /// local.
+    pub(crate) fn default_value<'ob>(&self, sym: Symbol, cx: &'ob Context) -> Option<GcObj<'ob>> {
+        self.vars.get(sym).map(|x| x.bind(cx))
+    }
+
+    /// Set the value of `sym`. If the variable is local to the current buffer,
+    /// or becomes local when set, only the value in that buffer is changed.
     pub(crate) fn set_var(&mut self, sym: Symbol, value: GcObj) -> Result<()> {
         if sym.is_const() {
-            Err(anyhow!("Attempt to set a constant symbol: {sym}"))
-        } else {
-            self.vars.insert(sym, value);
-            Ok(())
+            bail!(LispError::new(sym::SETTING_CONSTANT, [sym.into()]));
+        }
+        if let Some(buffer) = self.current_buffer.as_ref() {
+            // SAFETY: the buffer is rooted by the env and the reference does
+            // not outlive this call
+            let buffer = unsafe { buffer.bind_unchecked() };
+            // A `let` of the default value is set like a global variable
+            let make_local = sym.is_buffer_local() && !self.let_binds_default(sym);
+            if make_local || is_local_var(buffer, sym) {
+                set_buffer_var(buffer, sym, value);
+                return Ok(());
+            }
+        }
+        self.vars.insert(sym, value);
+        Ok(())
+    }
+
+    pub(crate) fn set_default(&mut self, sym: Symbol, value: GcObj) -> Result<()> {
+        if sym.is_const() {
+            bail!(LispError::new(sym::SETTING_CONSTANT, [sym.into()]));
         }
+        self.vars.insert(sym, value);
+        Ok(())
+    }
+
+    /// Make the value of `sym` void. If the variable is local to the current
+    /// buffer, only the value in that buffer is made void.
+    pub(crate) fn make_unbound(&mut self, sym: Symbol, cx: &Context) -> Result<()> {
+        if sym.is_const() {
+            bail!(LispError::new(sym::SETTING_CONSTANT, [sym.into()]));
+        }
+        if let Some(buffer) = self.current_buffer.as_ref() {
+            let buffer = buffer.bind(cx);
+            if is_local_var(buffer, sym) {
+                ensure!(!is_per_buffer(sym), "Making {sym} void is not supported");
+                buffer.void_local_var(sym);
+                return Ok(());
+            }
+        }
+        self.vars.remove(sym);
+        Ok(())
+    }
+
+    /// True if the default value of `sym` is dynamically bound.
+    fn let_binds_default(&self, sym: Symbol) -> bool {
+        self.binding_stack.iter().any(|x| x.binds_default(sym))
     }
 
     pub(crate) fn set_prop(&mut self, symbol: Symbol, propname: Symbol, value: GcObj) {
@@ -43,6 +234,17 @@ impl Rt<Env> {
         }
     }
 
+    /// The value of the property `propname` of `symbol`, if it has one.
+    pub(crate) fn prop<'ob>(
+        &self,
+        symbol: Symbol,
+        propname: Symbol,
+        cx: &'ob Context,
+    ) -> Option<GcObj<'ob>> {
+        let plist = self.props.get(symbol)?;
+        plist.iter().find(|x| x.0 == propname).map(|x| x.1.bind(cx))
+    }
+
     pub(in crate::core) fn set_exception(&mut self, tag: GcObj, data: GcObj) -> u32 {
         self.exception.0.set(tag);
         self.exception.1.set(data);
@@ -57,33 +259,196 @@ impl Rt<Env> {
         (id == self.exception_id).then_some((&self.exception.0, &self.exception.1))
     }
 
+    /// Dynamically bind `var` to `value`. If the variable is local to the
+    /// current buffer, only the value in that buffer is bound.
     pub(crate) fn varbind(&mut self, var: Symbol, value: GcObj, cx: &Context) {
+        let buffer = match is_per_buffer(var) {
+            true => Some(self.current_buffer(cx)),
+            false => self.current_buffer.as_ref().map(|x| x.bind(cx)),
+        };
+        if let Some(buffer) = buffer {
+            if is_local_var(buffer, var) {
+                let prev_value = buffer_var(buffer, var, cx);
+                self.binding_stack
+                    .push(Binding::BufferVar(var, buffer, prev_value));
+                set_buffer_var(buffer, var, value);
+                return;
+            }
+        }
         let prev_value = self.vars.get(var).map(|x| x.bind(cx));
-        self.binding_stack.push((var, prev_value));
+        self.binding_stack.push(Binding::Var(var, prev_value));
         self.vars.insert(var, value);
     }
 
+    /// Save the restriction of the current buffer on the binding stack. It is
+    /// restored by the matching [`unbind`](Self::unbind).
+    pub(crate) fn save_restriction(&mut self, cx: &Context) -> Result<()> {
+        let buffer = self.current_buffer(cx);
+        let saved = buffer.lock()?.text.save_restriction();
+        self.binding_stack.push(Binding::Restriction(buffer, saved));
+        Ok(())
+    }
+
+    /// Save the current buffer and its point on the binding stack. They are
+    /// restored by the matching [`unbind`](Self::unbind).
+    pub(crate) fn save_excursion(&mut self, cx: &Context) -> Result<()> {
+        let buffer = self.current_buffer(cx);
+        let saved = buffer.lock()?.text.save_excursion();
+        self.binding_stack.push(Binding::Excursion(buffer, saved));
+        Ok(())
+    }
+
+    /// Save the current buffer on the binding stack. It is made current again
+    /// by the matching [`unbind`](Self::unbind).
+    pub(crate) fn save_current_buffer(&mut self, cx: &Context) {
+        let buffer = self.current_buffer(cx);
+        self.binding_stack.push(Binding::CurrentBuffer(buffer));
+    }
+
+    /// Push the handler of an `unwind-protect` on the binding stack. It has to
+    /// be popped with [`pop_unwind_protect`](Self::pop_unwind_protect) and
+    /// called by whoever pushed it, because [`unbind`](Self::unbind) cannot
+    /// call functions.
+    pub(crate) fn push_unwind_protect(&mut self, handler: GcObj) {
+        self.binding_stack.push(Binding::UnwindProtect(handler));
+    }
+
+    /// If the top entry of the binding stack is an `unwind-protect`, pop it and
+    /// return its handler.
+    pub(crate) fn pop_unwind_protect<'ob>(&mut self, cx: &'ob Context) -> Option<GcObj<'ob>> {
+        if !self.binding_stack.last()?.is_unwind_protect() {
+            return None;
+        }
+        match self.binding_stack.pop_obj(cx) {
+            Some(Binding::UnwindProtect(handler)) => Some(handler),
+            _ => unreachable!("top of binding stack was not an unwind-protect"),
+        }
+    }
+
+    /// The number of entries on the binding stack. Pass this to
+    /// [`unbind_to`](Self::unbind_to) to unwind everything bound after it.
+    pub(crate) fn binding_depth(&self) -> usize {
+        self.binding_stack.len()
+    }
+
+    pub(crate) fn unbind_to(&mut self, depth: usize, cx: &Context) {
+        while self.binding_stack.len() > depth {
+            self.unbind(1, cx);
+        }
+    }
+
     pub(crate) fn unbind(&mut self, count: u16, cx: &Context) {
         for _ in 0..count {
             match self.binding_stack.pop_obj(cx) {
-                Some((sym, val)) => match val {
+                Some(Binding::Var(sym, val)) => match val {
                     Some(val) => self.vars.insert(sym, val),
                     None => self.vars.remove(sym),
                 },
+                Some(Binding::BufferVar(sym, buffer, val)) => {
+                    // if the local value was killed there is nothing to restore
+                    if is_local_var(buffer, sym) {
+                        match val {
+                            Some(val) => set_buffer_var(buffer, sym, val),
+                            None => buffer.void_local_var(sym),
+                        }
+                    }
+                }
+                Some(Binding::Restriction(buffer, saved)) => {
+                    // a killed buffer has no restriction left to restore
+                    if let Ok(mut data) = buffer.lock() {
+                        data.text.restore_restriction(saved);
+                    }
+                }
+                Some(Binding::Excursion(buffer, saved)) => {
+                    if let Ok(mut data) = buffer.lock() {
+                        data.text.restore_excursion(saved);
+                        drop(data);
+                        self.current_buffer.set(buffer);
+                    }
+                }
+                Some(Binding::CurrentBuffer(buffer)) => {
+                    // If the saved buffer was killed, leave whatever buffer is
+                    // current
+                    if buffer.is_live() {
+                        self.current_buffer.set(buffer);
+                    }
+                }
+                // The handler is called by the bytecode VM before it gets
+                // here, so there is nothing left to undo
+                Some(Binding::UnwindProtect(_)) => {}
                 None => panic!("Binding stack was empty"),
             }
         }
     }
 
+    /// Return the current buffer. If there is no current buffer, a
+    /// `*scratch*` buffer is created and made current.
+    pub(crate) fn current_buffer<'ob>(&mut self, cx: &'ob Context) -> &'ob LispBuffer {
+        if let Some(buffer) = self.current_buffer.as_ref() {
+            return buffer.bind(cx);
+        }
+        let buffer = self.create_buffer("*scratch*", cx);
+        self.current_buffer.set(buffer);
+        buffer
+    }
+
+    pub(crate) fn set_buffer(&mut self, buffer: &LispBuffer) -> Result<()> {
+        if !buffer.is_live() {
+            bail!("Selecting deleted buffer");
+        }
+        self.current_buffer.set(buffer);
+        Ok(())
+    }
+
+    /// Find a live buffer by name.
+    pub(crate) fn get_buffer<'ob>(
+        &mut self,
+        name: &str,
+        cx: &'ob Context,
+    ) -> Option<&'ob LispBuffer> {
+        // make sure the initial buffer exists
+        self.current_buffer(cx);
+        self.buffers
+            .iter()
+            .map(|x| x.bind(cx))
+            .find(|x| x.name().as_deref() == Some(name))
+    }
+
+    /// Create a new buffer and add it to the buffer list. This does not check
+    /// if a buffer with the same name already exists.
+    pub(crate) fn create_buffer<'ob>(&mut self, name: &str, cx: &'ob Context) -> &'ob LispBuffer {
+        let buffer: &LispBuffer = cx.add_as(LispBuffer::new(name.to_owned())).untag();
+        self.buffers.push(buffer);
+        buffer
+    }
+
+    /// Kill `buffer` and remove it from the buffer list. If it was the current
+    /// buffer, another buffer is made current. Returns false if the buffer was
+    /// already dead.
+    pub(crate) fn kill_buffer(&mut self, buffer: &LispBuffer, cx: &Context) -> bool {
+        if !buffer.kill() {
+            return false;
+        }
+        if let Some(idx) = self.buffers.iter().position(|x| x.bind(cx) == buffer) {
+            self.buffers.remove(idx);
+        }
+        let is_current = matches!(self.current_buffer.as_ref(), Some(x) if x.bind(cx) == buffer);
+        if is_current {
+            match self.buffers.first().map(|x| x.bind(cx)) {
+                Some(next) => self.current_buffer.set(next),
+                None => *self.current_buffer = None,
+            }
+        }
+        true
+    }
+
     pub(crate) fn defvar(&mut self, var: Symbol, value: GcObj) -> Result<()> {
-        self.set_var(var, value)?;
+        self.set_default(var, value)?;
         var.make_special();
         // If this variable was unbound previously in the binding stack,
         // we will bind it to the new value
         for binding in self.binding_stack.iter_mut() {
-            if binding.0 == var && binding.1.is_none() {
-                binding.1.set(value);
-            }
+            binding.set_unbound_var(var, value);
         }
         Ok(())
     }
@@ -92,6 +457,8 @@ impl Rt<Env> {
 pub(crate) struct ObjectMap {
     map: SymbolMap,
     block: Block<true>,
+    /// The properties of the standard errors that every [`Env`] starts with
+    error_props: HashMap<Symbol<'static>, Vec<(Symbol<'static>, GcObj<'static>)>>,
 }
 
 /// Box is marked as unique. However we are freely sharing the pointer to this
@@ -30,6 +30,8 @@ pub(crate) struct SymbolCell {
     // https://github.com/crossbeam-rs/crossbeam/issues/748
     func: Option<AtomicPtr<u8>>,
     special: AtomicBool,
+    /// Setting the variable makes it local to the current buffer
+    local_if_set: AtomicBool,
 }
 
 #[derive(Debug)]
@@ -109,6 +111,15 @@ impl<'a> Symbol<'a> {
     pub(crate) fn is_special(self) -> bool {
         self.special.load(Ordering::Acquire)
     }
+
+    /// Make the variable automatically buffer-local when it is set.
+    pub(crate) fn make_buffer_local(self) {
+        self.local_if_set.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_buffer_local(self) -> bool {
+        self.local_if_set.load(Ordering::Acquire)
+    }
 }
 
 unsafe impl Send for Symbol<'_> {}
@@ -209,6 +220,7 @@ impl SymbolCell {
                 func: Some(Self::EMTPTY),
                 marked: AtomicBool::new(true),
                 special: AtomicBool::new(false),
+                local_if_set: AtomicBool::new(false),
             }
         }
     }
@@ -219,6 +231,7 @@ impl SymbolCell {
             func: Some(Self::EMTPTY),
             marked: AtomicBool::new(true),
             special: AtomicBool::new(true),
+            local_if_set: AtomicBool::new(false),
         }
     }
 
@@ -228,6 +241,7 @@ impl SymbolCell {
             func: None,
             marked: AtomicBool::new(true),
             special: AtomicBool::new(true),
+            local_if_set: AtomicBool::new(false),
         }
     }
 
@@ -237,6 +251,7 @@ impl SymbolCell {
             func: Some(Self::EMTPTY),
             marked: AtomicBool::new(false),
             special: AtomicBool::new(false),
+            local_if_set: AtomicBool::new(false),
         }
     }
 
@@ -1,15 +1,32 @@
+use bstr::ByteSlice;
+use std::cell::RefCell;
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use super::{
-    env::Env,
-    gc::Rt,
-    object::{display_slice, GcObj},
+    env::{intern, sym, Env, Symbol},
+    gc::{Block, Context, Rt},
+    object::{
+        display_slice, nil, print_to_string, GcObj, LispString, Object, PrintOptions, RawObj,
+        TagType, WithLifetime,
+    },
 };
+use crate::hashmap::HashMap;
+use crate::reader;
 
 #[derive(Debug)]
 pub(crate) struct EvalError {
     backtrace: Vec<String>,
     pub(crate) error: ErrorType,
+    /// The addresses of the conses of the forms that were being evaluated when
+    /// the error happened, innermost first. These are used to find where the
+    /// error is in the source.
+    forms: Vec<usize>,
+    /// Where the error is in the source, as `FILE:LINE:COLUMN`.
+    location: Option<String>,
+    /// The message of a signal. It is formatted when the signal is raised,
+    /// because its data is only kept in the environment.
+    message: String,
 }
 
 #[derive(Debug)]
@@ -23,10 +40,13 @@ impl std::error::Error for EvalError {}
 
 impl Display for EvalError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{location}: ")?;
+        }
         match &self.error {
             ErrorType::Err(e) => writeln!(f, "{e}")?,
             ErrorType::Throw(_) => writeln!(f, "No catch for throw")?,
-            ErrorType::Signal(_) => writeln!(f, "Signal")?,
+            ErrorType::Signal(_) => writeln!(f, "{}", self.message)?,
         }
         for x in &self.backtrace {
             writeln!(f, "{x}")?;
@@ -40,13 +60,24 @@ impl EvalError {
     pub(crate) fn new_error(error: anyhow::Error) -> Self {
         Self {
             backtrace: Vec::new(),
+            forms: Vec::new(),
+            location: None,
+            message: String::new(),
             error: ErrorType::Err(error),
         }
     }
 
-    pub(crate) fn signal(error_symbol: GcObj, data: GcObj, env: &mut Rt<Env>) -> Self {
+    pub(crate) fn signal(
+        error_symbol: GcObj,
+        data: GcObj,
+        env: &mut Rt<Env>,
+        cx: &Context,
+    ) -> Self {
         Self {
             backtrace: Vec::new(),
+            forms: Vec::new(),
+            location: None,
+            message: error_message(error_symbol, data, env, cx),
             error: ErrorType::Signal(env.set_exception(error_symbol, data)),
         }
     }
@@ -54,6 +85,9 @@ impl EvalError {
     pub(crate) fn throw(tag: GcObj, data: GcObj, env: &mut Rt<Env>) -> Self {
         Self {
             backtrace: Vec::new(),
+            forms: Vec::new(),
+            location: None,
+            message: String::new(),
             error: ErrorType::Throw(env.set_exception(tag, data)),
         }
     }
@@ -67,6 +101,44 @@ impl EvalError {
         Self {
             backtrace: vec![format!("{name} {display}")],
             error: ErrorType::Err(error),
+            forms: Vec::new(),
+            location: None,
+            message: String::new(),
+        }
+    }
+
+    /// Signal an error raised from Rust as the Lisp error it stands for. This
+    /// is done where the error leaves the function or form that raised it.
+    /// After that its data is rooted in `env` like any signal.
+    pub(crate) fn into_signal(self, env: &mut Rt<Env>, cx: &Context) -> Self {
+        let ErrorType::Err(error) = &self.error else {
+            return self;
+        };
+        let Some((symbol, data)) = lisp_error_data(error, cx) else {
+            return self;
+        };
+        Self {
+            message: error_message(symbol, data, env, cx),
+            error: ErrorType::Signal(env.set_exception(symbol, data)),
+            ..self
+        }
+    }
+
+    /// The error symbol and data that this error is signaled to Lisp as, or
+    /// `None` if it is a `throw`. Errors that do not stand for a Lisp error
+    /// are signaled as `error` with their message.
+    pub(crate) fn signal_data<'ob>(
+        &self,
+        env: &Rt<Env>,
+        cx: &'ob Context,
+    ) -> Option<(GcObj<'ob>, GcObj<'ob>)> {
+        match &self.error {
+            ErrorType::Throw(_) => None,
+            ErrorType::Signal(id) => {
+                let (symbol, data) = env.get_exception(*id)?;
+                Some((symbol.bind(cx), data.bind(cx)))
+            }
+            ErrorType::Err(error) => Some((sym::ERROR.into(), list![format!("{error}"); cx])),
         }
     }
 
@@ -75,11 +147,289 @@ impl EvalError {
         self.backtrace.push(format!("{name} {display}"));
         self
     }
+
+    /// Record that the error happened while evaluating the form whose cons is
+    /// at address `form`.
+    pub(crate) fn add_form(mut self, form: usize) -> Self {
+        self.forms.push(form);
+        self
+    }
+
+    /// The addresses of the conses of the forms that were being evaluated,
+    /// innermost first.
+    pub(crate) fn forms(&self) -> &[usize] {
+        &self.forms
+    }
+
+    pub(crate) fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    pub(crate) fn set_location(&mut self, location: String) {
+        self.location = Some(location);
+    }
+}
+
+/// The standard errors, with their message and the error they are a kind of.
+/// Parents come before their children. `quit` is the only one that is not a
+/// kind of `error`.
+pub(crate) const STANDARD_ERRORS: &[(Symbol, &str, Option<Symbol>)] = &[
+    (sym::ERROR, "error", None),
+    (sym::QUIT, "Quit", None),
+    (sym::USER_ERROR, "", Some(sym::ERROR)),
+    (
+        sym::ARGS_OUT_OF_RANGE,
+        "Args out of range",
+        Some(sym::ERROR),
+    ),
+    (sym::ARITH_ERROR, "Arithmetic error", Some(sym::ERROR)),
+    (
+        sym::DOMAIN_ERROR,
+        "Arithmetic domain error",
+        Some(sym::ARITH_ERROR),
+    ),
+    (
+        sym::RANGE_ERROR,
+        "Arithmetic range error",
+        Some(sym::ARITH_ERROR),
+    ),
+    (
+        sym::SINGULARITY_ERROR,
+        "Arithmetic singularity error",
+        Some(sym::DOMAIN_ERROR),
+    ),
+    (
+        sym::OVERFLOW_ERROR,
+        "Arithmetic overflow error",
+        Some(sym::RANGE_ERROR),
+    ),
+    (
+        sym::UNDERFLOW_ERROR,
+        "Arithmetic underflow error",
+        Some(sym::RANGE_ERROR),
+    ),
+    (
+        sym::BEGINNING_OF_BUFFER,
+        "Beginning of buffer",
+        Some(sym::ERROR),
+    ),
+    (sym::END_OF_BUFFER, "End of buffer", Some(sym::ERROR)),
+    (
+        sym::BUFFER_READ_ONLY,
+        "Buffer is read-only",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::TEXT_READ_ONLY,
+        "Text is read-only",
+        Some(sym::BUFFER_READ_ONLY),
+    ),
+    (sym::CIRCULAR_LIST, "List contains a loop", Some(sym::ERROR)),
+    (
+        sym::CYCLIC_FUNCTION_INDIRECTION,
+        "Symbol\u{2019}s chain of function indirections contains a loop",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::CYCLIC_VARIABLE_INDIRECTION,
+        "Symbol\u{2019}s chain of variable indirections contains a loop",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::END_OF_FILE,
+        "End of file during parsing",
+        Some(sym::ERROR),
+    ),
+    (sym::INVALID_FUNCTION, "Invalid function", Some(sym::ERROR)),
+    (
+        sym::INVALID_READ_SYNTAX,
+        "Invalid read syntax",
+        Some(sym::ERROR),
+    ),
+    (sym::INVALID_REGEXP, "Invalid regexp", Some(sym::ERROR)),
+    (
+        sym::MARK_INACTIVE,
+        "The mark is not active now",
+        Some(sym::ERROR),
+    ),
+    (sym::NO_CATCH, "No catch for tag", Some(sym::ERROR)),
+    (sym::SCAN_ERROR, "Scan error", Some(sym::ERROR)),
+    (sym::SEARCH_FAILED, "Search failed", Some(sym::ERROR)),
+    (
+        sym::SETTING_CONSTANT,
+        "Attempt to set a constant symbol",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::VOID_FUNCTION,
+        "Symbol\u{2019}s function definition is void",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::VOID_VARIABLE,
+        "Symbol\u{2019}s value as variable is void",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::WRONG_LENGTH_ARGUMENT,
+        "Wrong length argument",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::WRONG_NUMBER_OF_ARGUMENTS,
+        "Wrong number of arguments",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::WRONG_TYPE_ARGUMENT,
+        "Wrong type argument",
+        Some(sym::ERROR),
+    ),
+    (sym::FILE_ERROR, "File error", Some(sym::ERROR)),
+    (
+        sym::FILE_ALREADY_EXISTS,
+        "File already exists",
+        Some(sym::FILE_ERROR),
+    ),
+    (sym::FILE_MISSING, "File is missing", Some(sym::FILE_ERROR)),
+    (
+        sym::PERMISSION_DENIED,
+        "Cannot access file or directory",
+        Some(sym::FILE_ERROR),
+    ),
+];
+
+/// The `error-conditions` and `error-message` properties of the standard
+/// errors, allocated in the global block. Every [`Env`] starts with these.
+pub(crate) fn standard_error_props(
+    block: &Block<true>,
+) -> HashMap<Symbol<'static>, Vec<(Symbol<'static>, GcObj<'static>)>> {
+    let mut props: HashMap<_, Vec<(_, GcObj)>> = HashMap::default();
+    for &(name, message, parent) in STANDARD_ERRORS {
+        let parents = parent
+            .and_then(|x| props.get(&x))
+            .map_or_else(nil, |x| x[0].1);
+        let conditions = crate::cons!(name, parents; block);
+        // SAFETY: The global block is never collected.
+        let conditions = unsafe { conditions.with_lifetime() };
+        let message = unsafe { block.add(message).with_lifetime() };
+        props.insert(
+            name,
+            vec![
+                (sym::ERROR_CONDITIONS, conditions),
+                (sym::ERROR_MESSAGE, message),
+            ],
+        );
+    }
+    props
+}
+
+/// Whether a `condition-case` handler for `condition` handles an error that is
+/// signaled as `symbol`. The condition is `t`, which handles every error, or an
+/// error symbol or list of them, which handle the error if one of them is in
+/// the `error-conditions` of `symbol`.
+pub(crate) fn condition_matches(
+    condition: GcObj,
+    symbol: GcObj,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> bool {
+    let conditions = match symbol.untag() {
+        Object::Symbol(symbol) => env.prop(symbol, sym::ERROR_CONDITIONS, cx),
+        _ => None,
+    };
+    let is_condition = |x: GcObj| match conditions.map(GcObj::untag) {
+        Some(Object::Cons(conditions)) => conditions.elements().flatten().any(|c| c == x),
+        _ => false,
+    };
+    match condition.untag() {
+        Object::Symbol(sym::TRUE) => true,
+        Object::Symbol(_) => is_condition(condition),
+        Object::Cons(list) => list.elements().flatten().any(is_condition),
+        _ => false,
+    }
+}
+
+/// The error symbol and data of an error from Rust that stands for a Lisp
+/// error.
+fn lisp_error_data<'ob>(
+    error: &anyhow::Error,
+    cx: &'ob Context,
+) -> Option<(GcObj<'ob>, GcObj<'ob>)> {
+    if let Some(error) = error.downcast_ref::<LispError>() {
+        return Some((error.symbol.to_obj(cx), error.data(cx)));
+    }
+    if let Some(error) = error.downcast_ref::<TypeError>() {
+        let predicate = intern(error.expect.predicate(), cx);
+        let data = list![predicate, error.value.to_obj(cx); cx];
+        return Some((sym::WRONG_TYPE_ARGUMENT.into(), data));
+    }
+    if let Some(error) = error.downcast_ref::<ArgError>() {
+        let data = list![error.function.to_obj(cx), i64::from(error.actual); cx];
+        return Some((sym::WRONG_NUMBER_OF_ARGUMENTS.into(), data));
+    }
+    if let Some(reader::Error::IntegerOverflow(_)) = error.downcast_ref() {
+        return Some((sym::OVERFLOW_ERROR.into(), nil()));
+    }
+    None
+}
+
+/// The message for the error `symbol` with `data`, formatted like Emacs does.
+/// This is the `error-message` property of `symbol` followed by the elements of
+/// `data`. For `error` itself the first element of `data` is the message
+/// instead, and for file errors the first element replaces the message.
+pub(crate) fn error_message(symbol: GcObj, data: GcObj, env: &Rt<Env>, cx: &Context) -> String {
+    let (mut message, mut items, conditions) = match symbol.untag() {
+        Object::Symbol(sym::ERROR) => match data.untag() {
+            Object::Cons(data) => (Some(data.car()), data.cdr(), None),
+            _ => (None, nil(), None),
+        },
+        Object::Symbol(symbol) => (
+            env.prop(symbol, sym::ERROR_MESSAGE, cx),
+            data,
+            env.prop(symbol, sym::ERROR_CONDITIONS, cx),
+        ),
+        _ => (None, data, None),
+    };
+    let is_file_error = conditions
+        .and_then(|x| x.as_list().ok())
+        .is_some_and(|mut x| x.any(|x| x.is_ok_and(|x| x == sym::FILE_ERROR)));
+    if is_file_error {
+        if let Object::Cons(cons) = items.untag() {
+            message = Some(cons.car());
+            items = cons.cdr();
+        }
+    }
+    // these show their data as text rather than as objects
+    let escape = !(is_file_error || symbol == sym::END_OF_FILE || symbol == sym::USER_ERROR);
+    let options = PrintOptions {
+        escape,
+        quoted: true,
+        ..PrintOptions::default()
+    };
+    let mut string = String::new();
+    let mut separator = ": ";
+    match message.map(GcObj::untag) {
+        Some(Object::String(message)) if message.is_empty() => separator = "",
+        Some(Object::String(message)) => string.push_str(&message.to_str_lossy()),
+        _ => string.push_str("peculiar error"),
+    }
+    while let Object::Cons(cons) = items.untag() {
+        string.push_str(separator);
+        string.push_str(&print_to_string(cons.car(), options));
+        separator = ", ";
+        items = cons.cdr();
+    }
+    string
 }
 
 impl From<anyhow::Error> for EvalError {
     fn from(e: anyhow::Error) -> Self {
-        Self::new_error(e)
+        // keep signals and throws that were wrapped by a builtin function
+        match e.downcast::<EvalError>() {
+            Ok(e) => e,
+            Err(e) => Self::new_error(e),
+        }
     }
 }
 
@@ -101,6 +451,12 @@ impl From<TypeError> for EvalError {
     }
 }
 
+impl From<LispError> for EvalError {
+    fn from(e: LispError) -> Self {
+        Self::new_error(e.into())
+    }
+}
+
 impl From<ArgError> for EvalError {
     fn from(e: ArgError) -> Self {
         Self::new_error(e.into())
@@ -127,11 +483,11 @@ macro_rules! bail_err {
 pub(crate) type EvalResult<'ob> = Result<GcObj<'ob>, EvalError>;
 
 /// The function or form has the wrong number of arguments.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub(crate) struct ArgError {
     expect: u16,
     actual: u16,
-    name: String,
+    function: ErrorData,
 }
 
 impl std::error::Error for ArgError {}
@@ -141,21 +497,23 @@ impl Display for ArgError {
         let Self {
             expect,
             actual,
-            name,
+            function,
         } = self;
         write!(
             f,
-            "Expected {expect} argument(s) for `{name}', but found {actual}"
+            "Expected {expect} argument(s) for `{function}', but found {actual}"
         )
     }
 }
 
 impl ArgError {
-    pub(crate) fn new(expect: u16, actual: u16, name: impl AsRef<str>) -> ArgError {
+    /// An error for calling `function` with `actual` arguments. `function` is
+    /// the function object, or the symbol of a special form.
+    pub(crate) fn new(expect: u16, actual: u16, function: impl Into<ErrorData>) -> ArgError {
         Self {
             expect,
             actual,
-            name: name.as_ref().to_owned(),
+            function: function.into(),
         }
     }
 }
@@ -172,16 +530,51 @@ pub(crate) enum Type {
     Symbol,
     Float,
     Func,
+    ByteFn,
     Number,
     List,
+    Buffer,
+    Marker,
+    Overlay,
+    IntOrMarker,
+    BufferOrString,
+    CharOrString,
+}
+
+impl Type {
+    /// The name of the predicate that is true for this type, which is the
+    /// data of a `wrong-type-argument` error.
+    fn predicate(&self) -> &'static str {
+        match self {
+            Type::Int => "integerp",
+            Type::Cons => "consp",
+            Type::Vec => "vectorp",
+            Type::Record => "recordp",
+            Type::HashTable => "hash-table-p",
+            Type::Sequence => "sequencep",
+            Type::String => "stringp",
+            Type::Symbol => "symbolp",
+            Type::Float => "floatp",
+            Type::Func => "functionp",
+            Type::ByteFn => "byte-code-function-p",
+            Type::Number => "number-or-marker-p",
+            Type::List => "listp",
+            Type::Buffer => "bufferp",
+            Type::Marker => "markerp",
+            Type::Overlay => "overlayp",
+            Type::IntOrMarker => "integer-or-marker-p",
+            Type::BufferOrString => "buffer-or-string-p",
+            Type::CharOrString => "char-or-string-p",
+        }
+    }
 }
 
 /// Error provided if object was the wrong type
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub(crate) struct TypeError {
     expect: Type,
     actual: Type,
-    print: String,
+    value: ErrorData,
 }
 
 impl std::error::Error for TypeError {}
@@ -191,9 +584,9 @@ impl Display for TypeError {
         let Self {
             expect,
             actual,
-            print,
+            value,
         } = self;
-        write!(f, "expected {expect:?}, found {actual:?}: {print}")
+        write!(f, "expected {expect:?}, found {actual:?}: {value}")
     }
 }
 
@@ -201,13 +594,209 @@ impl TypeError {
     /// Get a type error from an object.
     pub(crate) fn new<'ob, T>(expect: Type, obj: T) -> Self
     where
-        T: Into<super::object::Object<'ob>>,
+        T: Into<Object<'ob>>,
     {
         let obj = obj.into();
         Self {
             expect,
             actual: obj.get_type(),
-            print: obj.to_string(),
+            value: obj.into(),
+        }
+    }
+}
+
+thread_local! {
+    /// The objects in the data of errors that have not been signaled yet, by
+    /// the id of the [`ErrorObject`] that holds them.
+    static ERROR_OBJECTS: RefCell<HashMap<u64, GcObj<'static>>> = RefCell::default();
+}
+
+static NEXT_ERROR_OBJECT: AtomicU64 = AtomicU64::new(0);
+
+/// Add the objects held by errors that have not been signaled yet to the gray
+/// stack of the garbage collector. They are roots until the error is dropped.
+pub(crate) fn trace_error_objects(stack: &mut Vec<RawObj>) {
+    ERROR_OBJECTS.with(|objects| {
+        let objects = objects.borrow();
+        stack.extend(
+            objects
+                .values()
+                .filter(|x| x.is_markable())
+                .map(|x| x.into_raw()),
+        );
+    });
+}
+
+/// An object in the data of an error that has not been signaled yet. The
+/// object stays rooted until this is dropped, which happens once the error is
+/// turned into a signal with [`EvalError::into_signal`].
+#[derive(Debug)]
+pub(crate) struct ErrorObject(u64);
+
+impl ErrorObject {
+    fn new(obj: GcObj) -> Self {
+        let id = NEXT_ERROR_OBJECT.fetch_add(1, Ordering::Relaxed);
+        let obj = unsafe { obj.with_lifetime() };
+        ERROR_OBJECTS.with(|objects| objects.borrow_mut().insert(id, obj));
+        Self(id)
+    }
+
+    fn get<'ob>(&self, cx: &'ob Context) -> GcObj<'ob> {
+        let obj = ERROR_OBJECTS.with(|objects| objects.borrow().get(&self.0).copied());
+        cx.bind(obj.expect("error objects should be used by the thread that raised them"))
+    }
+}
+
+impl Drop for ErrorObject {
+    fn drop(&mut self) {
+        // The table is already gone if the thread is exiting
+        let _ = ERROR_OBJECTS.try_with(|objects| objects.borrow_mut().remove(&self.0));
+    }
+}
+
+/// An object in the data of an error. It keeps its printed representation to
+/// display the error without a [`Context`].
+#[derive(Debug)]
+pub(crate) enum ErrorData {
+    Object(ErrorObject, String),
+    /// Text from Rust, which becomes a new string.
+    String(String),
+}
+
+impl ErrorData {
+    fn to_obj<'ob>(&self, cx: &'ob Context) -> GcObj<'ob> {
+        match self {
+            ErrorData::Object(obj, _) => obj.get(cx),
+            ErrorData::String(x) => cx.add(x.clone()),
+        }
+    }
+}
+
+impl From<Object<'_>> for ErrorData {
+    fn from(obj: Object) -> Self {
+        ErrorData::Object(ErrorObject::new(obj.tag()), obj.to_string())
+    }
+}
+
+impl From<GcObj<'_>> for ErrorData {
+    fn from(obj: GcObj) -> Self {
+        obj.untag().into()
+    }
+}
+
+impl From<Symbol<'_>> for ErrorData {
+    fn from(symbol: Symbol) -> Self {
+        Object::Symbol(symbol).into()
+    }
+}
+
+impl From<i64> for ErrorData {
+    fn from(x: i64) -> Self {
+        Object::Int(x).into()
+    }
+}
+
+impl From<usize> for ErrorData {
+    fn from(x: usize) -> Self {
+        Object::Int(x as i64).into()
+    }
+}
+
+impl From<&LispString> for ErrorData {
+    fn from(x: &LispString) -> Self {
+        Object::String(x).into()
+    }
+}
+
+impl<T: Into<ErrorData>> From<Option<T>> for ErrorData {
+    fn from(x: Option<T>) -> Self {
+        match x {
+            Some(x) => x.into(),
+            None => Object::NIL.into(),
+        }
+    }
+}
+
+impl From<&str> for ErrorData {
+    fn from(x: &str) -> Self {
+        ErrorData::String(x.to_owned())
+    }
+}
+
+impl Display for ErrorData {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ErrorData::Object(_, print) => write!(f, "{print}"),
+            ErrorData::String(x) => {
+                write!(f, "\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+    }
+}
+
+/// An error that is signaled to Lisp as the error symbol `symbol` with `data`,
+/// like `(signal 'args-out-of-range '(5))`.
+#[derive(Debug)]
+pub(crate) struct LispError {
+    symbol: ErrorData,
+    name: String,
+    data: Vec<ErrorData>,
+}
+
+impl LispError {
+    pub(crate) fn new(symbol: Symbol, data: impl IntoIterator<Item = ErrorData>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            name: symbol.name().to_owned(),
+            data: data.into_iter().collect(),
+        }
+    }
+
+    fn data<'ob>(&self, cx: &'ob Context) -> GcObj<'ob> {
+        let data: Vec<_> = self.data.iter().map(|x| x.to_obj(cx)).collect();
+        data.into_iter()
+            .rev()
+            .fold(nil(), |tail, x| crate::cons!(x, tail; cx))
+    }
+}
+
+impl std::error::Error for LispError {}
+
+impl Display for LispError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let standard = STANDARD_ERRORS.iter().find(|x| x.0.name() == self.name);
+        match standard {
+            Some((_, message, _)) => write!(f, "{message}")?,
+            None => write!(f, "{}", self.name)?,
         }
+        let mut separator = ": ";
+        for x in &self.data {
+            write!(f, "{separator}{x}")?;
+            separator = ", ";
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::root;
+
+    #[test]
+    fn error_data_survives_gc() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let value = list![1, 2; cx];
+        let error = EvalError::new_error(TypeError::new(Type::String, value).into());
+        cx.garbage_collect(true);
+        root!(env, Env::default(), cx);
+        let error = error.into_signal(env, cx);
+        assert!(ERROR_OBJECTS.with(|x| x.borrow().is_empty()));
+        cx.garbage_collect(true);
+        let (symbol, data) = error.signal_data(env, cx).unwrap();
+        assert_eq!(symbol, sym::WRONG_TYPE_ARGUMENT);
+        assert_eq!(data, list![intern("stringp", cx), list![1, 2; cx]; cx]);
     }
 }
@@ -1,7 +1,9 @@
 use super::Block;
 use crate::core::cons::Cons;
 use crate::core::env::SymbolCell;
-use crate::core::object::{ByteFn, LispFloat, LispHashTable, LispString, LispVec};
+use crate::core::object::{
+    ByteFn, LispBuffer, LispFloat, LispHashTable, LispMarker, LispOverlay, LispString, LispVec,
+};
 use std::fmt::Debug;
 
 /// The owner of an object allocation. No references to
@@ -15,6 +17,9 @@ pub(super) enum OwnedObject {
     String(Box<LispString>),
     Symbol(Box<SymbolCell>),
     ByteFn(Box<ByteFn>),
+    Buffer(Box<LispBuffer>),
+    Marker(Box<LispMarker>),
+    Overlay(Box<LispOverlay>),
 }
 
 pub(in crate::core) trait AllocObject
@@ -110,3 +115,36 @@ impl AllocObject for LispHashTable {
         x.as_ref()
     }
 }
+
+impl AllocObject for LispBuffer {
+    type Output = Self;
+
+    fn alloc_obj<const C: bool>(self, block: &Block<C>) -> *const Self::Output {
+        let mut objects = block.objects.borrow_mut();
+        Block::<C>::register(&mut objects, OwnedObject::Buffer(Box::new(self)));
+        let Some(OwnedObject::Buffer(x)) = objects.last() else {unreachable!()};
+        x.as_ref()
+    }
+}
+
+impl AllocObject for LispMarker {
+    type Output = Self;
+
+    fn alloc_obj<const C: bool>(self, block: &Block<C>) -> *const Self::Output {
+        let mut objects = block.objects.borrow_mut();
+        Block::<C>::register(&mut objects, OwnedObject::Marker(Box::new(self)));
+        let Some(OwnedObject::Marker(x)) = objects.last() else {unreachable!()};
+        x.as_ref()
+    }
+}
+
+impl AllocObject for LispOverlay {
+    type Output = Self;
+
+    fn alloc_obj<const C: bool>(self, block: &Block<C>) -> *const Self::Output {
+        let mut objects = block.objects.borrow_mut();
+        Block::<C>::register(&mut objects, OwnedObject::Overlay(Box::new(self)));
+        let Some(OwnedObject::Overlay(x)) = objects.last() else {unreachable!()};
+        x.as_ref()
+    }
+}
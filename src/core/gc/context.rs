@@ -186,6 +186,7 @@ impl<'ob, 'rt> Context<'rt> {
                 (**x).trace(gray_stack);
             }
         }
+        crate::core::error::trace_error_objects(gray_stack);
         while !gray_stack.is_empty() {
             let raw = gray_stack.pop().unwrap();
             let obj = unsafe { GcObj::from_raw(raw) };
@@ -218,6 +219,9 @@ impl OwnedObject {
             OwnedObject::String(x) => x.unmark(),
             OwnedObject::Symbol(x) => x.unmark(),
             OwnedObject::ByteFn(x) => x.unmark(),
+            OwnedObject::Buffer(x) => x.unmark(),
+            OwnedObject::Marker(x) => x.unmark(),
+            OwnedObject::Overlay(x) => x.unmark(),
         }
     }
 
@@ -230,6 +234,9 @@ impl OwnedObject {
             OwnedObject::String(x) => x.is_marked(),
             OwnedObject::Symbol(x) => x.is_marked(),
             OwnedObject::ByteFn(x) => x.is_marked(),
+            OwnedObject::Buffer(x) => x.is_marked(),
+            OwnedObject::Marker(x) => x.is_marked(),
+            OwnedObject::Overlay(x) => x.is_marked(),
         }
     }
 }
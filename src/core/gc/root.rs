@@ -4,7 +4,9 @@ use super::super::{
 };
 use super::{Block, Context, RootSet, Trace};
 use crate::core::env::Symbol;
-use crate::core::object::{ByteFn, Gc, IntoObject, LispString, Object, Untag, WithLifetime};
+use crate::core::object::{
+    ByteFn, Gc, IntoObject, LispBuffer, LispString, Object, Untag, WithLifetime,
+};
 use crate::hashmap::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut, Index, IndexMut};
@@ -70,6 +72,12 @@ impl IntoRoot<&'static ByteFn> for &ByteFn {
     }
 }
 
+impl IntoRoot<&'static LispBuffer> for &LispBuffer {
+    unsafe fn into_root(self) -> &'static LispBuffer {
+        self.with_lifetime()
+    }
+}
+
 impl IntoRoot<Symbol<'static>> for Symbol<'_> {
     unsafe fn into_root(self) -> Symbol<'static> {
         self.with_lifetime()
@@ -487,6 +495,10 @@ impl<T> Rt<Vec<T>> {
         self.as_mut_ref().swap_remove(index);
     }
 
+    pub(crate) fn remove(&mut self, index: usize) {
+        self.as_mut_ref().remove(index);
+    }
+
     pub(crate) fn pop_obj<'ob, U>(&mut self, _cx: &'ob Context) -> Option<U>
     where
         T: WithLifetime<'ob, Out = U>,
@@ -14,6 +14,10 @@ mod convert;
 mod float;
 mod func;
 mod hashtable;
+mod intervals;
+mod lines;
+mod overlays;
+mod print;
 mod string;
 mod tagged;
 mod vector;
@@ -24,6 +28,10 @@ pub(crate) use convert::*;
 pub(crate) use float::*;
 pub(crate) use func::*;
 pub(crate) use hashtable::*;
+pub(crate) use intervals::*;
+pub(crate) use lines::*;
+pub(crate) use overlays::*;
+pub(crate) use print::*;
 pub(crate) use string::*;
 pub(crate) use tagged::*;
 pub(crate) use vector::*;
@@ -1,13 +1,83 @@
 #![allow(dead_code)]
-use std::ops::{Bound, RangeBounds};
+use std::cell::{Cell, RefCell};
+use std::fmt::{Debug, Display};
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
 
+use anyhow::{anyhow, ensure, Result};
 use bstr::ByteSlice;
 
 use bytecount::num_chars;
 
+use super::{
+    nil, CloneIn, Gc, GcObj, IntervalTree, IntoObject, LineIndex, Object, OverlayTree, RawObj,
+    WithLifetime,
+};
+use crate::core::env::{sym, Symbol};
+use crate::core::error::LispError;
+use crate::core::gc::{Block, Context, GcManaged, GcMark, Trace};
+use crate::hashmap::HashMap;
+
+/// The location of a marker. This is shared between a [`LispMarker`] and the
+/// [`Buffer`] it points into, so that edits to the text can keep it up to
+/// date.
+#[derive(Debug, Default)]
+pub(crate) struct MarkerPos {
+    position: AtomicUsize,
+    /// When true the marker advances when text is inserted at its position.
+    insertion_type: AtomicBool,
+}
+
+impl MarkerPos {
+    fn new(position: usize, insertion_type: bool) -> Self {
+        Self {
+            position: AtomicUsize::new(position),
+            insertion_type: AtomicBool::new(insertion_type),
+        }
+    }
+}
+
+/// The accessible region of a buffer saved by [`Buffer::save_restriction`].
+/// The bounds are tracked like markers so they stay valid across edits.
+#[derive(Debug)]
+pub(crate) struct Restriction(Option<(Arc<MarkerPos>, Arc<MarkerPos>)>);
+
+/// The point saved by [`Buffer::save_excursion`]. Like the bounds of a
+/// [`Restriction`] it moves with edits to the text.
+#[derive(Debug)]
+pub(crate) struct Excursion(Arc<MarkerPos>);
+
+/// A change to the text of a [`Buffer`], recorded so that it can be undone.
+/// Positions are character positions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Undo {
+    /// Text was inserted between the two positions.
+    Insert(usize, usize),
+    /// `text` was deleted from `pos`. `point_at_end` is set if point was at the
+    /// end of the deleted text.
+    Delete {
+        text: String,
+        pos: usize,
+        point_at_end: bool,
+    },
+    /// Point was at this position before the next change.
+    Point(usize),
+}
+
+/// The changes recorded for undo that have not been added to the lisp undo
+/// list yet.
+#[derive(Debug, Clone)]
+struct UndoLog {
+    enabled: bool,
+    /// True if no change has been recorded since the last undo boundary
+    at_boundary: bool,
+    pending: Vec<Undo>,
+}
+
 /// A Gap buffer. This represents the text of a buffer, and allows for
 /// efficient insertion and deletion of text.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Buffer {
     /// The buffer data
     storage: Box<[u8]>,
@@ -19,9 +89,28 @@ pub(crate) struct Buffer {
     gap_end: usize,
     /// The number of characters until the gap
     gap_chars: usize,
-    /// The current point. The first field is the byte index, the second is the
-    /// character count.
-    point: (usize, usize),
+    /// The total number of characters in the buffer
+    total_chars: usize,
+    /// The current point as a character position.
+    point: usize,
+    /// The most recent result of [`Buffer::char_to_byte`], as a (byte,
+    /// character) pair. The byte does not count the gap, so the pair stays
+    /// valid when the gap moves, and is adjusted when text is inserted or
+    /// deleted. Lookups start from here, which makes nearby lookups cheap.
+    cursor: Cell<(usize, usize)>,
+    /// The start of the accessible portion of the buffer (BEGV in GNU Emacs).
+    begv: usize,
+    /// The end of the accessible portion of the buffer (ZV in GNU Emacs).
+    zv: usize,
+    /// The markers that point into this buffer. Markers that have been freed
+    /// are removed lazily.
+    markers: Vec<Weak<MarkerPos>>,
+    undo: UndoLog,
+    /// The positions of the newlines in the buffer.
+    pub(crate) lines: LineIndex,
+    /// The text properties of the buffer.
+    pub(crate) props: IntervalTree,
+    pub(crate) overlays: OverlayTree,
 }
 
 impl Buffer {
@@ -36,15 +125,267 @@ impl Buffer {
             assert_eq!(storage.len(), capacity);
             storage.into_boxed_slice()
         };
+        let total_chars = num_chars(data.as_bytes());
         Self {
             storage,
             gap_start: 0,
             gap_end: Self::GAP_SIZE,
             gap_chars: 0,
-            point: (0, 0),
+            total_chars,
+            point: 0,
+            cursor: Cell::new((0, 0)),
+            begv: 0,
+            zv: total_chars,
+            markers: Vec::new(),
+            undo: UndoLog {
+                enabled: true,
+                at_boundary: true,
+                pending: Vec::new(),
+            },
+            lines: LineIndex::new(data),
+            props: IntervalTree::default(),
+            overlays: OverlayTree::default(),
+        }
+    }
+
+    /// The number of characters in the buffer.
+    pub(crate) fn len_chars(&self) -> usize {
+        self.total_chars
+    }
+
+    pub(crate) fn point(&self) -> usize {
+        self.point
+    }
+
+    pub(crate) fn set_point(&mut self, pos: usize) -> Result<()> {
+        self.check_pos(pos)?;
+        self.point = pos;
+        Ok(())
+    }
+
+    /// Insert `slice` at point, leaving point after the inserted text.
+    pub(crate) fn insert(&mut self, slice: &str) {
+        if self.point != self.gap_chars {
+            self.move_gap(self.point);
+        }
+        self.insert_string(slice);
+        self.point += num_chars(slice.as_bytes());
+    }
+
+    /// Delete the characters between `beg` and `end`. Point is moved to `beg` if it
+    /// was inside the deleted region.
+    pub(crate) fn delete_range(&mut self, beg: usize, end: usize) -> Result<()> {
+        self.check_range(beg, end)?;
+        self.delete_region(beg, end);
+        Ok(())
+    }
+
+    /// Signal `args-out-of-range` if `pos` is not in the buffer.
+    fn check_pos(&self, pos: usize) -> Result<()> {
+        ensure!(
+            pos <= self.total_chars,
+            LispError::new(sym::ARGS_OUT_OF_RANGE, [(pos + 1).into()])
+        );
+        Ok(())
+    }
+
+    /// Signal `args-out-of-range` if `beg..end` is not a region of the buffer.
+    fn check_range(&self, beg: usize, end: usize) -> Result<()> {
+        ensure!(
+            beg <= end && end <= self.total_chars,
+            LispError::new(sym::ARGS_OUT_OF_RANGE, [(beg + 1).into(), (end + 1).into()])
+        );
+        Ok(())
+    }
+
+    /// The start of the accessible portion of the buffer.
+    pub(crate) fn begv(&self) -> usize {
+        self.begv
+    }
+
+    /// The end of the accessible portion of the buffer.
+    pub(crate) fn zv(&self) -> usize {
+        self.zv
+    }
+
+    pub(crate) fn is_narrowed(&self) -> bool {
+        self.begv != 0 || self.zv != self.total_chars
+    }
+
+    /// Restrict the accessible portion of the buffer to `beg..end`. Point is
+    /// moved inside the new bounds.
+    pub(crate) fn narrow(&mut self, beg: usize, end: usize) -> Result<()> {
+        self.check_range(beg, end)?;
+        self.begv = beg;
+        self.zv = end;
+        self.point = self.point.clamp(beg, end);
+        Ok(())
+    }
+
+    /// Make the whole buffer accessible.
+    pub(crate) fn widen(&mut self) {
+        self.begv = 0;
+        self.zv = self.total_chars;
+    }
+
+    /// Save the accessible region so that it can be restored with
+    /// [`Buffer::restore_restriction`].
+    pub(crate) fn save_restriction(&mut self) -> Restriction {
+        if !self.is_narrowed() {
+            return Restriction(None);
+        }
+        let beg = Arc::new(MarkerPos::new(self.begv, false));
+        let end = Arc::new(MarkerPos::new(self.zv, true));
+        self.register_marker(&beg);
+        self.register_marker(&end);
+        Restriction(Some((beg, end)))
+    }
+
+    pub(crate) fn restore_restriction(&mut self, saved: Restriction) {
+        match saved.0 {
+            None => self.widen(),
+            Some((beg, end)) => {
+                self.unregister_marker(&beg);
+                self.unregister_marker(&end);
+                let beg = beg.position.load(Relaxed);
+                let end = end.position.load(Relaxed).max(beg);
+                self.narrow(beg, end)
+                    .expect("saved restriction should be inside the buffer");
+            }
+        }
+    }
+
+    /// Save point so that it can be restored with
+    /// [`Buffer::restore_excursion`].
+    pub(crate) fn save_excursion(&mut self) -> Excursion {
+        let point = Arc::new(MarkerPos::new(self.point, false));
+        self.register_marker(&point);
+        Excursion(point)
+    }
+
+    pub(crate) fn restore_excursion(&mut self, saved: Excursion) {
+        self.unregister_marker(&saved.0);
+        let point = saved.0.position.load(Relaxed);
+        self.point = point.clamp(self.begv, self.zv);
+    }
+
+    /// Take the changes recorded since the last call, oldest first.
+    pub(crate) fn take_undo(&mut self) -> Vec<Undo> {
+        std::mem::take(&mut self.undo.pending)
+    }
+
+    /// Discard any pending changes and set whether new changes are recorded.
+    /// `at_boundary` should be true if the undo list ends with a boundary.
+    pub(crate) fn reset_undo(&mut self, enabled: bool, at_boundary: bool) {
+        self.undo = UndoLog {
+            enabled,
+            at_boundary,
+            pending: Vec::new(),
+        };
+    }
+
+    pub(crate) fn undo_boundary(&mut self) {
+        self.undo.at_boundary = true;
+    }
+
+    fn record_undo(&mut self, change: Undo) {
+        if !self.undo.enabled {
+            return;
+        }
+        // Record where point was at the start of a change group, unless the
+        // change itself puts it back there
+        let beg = match change {
+            Undo::Insert(beg, _) | Undo::Delete { pos: beg, .. } | Undo::Point(beg) => beg,
+        };
+        if self.undo.at_boundary && self.point != beg {
+            self.undo.pending.push(Undo::Point(self.point));
+        }
+        self.undo.at_boundary = false;
+        // Consecutive insertions are merged
+        if let (Undo::Insert(beg, end), Some(Undo::Insert(_, last_end))) =
+            (&change, self.undo.pending.last_mut())
+        {
+            if last_end == beg {
+                *last_end = *end;
+                return;
+            }
+        }
+        self.undo.pending.push(change);
+    }
+
+    /// Start tracking `marker`, so that it is adjusted when text is inserted or
+    /// deleted.
+    pub(crate) fn register_marker(&mut self, marker: &Arc<MarkerPos>) {
+        self.markers.retain(|x| x.strong_count() > 0);
+        self.markers.push(Arc::downgrade(marker));
+    }
+
+    pub(crate) fn unregister_marker(&mut self, marker: &Arc<MarkerPos>) {
+        self.markers
+            .retain(|x| x.strong_count() > 0 && !std::ptr::eq(x.as_ptr(), Arc::as_ptr(marker)));
+    }
+
+    /// The live markers in this buffer.
+    fn markers(&self) -> impl Iterator<Item = Arc<MarkerPos>> + '_ {
+        self.markers.iter().filter_map(Weak::upgrade)
+    }
+
+    /// Return the character at char position `pos`.
+    pub(crate) fn char_at(&self, pos: usize) -> Option<char> {
+        if pos >= self.total_chars {
+            return None;
+        }
+        let byte = self.char_to_byte(pos);
+        let string = unsafe { std::str::from_utf8_unchecked(&self.storage[byte..]) };
+        string.chars().next()
+    }
+
+    /// The start of the line containing `pos`, ignoring any narrowing.
+    pub(crate) fn line_beginning(&self, pos: usize) -> usize {
+        let line = self.lines.line_at(pos);
+        self.lines.line_start(line).unwrap_or(0)
+    }
+
+    /// The end of the line containing `pos`, ignoring any narrowing. This is
+    /// the position of the newline, or the end of the buffer.
+    pub(crate) fn line_end(&self, pos: usize) -> usize {
+        let line = self.lines.line_at(pos);
+        self.lines.newline(line).unwrap_or(self.total_chars)
+    }
+
+    /// Return the text between the character positions `beg` and `end` as two
+    /// slices, the first before the gap and the second after it. Either can be
+    /// empty.
+    pub(crate) fn slice(&self, beg: usize, end: usize) -> Result<(&str, &str)> {
+        self.check_range(beg, end)?;
+        let beg = self.char_to_byte(beg);
+        let end = self.char_to_byte(end);
+        let (front, back) = if end <= self.gap_start || beg >= self.gap_end {
+            (&self.storage[beg..end], &[][..])
+        } else {
+            (
+                &self.storage[beg..self.gap_start],
+                &self.storage[self.gap_end..end],
+            )
+        };
+        // SAFETY: both ends were checked to be on char boundaries
+        unsafe {
+            Ok((
+                std::str::from_utf8_unchecked(front),
+                std::str::from_utf8_unchecked(back),
+            ))
         }
     }
 
+    /// Return the text between the character positions `beg` and `end`.
+    pub(crate) fn substring(&self, beg: usize, end: usize) -> Result<String> {
+        let (front, back) = self.slice(beg, end)?;
+        let mut string = String::with_capacity(front.len() + back.len());
+        string.push_str(front);
+        string.push_str(back);
+        Ok(string)
+    }
+
     fn grow(&mut self, slice: &str) {
         let new_capacity = {
             let pre_gap = self.gap_start;
@@ -67,7 +408,9 @@ impl Buffer {
         self.storage = new_storage;
         self.gap_start += slice.len();
         self.gap_end = self.gap_start + Self::GAP_SIZE;
-        self.gap_chars += num_chars(slice.as_bytes());
+        let chars = num_chars(slice.as_bytes());
+        self.gap_chars += chars;
+        self.total_chars += chars;
     }
 
     pub(crate) fn insert_char(&mut self, chr: char) {
@@ -76,13 +419,43 @@ impl Buffer {
     }
 
     pub(crate) fn insert_string(&mut self, slice: &str) {
+        let chars = num_chars(slice.as_bytes());
+        if chars > 0 {
+            self.record_undo(Undo::Insert(self.gap_chars, self.gap_chars + chars));
+        }
+        self.lines.insert(self.gap_chars, slice);
+        self.props.insert(self.gap_chars, chars);
+        self.overlays.insert(self.gap_chars, chars);
+        if self.point > self.gap_chars {
+            self.point += chars;
+        }
+        let (cursor_byte, cursor_char) = self.cursor.get();
+        if cursor_char > self.gap_chars {
+            self.cursor
+                .set((cursor_byte + slice.len(), cursor_char + chars));
+        }
+        if self.begv > self.gap_chars {
+            self.begv += chars;
+        }
+        if self.zv >= self.gap_chars {
+            self.zv += chars;
+        }
+        for marker in self.markers() {
+            let pos = marker.position.load(Relaxed);
+            if pos > self.gap_chars
+                || (pos == self.gap_chars && marker.insertion_type.load(Relaxed))
+            {
+                marker.position.store(pos + chars, Relaxed);
+            }
+        }
         if (self.gap_end - self.gap_start) < slice.len() {
             self.grow(slice);
         } else {
             let new_slice = &mut self.storage[self.gap_start..(self.gap_start + slice.len())];
             new_slice.copy_from_slice(slice.as_bytes());
             self.gap_start += slice.len();
-            self.gap_chars += num_chars(slice.as_bytes());
+            self.gap_chars += chars;
+            self.total_chars += chars;
         }
     }
 
@@ -95,9 +468,43 @@ impl Buffer {
     }
 
     fn delete_region(&mut self, beg: usize, end: usize) {
-        let beg = self.char_to_byte(beg);
-        let end = self.char_to_byte(end);
-        self.delete_byte_region(beg, end);
+        if beg < end && self.undo.enabled {
+            let text = self
+                .substring(beg, end)
+                .expect("deleted region should be in the buffer");
+            let point_at_end = self.point == end;
+            self.record_undo(Undo::Delete {
+                text,
+                pos: beg,
+                point_at_end,
+            });
+        }
+        let adjust = |pos: usize| {
+            if pos >= end {
+                pos - (end - beg)
+            } else if pos > beg {
+                beg
+            } else {
+                pos
+            }
+        };
+        self.lines.delete(beg, end);
+        self.props.delete(beg, end);
+        self.overlays.delete(beg, end);
+        self.point = adjust(self.point);
+        self.begv = adjust(self.begv);
+        self.zv = adjust(self.zv);
+        for marker in self.markers() {
+            marker
+                .position
+                .store(adjust(marker.position.load(Relaxed)), Relaxed);
+        }
+        let beg_byte = self.char_to_byte(beg);
+        // the text before `beg` is unchanged, so its cursor stays valid
+        let beg_cursor = self.cursor.get();
+        let end_byte = self.char_to_byte(end);
+        self.delete_byte_region(beg_byte, end_byte);
+        self.cursor.set(beg_cursor);
     }
 
     fn delete_byte_region(&mut self, beg: usize, end: usize) {
@@ -117,7 +524,9 @@ impl Buffer {
             //             ^
             //             gap_start
             let size = end - beg;
-            self.gap_chars -= num_chars(&self.storage[beg..end]);
+            let chars = num_chars(&self.storage[beg..end]);
+            self.gap_chars -= chars;
+            self.total_chars -= chars;
             self.storage[..self.gap_start].copy_within(end.., beg);
             self.gap_start -= size;
         } else if beg >= self.gap_end {
@@ -134,12 +543,16 @@ impl Buffer {
             //        ^
             //        gap_end
             let size = end - beg;
+            self.total_chars -= num_chars(&self.storage[beg..end]);
             let beg = beg - self.gap_end;
             self.storage[self.gap_end..].copy_within(..beg, size);
             self.gap_end += size;
         } else if beg < self.gap_start && end >= self.gap_end {
             // delete spans gap
-            self.gap_chars -= num_chars(&self.storage[beg..self.gap_start]);
+            let chars_before = num_chars(&self.storage[beg..self.gap_start]);
+            let chars_after = num_chars(&self.storage[self.gap_end..end]);
+            self.gap_chars -= chars_before;
+            self.total_chars -= chars_before + chars_after;
             self.gap_start = beg;
             self.gap_end = end;
         } else {
@@ -210,12 +623,37 @@ impl Buffer {
     }
 
     fn char_to_byte(&self, pos: usize) -> usize {
+        let byte = self.char_to_storage_byte(pos);
+        let logical = if byte >= self.gap_end {
+            byte - self.gap_len()
+        } else {
+            byte
+        };
+        self.cursor.set((logical, pos));
+        byte
+    }
+
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
+
+    fn char_to_storage_byte(&self, pos: usize) -> usize {
+        let (cursor_byte, cursor_char) = self.cursor.get();
         // (byte position, char positions) pairs sorted in ascending order
-        #[rustfmt::skip]
-        let positions = if self.point.1 <= self.gap_chars {
-            [(0, 0), self.point, (self.gap_start, self.gap_chars), (self.gap_end, self.gap_chars)]
+        let positions = if cursor_char < self.gap_chars {
+            [
+                (0, 0),
+                (cursor_byte, cursor_char),
+                (self.gap_start, self.gap_chars),
+                (self.gap_end, self.gap_chars),
+            ]
         } else {
-            [(0, 0), (self.gap_start, self.gap_chars), (self.gap_end, self.gap_chars), self.point]
+            [
+                (0, 0),
+                (self.gap_start, self.gap_chars),
+                (self.gap_end, self.gap_chars),
+                (cursor_byte + self.gap_len(), cursor_char),
+            ]
         };
 
         // find which positions window the char position falls into
@@ -252,6 +690,11 @@ impl Buffer {
     }
 
     fn assert_char_boundary(&self, pos: usize) {
+        // the gap can hold stale bytes, but the text before it always ends on
+        // a char boundary
+        if pos == self.gap_start {
+            return;
+        }
         let is_boundary = match self.storage.get(pos) {
             Some(byte) => Self::is_char_boundary(*byte),
             None => pos == self.storage.len(),
@@ -310,6 +753,528 @@ impl Buffer {
     }
 }
 
+/// The contents of a live buffer.
+#[derive(Debug, Clone)]
+pub(crate) struct BufferData {
+    pub(crate) name: String,
+    pub(crate) text: Buffer,
+    /// The lisp value of `buffer-undo-list`, not including the changes still
+    /// pending in `text`.
+    undo_list: GcObj<'static>,
+    /// The values of variables that are local to this buffer. A local value
+    /// that was made void by `makunbound` is `None`.
+    locals: HashMap<Symbol<'static>, Option<GcObj<'static>>>,
+}
+
+/// A lisp buffer object. When a buffer is killed its data is dropped, but the
+/// object itself remains valid as long as it is referenced. A copy made by
+/// [`CloneIn`] is another handle to the same buffer: it shares the data and is
+/// `eq` to the original.
+#[derive(Debug)]
+pub(crate) struct LispBuffer {
+    gc: GcMark,
+    data: Arc<Mutex<Option<BufferData>>>,
+}
+
+/// Exclusive access to the data of a live buffer, returned by
+/// [`LispBuffer::lock`].
+pub(crate) struct BufferGuard<'a>(MutexGuard<'a, Option<BufferData>>);
+
+impl Deref for BufferGuard<'_> {
+    type Target = BufferData;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+            .as_ref()
+            .expect("buffer guard should only hold live buffers")
+    }
+}
+
+impl DerefMut for BufferGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+            .as_mut()
+            .expect("buffer guard should only hold live buffers")
+    }
+}
+
+impl LispBuffer {
+    pub(crate) fn new(name: String) -> Self {
+        let mut text = Buffer::new("");
+        // Like GNU Emacs, undo is disabled in internal buffers
+        let undo_list = if name.starts_with(' ') {
+            text.reset_undo(false, true);
+            sym::TRUE.into()
+        } else {
+            nil()
+        };
+        Self {
+            gc: GcMark::default(),
+            data: Arc::new(Mutex::new(Some(BufferData {
+                name,
+                text,
+                undo_list,
+                locals: HashMap::default(),
+            }))),
+        }
+    }
+
+    /// Get mutable access to the buffer contents. Fails if the buffer has been
+    /// killed.
+    pub(crate) fn lock(&self) -> Result<BufferGuard<'_>> {
+        let data = self.data();
+        match *data {
+            Some(_) => Ok(BufferGuard(data)),
+            None => Err(anyhow!("Selecting deleted buffer")),
+        }
+    }
+
+    fn data(&self) -> MutexGuard<'_, Option<BufferData>> {
+        // A panic while the buffer was locked does not leave the data invalid
+        self.data.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn is_live(&self) -> bool {
+        self.data().is_some()
+    }
+
+    /// The name of the buffer, or `None` if it has been killed.
+    pub(crate) fn name(&self) -> Option<String> {
+        self.data().as_ref().map(|x| x.name.clone())
+    }
+
+    /// The value of `buffer-undo-list`. Changes recorded in the text since the
+    /// last call are added to the front of the list first. A killed buffer
+    /// has no undo list and returns `t`.
+    pub(crate) fn undo_list<'ob>(&self, cx: &'ob Context) -> GcObj<'ob> {
+        let Ok(mut data) = self.lock() else { return sym::TRUE.into() };
+        let mut list = unsafe { data.undo_list.with_lifetime() };
+        for change in data.text.take_undo() {
+            let entry = match change {
+                Undo::Insert(beg, end) => cons!(beg as i64 + 1, end as i64 + 1; cx),
+                Undo::Delete {
+                    text,
+                    pos,
+                    point_at_end,
+                } => {
+                    let pos = pos as i64 + 1;
+                    cons!(text, if point_at_end { -pos } else { pos }; cx)
+                }
+                Undo::Point(pos) => cx.add(pos as i64 + 1),
+            };
+            list = cons!(entry, list; cx);
+        }
+        data.undo_list = unsafe { list.with_lifetime() };
+        list
+    }
+
+    /// Set the value of `buffer-undo-list`. Setting it to `t` disables undo.
+    pub(crate) fn set_undo_list(&self, list: GcObj) {
+        let Ok(mut data) = self.lock() else { return };
+        let at_boundary = match list.untag() {
+            Object::Cons(cons) => cons.car().nil(),
+            _ => true,
+        };
+        data.text.reset_undo(list != sym::TRUE, at_boundary);
+        data.undo_list = unsafe { list.with_lifetime() };
+    }
+
+    /// The value of `var` in this buffer, or `None` if it is not local to the
+    /// buffer.
+    pub(crate) fn local_var<'ob>(&self, var: Symbol, cx: &'ob Context) -> Option<GcObj<'ob>> {
+        let data = self.data();
+        let value = (*data.as_ref()?.locals.get(&var)?)?;
+        Some(cx.bind(value))
+    }
+
+    pub(crate) fn has_local_var(&self, var: Symbol) -> bool {
+        matches!(&*self.data(), Some(data) if data.locals.contains_key(&var))
+    }
+
+    /// Set the value of `var` in this buffer, making it local if it was not
+    /// already.
+    pub(crate) fn set_local_var(&self, var: Symbol, value: GcObj) {
+        if let Ok(mut data) = self.lock() {
+            let (var, value) = unsafe { (var.with_lifetime(), value.with_lifetime()) };
+            data.locals.insert(var, Some(value));
+        }
+    }
+
+    /// Make the value of `var` in this buffer void. It stays local to the
+    /// buffer.
+    pub(crate) fn void_local_var(&self, var: Symbol) {
+        if let Ok(mut data) = self.lock() {
+            data.locals.insert(unsafe { var.with_lifetime() }, None);
+        }
+    }
+
+    /// Remove the local value of `var`. Returns false if it did not have one.
+    pub(crate) fn kill_local_var(&self, var: Symbol) -> bool {
+        let var = unsafe { var.with_lifetime() };
+        match self.lock() {
+            Ok(mut data) => data.locals.remove(&var).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// All the variables local to this buffer and their values, which are
+    /// `None` if they are void.
+    pub(crate) fn local_vars<'ob>(
+        &self,
+        cx: &'ob Context,
+    ) -> Vec<(Symbol<'ob>, Option<GcObj<'ob>>)> {
+        match &*self.data() {
+            Some(data) => data
+                .locals
+                .iter()
+                .map(|(k, v)| (cx.bind(*k), v.map(|x| cx.bind(x))))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Kill the buffer, releasing its contents. Returns false if the buffer
+    /// was already dead.
+    pub(crate) fn kill(&self) -> bool {
+        self.data().take().is_some()
+    }
+}
+
+// Buffers are only equal to themselves and their copies
+impl PartialEq for LispBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl Eq for LispBuffer {}
+
+impl<'new> CloneIn<'new, &'new Self> for LispBuffer {
+    fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> Gc<&'new Self> {
+        let new = Self {
+            gc: GcMark::default(),
+            data: self.data.clone(),
+        };
+        new.into_obj(bk)
+    }
+}
+
+impl GcManaged for LispBuffer {
+    fn get_mark(&self) -> &GcMark {
+        &self.gc
+    }
+}
+
+impl Trace for LispBuffer {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        self.mark();
+        if let Some(data) = &*self.data() {
+            if data.undo_list.is_markable() {
+                stack.push(data.undo_list.into_raw());
+            }
+            data.text.props.trace(stack);
+            for (var, value) in &data.locals {
+                var.trace(stack);
+                if let Some(value) = value.filter(|x| x.is_markable()) {
+                    stack.push(value.into_raw());
+                }
+            }
+            for overlay in data.text.overlays.iter() {
+                if !overlay.is_marked() {
+                    let obj: GcObj = overlay.into();
+                    stack.push(obj.into_raw());
+                }
+            }
+        }
+    }
+}
+
+impl Display for LispBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.data() {
+            Some(data) => write!(f, "#<buffer {}>", data.name),
+            None => write!(f, "#<killed buffer>"),
+        }
+    }
+}
+
+/// A lisp marker object. A marker either points nowhere, or to a position in a
+/// buffer that moves along with the text around it.
+#[derive(Debug)]
+pub(crate) struct LispMarker {
+    gc: GcMark,
+    buffer: Cell<Option<Gc<&'static LispBuffer>>>,
+    pos: Arc<MarkerPos>,
+}
+
+impl LispMarker {
+    /// Create a marker that points nowhere.
+    pub(crate) fn new() -> Self {
+        Self {
+            gc: GcMark::default(),
+            buffer: Cell::new(None),
+            pos: Arc::default(),
+        }
+    }
+
+    /// The buffer the marker points into, or `None` if it points nowhere or
+    /// the buffer has been killed.
+    pub(crate) fn buffer(&self) -> Option<&LispBuffer> {
+        self.buffer.get().map(Gc::untag).filter(|x| x.is_live())
+    }
+
+    /// The character position of the marker, or `None` if it points nowhere.
+    pub(crate) fn position(&self) -> Option<usize> {
+        self.buffer().map(|_| self.pos.position.load(Relaxed))
+    }
+
+    pub(crate) fn insertion_type(&self) -> bool {
+        self.pos.insertion_type.load(Relaxed)
+    }
+
+    pub(crate) fn set_insertion_type(&self, insertion_type: bool) {
+        self.pos.insertion_type.store(insertion_type, Relaxed);
+    }
+
+    /// Point the marker at character position `pos` in `buffer`. The position
+    /// is clamped to the size of the buffer. The buffer must not be locked by
+    /// the caller.
+    pub(crate) fn set(&self, buffer: &LispBuffer, pos: usize) -> Result<()> {
+        self.detach();
+        let mut data = buffer.lock()?;
+        let text = &mut data.text;
+        self.pos.position.store(pos.min(text.len_chars()), Relaxed);
+        text.register_marker(&self.pos);
+        let buffer: Gc<&LispBuffer> = buffer.into();
+        self.buffer.set(Some(unsafe { buffer.with_lifetime() }));
+        Ok(())
+    }
+
+    /// Make the marker point nowhere.
+    pub(crate) fn detach(&self) {
+        if let Some(buffer) = self.buffer.take() {
+            if let Ok(mut data) = buffer.untag().lock() {
+                data.text.unregister_marker(&self.pos);
+            }
+        }
+    }
+}
+
+// Markers are equal if they point to the same place
+impl PartialEq for LispMarker {
+    fn eq(&self, other: &Self) -> bool {
+        let buffers_eq = match (self.buffer(), other.buffer()) {
+            (Some(b1), Some(b2)) => b1 == b2,
+            (None, None) => true,
+            _ => false,
+        };
+        buffers_eq && self.position() == other.position()
+    }
+}
+
+impl Eq for LispMarker {}
+
+impl<'new> CloneIn<'new, &'new Self> for LispMarker {
+    fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> Gc<&'new Self> {
+        let new = Self::new();
+        new.set_insertion_type(self.insertion_type());
+        if let (Some(buffer), Some(pos)) = (self.buffer(), self.position()) {
+            let buffer = buffer.clone_in(bk).untag();
+            new.set(buffer, pos).expect("cloned buffer should be live");
+        }
+        new.into_obj(bk)
+    }
+}
+
+impl GcManaged for LispMarker {
+    fn get_mark(&self) -> &GcMark {
+        &self.gc
+    }
+}
+
+impl Trace for LispMarker {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        self.mark();
+        if let Some(buffer) = self.buffer.get() {
+            if !buffer.untag().is_marked() {
+                let obj: GcObj = buffer.into();
+                stack.push(obj.into_raw());
+            }
+        }
+    }
+}
+
+impl Display for LispMarker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let insertion_type = if self.insertion_type() { "(moves after insertion) " } else { "" };
+        match (self.buffer(), self.position()) {
+            (Some(buffer), Some(pos)) => {
+                let name = buffer.name().unwrap_or_default();
+                write!(f, "#<marker {insertion_type}at {} in {name}>", pos + 1)
+            }
+            _ => write!(f, "#<marker {insertion_type}in no buffer>"),
+        }
+    }
+}
+
+/// A lisp overlay object. An overlay covers a region of a buffer that moves
+/// with the text around it, and has its own property list. The position is
+/// kept in the [`OverlayTree`] of the buffer. A deleted overlay belongs to no
+/// buffer.
+#[derive(Debug)]
+pub(crate) struct LispOverlay {
+    gc: GcMark,
+    buffer: Cell<Option<Gc<&'static LispBuffer>>>,
+    /// The index of the overlay's node in the overlays of the buffer.
+    node: Cell<usize>,
+    /// When true text inserted at the start is not included in the overlay.
+    front_advance: bool,
+    /// When true text inserted at the end is included in the overlay.
+    rear_advance: bool,
+    plist: RefCell<Vec<(GcObj<'static>, GcObj<'static>)>>,
+}
+
+impl LispOverlay {
+    /// Create an overlay that belongs to no buffer.
+    pub(crate) fn new(front_advance: bool, rear_advance: bool) -> Self {
+        Self {
+            gc: GcMark::default(),
+            buffer: Cell::new(None),
+            node: Cell::new(0),
+            front_advance,
+            rear_advance,
+            plist: RefCell::default(),
+        }
+    }
+
+    /// The buffer the overlay is in, or `None` if it has been deleted or the
+    /// buffer has been killed.
+    pub(crate) fn buffer(&self) -> Option<&LispBuffer> {
+        self.buffer.get().map(Gc::untag).filter(|x| x.is_live())
+    }
+
+    /// The character positions of the overlay, or `None` if it is not in a
+    /// buffer.
+    pub(crate) fn position(&self) -> Option<(usize, usize)> {
+        let data = self.buffer()?.data();
+        Some(data.as_ref()?.text.overlays.range(self.node.get()))
+    }
+
+    pub(super) fn advances(&self) -> (bool, bool) {
+        (self.front_advance, self.rear_advance)
+    }
+
+    /// Move the overlay to cover `beg..end` in `buffer`. The positions are
+    /// clamped to the size of the buffer. The buffer must not be locked by the
+    /// caller.
+    pub(crate) fn set(&self, buffer: &LispBuffer, beg: usize, end: usize) -> Result<()> {
+        self.detach();
+        let mut data = buffer.lock()?;
+        let len = data.text.len_chars();
+        let (beg, end) = (beg.min(len), end.min(len));
+        let node = data.text.overlays.add(self, beg.min(end), beg.max(end));
+        self.node.set(node);
+        let buffer: Gc<&LispBuffer> = buffer.into();
+        self.buffer.set(Some(unsafe { buffer.with_lifetime() }));
+        Ok(())
+    }
+
+    /// Remove the overlay from its buffer.
+    pub(crate) fn detach(&self) {
+        if let Some(buffer) = self.buffer.take() {
+            if let Ok(mut data) = buffer.untag().lock() {
+                data.text.overlays.remove(self.node.get());
+            }
+        }
+    }
+
+    pub(crate) fn get<'ob>(&self, prop: GcObj, _cx: &'ob Context) -> GcObj<'ob> {
+        let plist = self.plist.borrow();
+        match plist.iter().find(|(k, _)| k.ptr_eq(prop)) {
+            Some((_, value)) => unsafe { value.with_lifetime() },
+            None => nil(),
+        }
+    }
+
+    pub(crate) fn put(&self, prop: GcObj, value: GcObj) {
+        let value = unsafe { value.with_lifetime() };
+        let mut plist = self.plist.borrow_mut();
+        match plist.iter_mut().find(|(k, _)| k.ptr_eq(prop)) {
+            Some((_, v)) => *v = value,
+            None => plist.push((unsafe { prop.with_lifetime() }, value)),
+        }
+    }
+
+    /// The properties of the overlay, in the order they were added.
+    pub(crate) fn properties<'ob>(&self, _cx: &'ob Context) -> Vec<(GcObj<'ob>, GcObj<'ob>)> {
+        let plist = self.plist.borrow();
+        plist.iter().map(|x| unsafe { x.with_lifetime() }).collect()
+    }
+}
+
+// Overlays are only equal to themselves
+impl PartialEq for LispOverlay {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for LispOverlay {}
+
+impl<'new> CloneIn<'new, &'new Self> for LispOverlay {
+    fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> Gc<&'new Self> {
+        let new = Self::new(self.front_advance, self.rear_advance);
+        for (k, v) in self.plist.borrow().iter() {
+            new.put(k.clone_in(bk), v.clone_in(bk));
+        }
+        let new = new.into_obj(bk);
+        if let (Some(buffer), Some((beg, end))) = (self.buffer(), self.position()) {
+            let buffer = buffer.clone_in(bk).untag();
+            new.untag()
+                .set(buffer, beg, end)
+                .expect("cloned buffer should be live");
+        }
+        new
+    }
+}
+
+impl GcManaged for LispOverlay {
+    fn get_mark(&self) -> &GcMark {
+        &self.gc
+    }
+}
+
+impl Trace for LispOverlay {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        self.mark();
+        if let Some(buffer) = self.buffer.get() {
+            if !buffer.untag().is_marked() {
+                let obj: GcObj = buffer.into();
+                stack.push(obj.into_raw());
+            }
+        }
+        for (k, v) in self.plist.borrow().iter() {
+            for obj in [k, v] {
+                if obj.is_markable() {
+                    stack.push(obj.into_raw());
+                }
+            }
+        }
+    }
+}
+
+impl Display for LispOverlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.buffer(), self.position()) {
+            (Some(buffer), Some((beg, end))) => {
+                let name = buffer.name().unwrap_or_default();
+                write!(f, "#<overlay from {} to {} in {name}>", beg + 1, end + 1)
+            }
+            _ => write!(f, "#<overlay in no buffer>"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -408,6 +1373,38 @@ mod test {
         assert_eq!(buffer.as_str(), "hello world");
     }
 
+    #[test]
+    fn insert_at_point() {
+        let mut buffer = Buffer::new("hello world");
+        assert_eq!(buffer.len_chars(), 11);
+        buffer.set_point(5).unwrap();
+        buffer.insert(",");
+        assert_eq!(buffer.point(), 6);
+        buffer.set_point(0).unwrap();
+        buffer.insert("Θ ");
+        assert_eq!(buffer.point(), 2);
+        assert_eq!(buffer.len_chars(), 14);
+        assert_eq!(buffer.substring(0, 14).unwrap(), "Θ hello, world");
+        assert_eq!(buffer.substring(2, 7).unwrap(), "hello");
+        assert_eq!(buffer.char_at(0), Some('Θ'));
+        assert_eq!(buffer.char_at(13), Some('d'));
+        assert_eq!(buffer.char_at(14), None);
+    }
+
+    #[test]
+    fn delete_range() {
+        let mut buffer = Buffer::new("hello world");
+        buffer.set_point(8).unwrap();
+        buffer.delete_range(0, 6).unwrap();
+        assert_eq!(buffer.point(), 2);
+        assert_eq!(buffer.len_chars(), 5);
+        buffer.insert("Θ");
+        buffer.delete_range(1, 4).unwrap();
+        assert_eq!(buffer.point(), 1);
+        assert_eq!(buffer.substring(0, 3).unwrap(), "wld");
+        assert_eq!(buffer.len_chars(), 3);
+    }
+
     #[test]
     fn point() {
         let string = "world";
@@ -416,4 +1413,142 @@ mod test {
         buffer.insert_string(new_string);
         assert_eq!(buffer.gap_chars, new_string.len());
     }
+
+    #[test]
+    fn markers() {
+        let mut buffer = Buffer::new("hello world");
+        let pos = Arc::new(MarkerPos::default());
+        let after = Arc::new(MarkerPos::default());
+        pos.position.store(6, Relaxed);
+        after.position.store(6, Relaxed);
+        after.insertion_type.store(true, Relaxed);
+        buffer.register_marker(&pos);
+        buffer.register_marker(&after);
+        buffer.set_point(6).unwrap();
+        buffer.insert("big ");
+        assert_eq!(pos.position.load(Relaxed), 6);
+        assert_eq!(after.position.load(Relaxed), 10);
+        buffer.set_point(0).unwrap();
+        buffer.insert("oh ");
+        assert_eq!(pos.position.load(Relaxed), 9);
+        assert_eq!(after.position.load(Relaxed), 13);
+        buffer.delete_range(8, 11).unwrap();
+        assert_eq!(pos.position.load(Relaxed), 8);
+        assert_eq!(after.position.load(Relaxed), 10);
+        buffer.unregister_marker(&after);
+        buffer.delete_range(0, 3).unwrap();
+        assert_eq!(pos.position.load(Relaxed), 5);
+        assert_eq!(after.position.load(Relaxed), 10);
+    }
+
+    #[test]
+    fn narrowing() {
+        let mut buffer = Buffer::new("hello big world");
+        buffer.set_point(15).unwrap();
+        buffer.narrow(6, 9).unwrap();
+        assert_eq!(buffer.point(), 9);
+        assert!(buffer.is_narrowed());
+        let saved = buffer.save_restriction();
+        buffer.insert("!");
+        assert_eq!((buffer.begv(), buffer.zv()), (6, 10));
+        buffer.set_point(0).unwrap();
+        buffer.insert("oh ");
+        assert_eq!((buffer.begv(), buffer.zv()), (9, 13));
+        buffer.widen();
+        assert!(!buffer.is_narrowed());
+        buffer.delete_range(10, 12).unwrap();
+        buffer.restore_restriction(saved);
+        assert_eq!((buffer.begv(), buffer.zv()), (9, 11));
+        assert_eq!(buffer.substring(buffer.begv(), buffer.zv()).unwrap(), "b!");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut buffer = Buffer::new("hello");
+        assert!(buffer.set_point(6).is_err());
+        assert!(buffer.narrow(3, 2).is_err());
+        assert!(buffer.narrow(2, 10).is_err());
+        assert!(buffer.delete_range(0, 6).is_err());
+        assert!(buffer.slice(4, 9).is_err());
+        assert_eq!(buffer.point(), 0);
+        assert_eq!((buffer.begv(), buffer.zv()), (0, 5));
+        assert_eq!(buffer.substring(0, 5).unwrap(), "hello");
+    }
+
+    #[test]
+    fn cached_positions() {
+        let mut buffer = Buffer::new("αβγ hello ΔΕ world");
+        assert_eq!(buffer.substring(12, 18).unwrap(), " world");
+        assert_eq!(buffer.substring(4, 9).unwrap(), "hello");
+        buffer.set_point(3).unwrap();
+        buffer.insert("ζη");
+        // lookups on both sides of the gap after an insert before the cursor
+        assert_eq!(buffer.substring(12, 14).unwrap(), "ΔΕ");
+        assert_eq!(buffer.substring(0, 6).unwrap(), "αβγζη ");
+        buffer.delete_range(1, 6).unwrap();
+        assert_eq!(buffer.substring(0, 8).unwrap(), "αhello Δ");
+        buffer.set_point(15).unwrap();
+        buffer.insert("!");
+        buffer.delete_range(6, 8).unwrap();
+        assert_eq!(buffer.substring(0, 14).unwrap(), "αhelloΕ world!");
+        assert_eq!(buffer.substring(5, 7).unwrap(), "oΕ");
+    }
+
+    #[test]
+    fn excursion() {
+        let mut buffer = Buffer::new("hello world");
+        buffer.set_point(6).unwrap();
+        let saved = buffer.save_excursion();
+        buffer.set_point(0).unwrap();
+        buffer.insert("oh ");
+        buffer.narrow(0, 5).unwrap();
+        buffer.restore_excursion(saved);
+        assert_eq!(buffer.point(), 5);
+        buffer.widen();
+        let saved = buffer.save_excursion();
+        buffer.delete_range(0, 3).unwrap();
+        buffer.restore_excursion(saved);
+        assert_eq!(buffer.point(), 2);
+    }
+
+    #[test]
+    fn undo() {
+        let mut buffer = Buffer::new("hello");
+        buffer.insert("ab");
+        buffer.insert("c");
+        buffer.undo_boundary();
+        buffer.set_point(5).unwrap();
+        buffer.delete_range(1, 5).unwrap();
+        assert_eq!(
+            buffer.take_undo(),
+            vec![
+                Undo::Insert(0, 3),
+                Undo::Point(5),
+                Undo::Delete {
+                    text: "bche".into(),
+                    pos: 1,
+                    point_at_end: true
+                },
+            ]
+        );
+        buffer.reset_undo(false, true);
+        buffer.insert("x");
+        assert_eq!(buffer.take_undo(), vec![]);
+    }
+
+    #[test]
+    fn clone_in() {
+        let buffer = LispBuffer::new("foo".into());
+        let marker = LispMarker::new();
+        marker.set_insertion_type(true);
+        marker.set(&buffer, 0).unwrap();
+        let block = Block::new_local();
+        let copy = buffer.clone_in(&block).untag();
+        assert_eq!(copy, &buffer);
+        copy.lock().unwrap().text.insert("hello");
+        assert_eq!(buffer.lock().unwrap().text.len_chars(), 5);
+        assert_eq!(marker.position(), Some(5));
+        assert!(copy.kill());
+        assert!(!buffer.is_live());
+    }
 }
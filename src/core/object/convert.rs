@@ -4,7 +4,7 @@
 
 use super::{
     super::error::{ArgError, Type, TypeError},
-    nil, qtrue, LispHashTable, LispString, LispVec,
+    nil, qtrue, LispBuffer, LispHashTable, LispMarker, LispOverlay, LispString, LispVec,
 };
 use super::{Gc, Object};
 use super::{GcObj, LispFloat};
@@ -116,6 +116,9 @@ define_unbox!(Float, &'ob LispFloat);
 define_unbox!(HashTable, &'ob LispHashTable);
 define_unbox!(String, &'ob LispString);
 define_unbox!(Vec, &'ob LispVec);
+define_unbox!(Buffer, &'ob LispBuffer);
+define_unbox!(Marker, &'ob LispMarker);
+define_unbox!(Overlay, &'ob LispOverlay);
 define_unbox!(Symbol, Symbol<'ob>);
 
 impl<'ob, T> From<Option<T>> for GcObj<'ob>
@@ -36,7 +36,12 @@ impl GcManaged for LispFloat {
 impl Display for LispFloat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let float = self.float;
-        if float.fract() == 0.0_f64 {
+        let sign = if float.is_sign_negative() { "-" } else { "" };
+        if float.is_nan() {
+            write!(f, "{sign}0.0e+NaN")
+        } else if float.is_infinite() {
+            write!(f, "{sign}1.0e+INF")
+        } else if float.fract() == 0.0_f64 {
             write!(f, "{float:.1}")
         } else {
             write!(f, "{float}")
@@ -3,9 +3,9 @@ use super::{
         error::ArgError,
         gc::{Block, Context},
     },
-    display_slice, nil, CloneIn, IntoObject, LispString, LispVec,
+    nil, print_object, CloneIn, IntoObject, LispString, LispVec, PrintOptions, TagType,
 };
-use super::{GcObj, WithLifetime};
+use super::{GcObj, Object, WithLifetime};
 use crate::core::gc::{GcManaged, GcMark, Rt};
 use anyhow::{bail, ensure, Result};
 use fn_macros::Trace;
@@ -94,10 +94,7 @@ impl GcManaged for ByteFn {
 
 impl Display for ByteFn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let args = &self.args;
-        let code = display_slice(self.op_codes);
-        let consts = display_slice(self.constants);
-        write!(f, "#[{args:?} {code} [{consts}]]")
+        print_object(self.tag().into(), PrintOptions::default(), f)
     }
 }
 
@@ -195,13 +192,13 @@ impl FnArgs {
     /// If a function has 3 required args and 2 optional, and it is called with
     /// 4 arguments, then 1 will be returned. Indicating that 1 additional `nil`
     /// argument should be added to the stack.
-    pub(crate) fn num_of_fill_args(self, args: u16, name: &str) -> Result<u16> {
+    pub(crate) fn num_of_fill_args(self, args: u16, function: Object) -> Result<u16> {
         if args < self.required {
-            bail!(ArgError::new(self.required, args, name));
+            bail!(ArgError::new(self.required, args, function));
         }
         let total = self.required + self.optional;
         if !self.rest && (args > total) {
-            bail!(ArgError::new(total, args, name));
+            bail!(ArgError::new(total, args, function));
         }
         Ok(total.saturating_sub(args))
     }
@@ -222,14 +219,14 @@ define_unbox!(SubrFn, Func, &'ob SubrFn);
 
 impl SubrFn {
     pub(crate) fn call<'ob>(
-        &self,
+        &'static self,
         args: &mut Rt<Vec<GcObj<'static>>>,
         env: &mut Rt<crate::core::env::Env>,
         cx: &'ob mut Context,
     ) -> Result<GcObj<'ob>> {
         {
             let arg_cnt = args.len() as u16;
-            let fill_args = self.args.num_of_fill_args(arg_cnt, self.name)?;
+            let fill_args = self.args.num_of_fill_args(arg_cnt, Object::SubrFn(self))?;
             for _ in 0..fill_args {
                 args.push(nil());
             }
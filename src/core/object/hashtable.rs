@@ -1,4 +1,6 @@
-use super::{CloneIn, Gc, GcObj, IntoObject, MutObjCell, ObjCell};
+use super::{
+    print_object, CloneIn, Gc, GcObj, IntoObject, MutObjCell, ObjCell, PrintOptions, TagType,
+};
 use crate::core::gc::{Context, Rt};
 use crate::{
     core::gc::{GcManaged, GcMark, Trace},
@@ -181,6 +183,6 @@ impl<'rt> StreamingIterator for HashTableStreamIter<'rt> {
 
 impl Display for LispHashTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        print_object(self.tag().into(), PrintOptions::default(), f)
     }
 }
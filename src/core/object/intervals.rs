@@ -0,0 +1,410 @@
+//! Text properties shared by strings and buffers.
+use super::{CloneIn, GcObj, RawObj, WithLifetime};
+use crate::core::gc::{Block, Context, Trace};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+type Plist = Vec<(GcObj<'static>, GcObj<'static>)>;
+
+#[derive(Debug, Clone)]
+struct Interval {
+    end: usize,
+    /// Properties are compared with `eq`, and each appears at most once.
+    plist: Plist,
+}
+
+/// The text properties of a string or buffer. The text is divided into
+/// non-overlapping intervals, keyed by their start position, that each have a
+/// property list. Text outside of any interval has no properties. Positions
+/// are character offsets from the start of the text.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IntervalTree {
+    intervals: BTreeMap<usize, Interval>,
+}
+
+fn plist_eq(a: &Plist, b: &Plist) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(k, v)| b.iter().any(|(k2, v2)| k.ptr_eq(*k2) && v.ptr_eq(*v2)))
+}
+
+impl IntervalTree {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Iterate over the intervals that have properties, as `(start, end,
+    /// plist)`.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, usize, &[(GcObj<'_>, GcObj<'_>)])> {
+        self.intervals
+            .iter()
+            .map(|(start, iv)| (*start, iv.end, &iv.plist[..]))
+    }
+
+    fn find(&self, pos: usize) -> Option<(usize, &Interval)> {
+        self.intervals
+            .range(..=pos)
+            .next_back()
+            .filter(|(_, iv)| pos < iv.end)
+            .map(|(start, iv)| (*start, iv))
+    }
+
+    /// The properties of the character at `pos`.
+    pub(crate) fn properties_at<'ob>(
+        &self,
+        pos: usize,
+        _cx: &'ob Context,
+    ) -> Vec<(GcObj<'ob>, GcObj<'ob>)> {
+        match self.find(pos) {
+            Some((_, iv)) => iv
+                .plist
+                .iter()
+                .map(|x| unsafe { x.with_lifetime() })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The value of `prop` for the character at `pos`.
+    pub(crate) fn get<'ob>(
+        &self,
+        pos: usize,
+        prop: GcObj,
+        _cx: &'ob Context,
+    ) -> Option<GcObj<'ob>> {
+        self.get_static(pos, prop)
+            .map(|x| unsafe { x.with_lifetime() })
+    }
+
+    fn get_static(&self, pos: usize, prop: GcObj) -> Option<GcObj<'static>> {
+        let (_, iv) = self.find(pos)?;
+        iv.plist.iter().find(|(k, _)| k.ptr_eq(prop)).map(|x| x.1)
+    }
+
+    /// Make `pos` the start of an interval if it is inside one.
+    fn split(&mut self, pos: usize) {
+        let Some((start, iv)) = self.find(pos) else {
+            return;
+        };
+        if start == pos {
+            return;
+        }
+        let right = iv.clone();
+        self.intervals.get_mut(&start).unwrap().end = pos;
+        self.intervals.insert(pos, right);
+    }
+
+    /// Remove intervals with no properties and merge neighbors with the same
+    /// properties.
+    fn normalize(&mut self) {
+        let mut merged: BTreeMap<usize, Interval> = BTreeMap::new();
+        let mut last: Option<(usize, Interval)> = None;
+        for (start, iv) in std::mem::take(&mut self.intervals) {
+            if iv.plist.is_empty() || start == iv.end {
+                continue;
+            }
+            match &mut last {
+                Some((_, prev)) if prev.end == start && plist_eq(&prev.plist, &iv.plist) => {
+                    prev.end = iv.end;
+                }
+                _ => {
+                    if let Some((start, prev)) = last.replace((start, iv)) {
+                        merged.insert(start, prev);
+                    }
+                }
+            }
+        }
+        if let Some((start, prev)) = last {
+            merged.insert(start, prev);
+        }
+        self.intervals = merged;
+    }
+
+    /// Call `f` on the property list of every part of the text between `beg`
+    /// and `end`. Returns true if any of the calls did.
+    fn modify(&mut self, beg: usize, end: usize, mut f: impl FnMut(&mut Plist) -> bool) -> bool {
+        if beg >= end {
+            return false;
+        }
+        self.split(beg);
+        self.split(end);
+        // cover the gaps between intervals so that they can be modified too
+        let mut gaps = Vec::new();
+        let mut pos = beg;
+        for (start, iv) in self.intervals.range(beg..end) {
+            if *start > pos {
+                gaps.push((pos, *start));
+            }
+            pos = iv.end;
+        }
+        if pos < end {
+            gaps.push((pos, end));
+        }
+        for (start, end) in gaps {
+            let plist = Vec::new();
+            self.intervals.insert(start, Interval { end, plist });
+        }
+        let mut changed = false;
+        for (_, iv) in self.intervals.range_mut(beg..end) {
+            changed |= f(&mut iv.plist);
+        }
+        self.normalize();
+        changed
+    }
+
+    /// Set `prop` to `value` between `beg` and `end`. Returns true if any
+    /// property changed.
+    pub(crate) fn put(&mut self, beg: usize, end: usize, prop: GcObj, value: GcObj) -> bool {
+        self.add(beg, end, &[(prop, value)])
+    }
+
+    /// Set each property in `props` between `beg` and `end`. Returns true if
+    /// any property changed.
+    pub(crate) fn add(&mut self, beg: usize, end: usize, props: &[(GcObj, GcObj)]) -> bool {
+        self.modify(beg, end, |plist| {
+            let mut changed = false;
+            for (prop, value) in props {
+                let value = unsafe { value.with_lifetime() };
+                match plist.iter_mut().find(|(k, _)| k.ptr_eq(*prop)) {
+                    Some((_, v)) if v.ptr_eq(value) => {}
+                    Some((_, v)) => {
+                        *v = value;
+                        changed = true;
+                    }
+                    None => {
+                        plist.push((unsafe { prop.with_lifetime() }, value));
+                        changed = true;
+                    }
+                }
+            }
+            changed
+        })
+    }
+
+    /// Remove each of `props` between `beg` and `end`. Returns true if any
+    /// property was removed.
+    pub(crate) fn remove(&mut self, beg: usize, end: usize, props: &[GcObj]) -> bool {
+        self.modify(beg, end, |plist| {
+            let len = plist.len();
+            plist.retain(|(k, _)| !props.iter().any(|x| x.ptr_eq(*k)));
+            len != plist.len()
+        })
+    }
+
+    /// Replace all properties between `beg` and `end` with `props`.
+    pub(crate) fn set(&mut self, beg: usize, end: usize, props: &[(GcObj, GcObj)]) {
+        let props: Plist = props.iter().map(|x| unsafe { x.with_lifetime() }).collect();
+        self.modify(beg, end, |plist| {
+            plist.clone_from(&props);
+            true
+        });
+    }
+
+    /// The first position after `pos` where the value of `prop` changes. Returns
+    /// `None` if it does not change before `end`, the end of the text.
+    pub(crate) fn next_change(&self, pos: usize, prop: GcObj, end: usize) -> Option<usize> {
+        let value = self.get_static(pos, prop);
+        let mut pos = pos;
+        loop {
+            // the next interval boundary
+            let containing = self.find(pos).map(|(_, iv)| iv.end);
+            let following = self
+                .intervals
+                .range(pos + 1..)
+                .next()
+                .map(|(start, _)| *start);
+            pos = match (containing, following) {
+                (Some(x), Some(y)) => x.min(y),
+                (x, y) => x.or(y)?,
+            };
+            if pos >= end {
+                return None;
+            }
+            let new = self.get_static(pos, prop);
+            let same = match (value, new) {
+                (Some(x), Some(y)) => x.ptr_eq(y),
+                (x, y) => x.is_none() && y.is_none(),
+            };
+            if !same {
+                return Some(pos);
+            }
+        }
+    }
+
+    /// The properties between `beg` and `end`, shifted to start at 0.
+    pub(crate) fn slice(&self, beg: usize, end: usize) -> Self {
+        let mut intervals = BTreeMap::new();
+        let first = self.find(beg).map_or(beg, |(start, _)| start);
+        for (start, iv) in self.intervals.range(first..end) {
+            let new = Interval {
+                end: iv.end.min(end) - beg,
+                plist: iv.plist.clone(),
+            };
+            intervals.insert((*start).max(beg) - beg, new);
+        }
+        Self { intervals }
+    }
+
+    /// Replace the properties of the text starting at `offset` with those of
+    /// `other`.
+    pub(crate) fn graft(&mut self, other: &Self, offset: usize) {
+        for (start, iv) in &other.intervals {
+            self.modify(start + offset, iv.end + offset, |plist| {
+                plist.clone_from(&iv.plist);
+                true
+            });
+        }
+    }
+
+    /// Adjust for `len` characters inserted at `pos`. The new text has no
+    /// properties.
+    pub(crate) fn insert(&mut self, pos: usize, len: usize) {
+        if len == 0 || self.is_empty() {
+            return;
+        }
+        self.split(pos);
+        for (start, mut iv) in self.intervals.split_off(&pos) {
+            iv.end += len;
+            self.intervals.insert(start + len, iv);
+        }
+    }
+
+    /// Adjust for the text between `beg` and `end` being deleted.
+    pub(crate) fn delete(&mut self, beg: usize, end: usize) {
+        if beg >= end || self.is_empty() {
+            return;
+        }
+        self.split(beg);
+        self.split(end);
+        let tail = self.intervals.split_off(&beg);
+        let len = end - beg;
+        for (start, mut iv) in tail {
+            if start >= end {
+                iv.end -= len;
+                self.intervals.insert(start - len, iv);
+            }
+        }
+        self.normalize();
+    }
+
+    /// Compare the properties of two texts. Property values are compared with
+    /// `equal`.
+    pub(crate) fn equal(&self, other: &Self) -> bool {
+        self.intervals.len() == other.intervals.len()
+            && self.iter().zip(other.iter()).all(|(a, b)| {
+                a.0 == b.0
+                    && a.1 == b.1
+                    && a.2.len() == b.2.len()
+                    && a.2
+                        .iter()
+                        .all(|(k, v)| b.2.iter().any(|(k2, v2)| k.ptr_eq(*k2) && v == v2))
+            })
+    }
+
+    pub(crate) fn clone_in<const C: bool>(&self, bk: &Block<C>) -> Self {
+        let intervals = self
+            .intervals
+            .iter()
+            .map(|(start, iv)| {
+                let plist = iv
+                    .plist
+                    .iter()
+                    .map(|(k, v)| unsafe {
+                        (
+                            k.clone_in(bk).with_lifetime(),
+                            v.clone_in(bk).with_lifetime(),
+                        )
+                    })
+                    .collect();
+                (*start, Interval { end: iv.end, plist })
+            })
+            .collect();
+        Self { intervals }
+    }
+}
+
+impl Trace for IntervalTree {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        for iv in self.intervals.values() {
+            for (k, v) in &iv.plist {
+                for obj in [k, v] {
+                    if obj.is_markable() {
+                        stack.push(obj.into_raw());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Prints the intervals in the `#("str" ...)` read syntax, without the string.
+impl Display for IntervalTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (start, end, plist) in self.iter() {
+            write!(f, " {start} {end} (")?;
+            for (idx, (k, v)) in plist.iter().enumerate() {
+                if idx != 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{k} {v}")?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::intern;
+    use crate::core::gc::RootSet;
+
+    #[test]
+    fn properties() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let face = intern("face", cx).into();
+        let bold = cx.add("bold");
+        let mut tree = IntervalTree::default();
+        assert!(tree.put(2, 6, face, bold));
+        assert!(!tree.put(3, 5, face, bold));
+        assert_eq!(tree.iter().count(), 1);
+        assert_eq!(tree.get(4, face, cx), Some(bold));
+        assert_eq!(tree.get(6, face, cx), None);
+        assert_eq!(tree.next_change(0, face, 10), Some(2));
+        assert_eq!(tree.next_change(2, face, 10), Some(6));
+        assert_eq!(tree.next_change(6, face, 10), None);
+        assert_eq!(tree.next_change(2, face, 6), None);
+
+        assert!(tree.remove(3, 4, &[face]));
+        assert_eq!(tree.iter().count(), 2);
+        tree.put(3, 4, face, bold);
+        assert_eq!(tree.iter().count(), 1);
+
+        // edits
+        tree.insert(4, 2);
+        assert_eq!(tree.get(4, face, cx), None);
+        assert_eq!(tree.next_change(4, face, 10), Some(6));
+        tree.delete(3, 7);
+        assert_eq!(
+            tree.iter().map(|x| (x.0, x.1)).collect::<Vec<_>>(),
+            vec![(2, 4)]
+        );
+
+        let slice = tree.slice(3, 5);
+        assert_eq!(
+            slice.iter().map(|x| (x.0, x.1)).collect::<Vec<_>>(),
+            vec![(0, 1)]
+        );
+        let mut other = IntervalTree::default();
+        other.graft(&slice, 3);
+        assert_eq!(
+            other.iter().map(|x| (x.0, x.1)).collect::<Vec<_>>(),
+            vec![(3, 4)]
+        );
+        assert!(!other.equal(&tree));
+        other.graft(&tree, 0);
+        assert!(other.equal(&tree));
+    }
+}
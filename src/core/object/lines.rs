@@ -0,0 +1,143 @@
+//! An index of the line starts in a buffer.
+
+/// The character positions of every newline in a buffer. Like the text itself,
+/// the index is split by a gap. Newlines before the gap are stored as their
+/// position, and newlines after it as their distance from the end of the text.
+/// This means an edit only has to update the index near the gap, which is
+/// moved to the edit first. Moving the gap costs time proportional to the
+/// number of newlines it moves over, and looking up a line is a binary search.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineIndex {
+    /// The positions of the newlines before the gap in ascending order.
+    before: Vec<usize>,
+    /// The distances from the end of the text of the newlines after the gap, in
+    /// ascending order. The last element is the newline closest to the gap.
+    after: Vec<usize>,
+    /// The number of characters in the text.
+    len: usize,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut index = Self::default();
+        index.insert(0, text);
+        index
+    }
+
+    /// Move the gap so that it is at `pos`.
+    fn move_gap(&mut self, pos: usize) {
+        while let Some(&last) = self.before.last() {
+            if last < pos {
+                break;
+            }
+            self.before.pop();
+            self.after.push(self.len - last);
+        }
+        while let Some(&last) = self.after.last() {
+            if self.len - last >= pos {
+                break;
+            }
+            self.after.pop();
+            self.before.push(self.len - last);
+        }
+    }
+
+    /// Adjust for `text` inserted at `pos`.
+    pub(crate) fn insert(&mut self, pos: usize, text: &str) {
+        self.move_gap(pos);
+        let mut len = 0;
+        for chr in text.chars() {
+            if chr == '\n' {
+                self.before.push(pos + len);
+            }
+            len += 1;
+        }
+        self.len += len;
+    }
+
+    /// Adjust for the text between `beg` and `end` being deleted.
+    pub(crate) fn delete(&mut self, beg: usize, end: usize) {
+        if beg >= end {
+            return;
+        }
+        self.move_gap(beg);
+        while let Some(&last) = self.after.last() {
+            if self.len - last >= end {
+                break;
+            }
+            self.after.pop();
+        }
+        self.len -= end - beg;
+    }
+
+    /// The number of newlines before `pos`. This is the zero-based line number
+    /// of the line containing `pos`.
+    pub(crate) fn line_at(&self, pos: usize) -> usize {
+        let before = self.before.partition_point(|&x| x < pos);
+        if before < self.before.len() {
+            return before;
+        }
+        // newlines after the gap are before `pos` if their distance from the
+        // end is greater than that of `pos`
+        let distance = self.len.saturating_sub(pos);
+        let after = self.after.len() - self.after.partition_point(|&x| x <= distance);
+        before + after
+    }
+
+    /// The position of the newline that ends line `line`, if it has one.
+    pub(crate) fn newline(&self, line: usize) -> Option<usize> {
+        match line.checked_sub(self.before.len()) {
+            None => Some(self.before[line]),
+            Some(idx) => {
+                let idx = self.after.len().checked_sub(idx + 1)?;
+                Some(self.len - self.after[idx])
+            }
+        }
+    }
+
+    /// The position where line `line` starts, if the text has that many lines.
+    pub(crate) fn line_start(&self, line: usize) -> Option<usize> {
+        match line.checked_sub(1) {
+            None => Some(0),
+            Some(prev) => self.newline(prev).map(|x| x + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn newlines(index: &LineIndex) -> Vec<usize> {
+        (0..index.line_at(index.len))
+            .map(|x| index.newline(x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_line_index() {
+        let mut index = LineIndex::new("foo\nbar\nbaz");
+        assert_eq!(newlines(&index), vec![3, 7]);
+        assert_eq!(index.line_at(3), 0);
+        assert_eq!(index.line_at(4), 1);
+        assert_eq!(index.line_at(11), 2);
+        assert_eq!(index.line_start(2), Some(8));
+        assert_eq!(index.line_start(3), None);
+
+        index.insert(5, "\nλ\n");
+        assert_eq!(newlines(&index), vec![3, 5, 7, 10]);
+        index.insert(0, "\n");
+        assert_eq!(newlines(&index), vec![0, 4, 6, 8, 11]);
+        assert_eq!(index.line_at(7), 3);
+        assert_eq!(index.line_at(12), 5);
+
+        index.delete(4, 7);
+        assert_eq!(newlines(&index), vec![0, 5, 8]);
+        index.delete(8, 12);
+        assert_eq!(newlines(&index), vec![0, 5]);
+        assert_eq!(index.line_at(8), 2);
+        index.delete(0, 8);
+        assert_eq!(newlines(&index), Vec::<usize>::new());
+        assert_eq!(index.line_at(0), 0);
+    }
+}
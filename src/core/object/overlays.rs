@@ -0,0 +1,492 @@
+//! The overlays of a buffer.
+use super::{Gc, LispOverlay, WithLifetime};
+use crate::core::gc::Context;
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    overlay: Gc<&'static LispOverlay>,
+    begin: usize,
+    end: usize,
+    /// The greatest `end` in this subtree.
+    limit: usize,
+    /// A shift that still has to be applied to every position in this
+    /// subtree, including this node's.
+    offset: isize,
+    priority: u64,
+    left: usize,
+    right: usize,
+    parent: usize,
+}
+
+impl Node {
+    fn shift(&mut self, offset: isize) {
+        self.begin = self.begin.wrapping_add_signed(offset);
+        self.end = self.end.wrapping_add_signed(offset);
+        self.limit = self.limit.wrapping_add_signed(offset);
+    }
+}
+
+/// The overlays in a buffer, kept in a treap ordered by start position. Each
+/// node records the greatest end position in its subtree, so queries can skip
+/// any subtree that ends before the region they are looking at. Like the
+/// interval tree in GNU Emacs, edits to the text shift the positions of a
+/// whole subtree at once by recording an offset that is only pushed down to
+/// the children when they are visited. Adding or removing an overlay takes
+/// logarithmic time, as does an edit apart from the overlays that span it.
+///
+/// Nodes are stored in a vector and refer to each other by index. The index of
+/// an overlay's node stays the same until it is removed, so the overlay keeps
+/// it to find its position.
+#[derive(Debug, Clone)]
+pub(crate) struct OverlayTree {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    root: usize,
+}
+
+impl Default for OverlayTree {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+        }
+    }
+}
+
+/// A well mixed priority for the node at `idx`, so that the tree stays
+/// balanced whatever order the overlays are added in.
+fn priority(idx: usize) -> u64 {
+    let mut z = (idx as u64)
+        .wrapping_add(1)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl OverlayTree {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.root == NONE
+    }
+
+    /// The overlays in the tree, in order of their start positions.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &LispOverlay> {
+        let mut found = Vec::new();
+        self.visit(self.root, 0, usize::MAX, 0, &mut |overlay, _, _| {
+            found.push(overlay.untag());
+        });
+        found.into_iter()
+    }
+
+    /// Add `overlay` covering `begin..end`, returning the index of its node.
+    pub(crate) fn add(&mut self, overlay: &LispOverlay, begin: usize, end: usize) -> usize {
+        let overlay: Gc<&LispOverlay> = overlay.into();
+        let node = Node {
+            overlay: unsafe { overlay.with_lifetime() },
+            begin,
+            end,
+            limit: end,
+            offset: 0,
+            priority: 0,
+            left: NONE,
+            right: NONE,
+            parent: NONE,
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.nodes[idx].priority = priority(idx);
+        let (left, right) = self.split(self.root, begin, true);
+        let left = self.merge(left, idx);
+        let root = self.merge(left, right);
+        self.set_root(root);
+        idx
+    }
+
+    /// Remove the node at `idx`, which must be in the tree.
+    pub(crate) fn remove(&mut self, idx: usize) {
+        let mut path = Vec::new();
+        let mut parent = self.nodes[idx].parent;
+        while parent != NONE {
+            path.push(parent);
+            parent = self.nodes[parent].parent;
+        }
+        for &node in path.iter().rev() {
+            self.push(node);
+        }
+        self.push(idx);
+        let Node {
+            left,
+            right,
+            parent,
+            ..
+        } = self.nodes[idx];
+        let child = self.merge(left, right);
+        if parent == NONE {
+            self.set_root(child);
+        } else {
+            let node = &mut self.nodes[parent];
+            if node.left == idx {
+                node.left = child;
+            } else {
+                node.right = child;
+            }
+            for node in path {
+                self.pull(node);
+            }
+        }
+        self.free.push(idx);
+    }
+
+    /// The position of the node at `idx`.
+    pub(crate) fn range(&self, idx: usize) -> (usize, usize) {
+        let node = &self.nodes[idx];
+        let mut offset = node.offset;
+        let mut parent = node.parent;
+        while parent != NONE {
+            offset += self.nodes[parent].offset;
+            parent = self.nodes[parent].parent;
+        }
+        (
+            node.begin.wrapping_add_signed(offset),
+            node.end.wrapping_add_signed(offset),
+        )
+    }
+
+    fn set_root(&mut self, root: usize) {
+        self.root = root;
+        if root != NONE {
+            self.nodes[root].parent = NONE;
+        }
+    }
+
+    /// Apply the pending offset of `idx` to itself and hand it on to its
+    /// children.
+    fn push(&mut self, idx: usize) {
+        let node = &mut self.nodes[idx];
+        let offset = std::mem::take(&mut node.offset);
+        if offset == 0 {
+            return;
+        }
+        node.shift(offset);
+        let (left, right) = (node.left, node.right);
+        for child in [left, right] {
+            if child != NONE {
+                self.nodes[child].offset += offset;
+            }
+        }
+    }
+
+    /// Update the limit of `idx` and the parent of its children after they
+    /// have changed. The node must have no pending offset.
+    fn pull(&mut self, idx: usize) {
+        let Node {
+            left, right, end, ..
+        } = self.nodes[idx];
+        let mut limit = end;
+        for child in [left, right] {
+            if child != NONE {
+                let node = &mut self.nodes[child];
+                node.parent = idx;
+                limit = limit.max(node.limit.wrapping_add_signed(node.offset));
+            }
+        }
+        self.nodes[idx].limit = limit;
+    }
+
+    /// Split the subtree at `idx` into the nodes that start before `pos` and
+    /// the rest. If `inclusive` is set the nodes that start at `pos` go in the
+    /// first part.
+    fn split(&mut self, idx: usize, pos: usize, inclusive: bool) -> (usize, usize) {
+        if idx == NONE {
+            return (NONE, NONE);
+        }
+        self.push(idx);
+        let begin = self.nodes[idx].begin;
+        if begin < pos || (inclusive && begin == pos) {
+            let (left, right) = self.split(self.nodes[idx].right, pos, inclusive);
+            self.nodes[idx].right = left;
+            self.pull(idx);
+            (idx, right)
+        } else {
+            let (left, right) = self.split(self.nodes[idx].left, pos, inclusive);
+            self.nodes[idx].left = right;
+            self.pull(idx);
+            (left, idx)
+        }
+    }
+
+    /// Join two subtrees, where every node in `a` starts at or before every
+    /// node in `b`.
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NONE {
+            return b;
+        }
+        if b == NONE {
+            return a;
+        }
+        if self.nodes[a].priority > self.nodes[b].priority {
+            self.push(a);
+            let right = self.merge(self.nodes[a].right, b);
+            self.nodes[a].right = right;
+            self.pull(a);
+            a
+        } else {
+            self.push(b);
+            let left = self.merge(a, self.nodes[b].left);
+            self.nodes[b].left = left;
+            self.pull(b);
+            b
+        }
+    }
+
+    /// Call `f` on every node in the subtree at `idx` that ends at or after
+    /// `min_end`. `f` must not change the order of the nodes.
+    fn update(&mut self, idx: usize, min_end: usize, f: &impl Fn(&mut Node)) {
+        if idx == NONE {
+            return;
+        }
+        self.push(idx);
+        if self.nodes[idx].limit < min_end {
+            return;
+        }
+        let Node { left, right, .. } = self.nodes[idx];
+        self.update(left, min_end, f);
+        self.update(right, min_end, f);
+        if self.nodes[idx].end >= min_end {
+            f(&mut self.nodes[idx]);
+        }
+        self.pull(idx);
+    }
+
+    /// Remove every node from the subtree at `idx`, adding them to `nodes`.
+    fn take_nodes(&mut self, idx: usize, nodes: &mut Vec<usize>) {
+        if idx == NONE {
+            return;
+        }
+        self.push(idx);
+        let node = &mut self.nodes[idx];
+        let (left, right) = (node.left, node.right);
+        node.left = NONE;
+        node.right = NONE;
+        node.limit = node.end;
+        nodes.push(idx);
+        self.take_nodes(left, nodes);
+        self.take_nodes(right, nodes);
+    }
+
+    /// Call `f` on every overlay in the subtree at `idx` that starts at or
+    /// before `max_start` and ends at or after `min_end`, in order of their
+    /// start positions. `offset` is the pending offset of the ancestors.
+    fn visit(
+        &self,
+        idx: usize,
+        offset: isize,
+        max_start: usize,
+        min_end: usize,
+        f: &mut impl FnMut(Gc<&'static LispOverlay>, usize, usize),
+    ) {
+        if idx == NONE {
+            return;
+        }
+        let node = &self.nodes[idx];
+        let offset = offset + node.offset;
+        if node.limit.wrapping_add_signed(offset) < min_end {
+            return;
+        }
+        self.visit(node.left, offset, max_start, min_end, f);
+        let begin = node.begin.wrapping_add_signed(offset);
+        if begin > max_start {
+            return;
+        }
+        let end = node.end.wrapping_add_signed(offset);
+        if end >= min_end {
+            f(node.overlay, begin, end);
+        }
+        self.visit(node.right, offset, max_start, min_end, f);
+    }
+
+    /// The overlays that overlap the region between `beg` and `end`. Empty
+    /// overlays are included if they are at `beg`, inside the region, or at
+    /// `end` when it is `zv`, the end of the accessible portion of the buffer.
+    pub(crate) fn overlays_in<'ob>(
+        &self,
+        beg: usize,
+        end: usize,
+        zv: usize,
+        _cx: &'ob Context,
+    ) -> Vec<&'ob LispOverlay> {
+        let mut found = Vec::new();
+        self.visit(self.root, 0, end, beg, &mut |overlay, start, ov_end| {
+            let include = if start == ov_end {
+                (beg..end).contains(&start) || start == beg || (start == end && end == zv)
+            } else {
+                start < end && ov_end > beg
+            };
+            if include {
+                let overlay: Gc<&'ob LispOverlay> = unsafe { overlay.with_lifetime() };
+                found.push(overlay.untag());
+            }
+        });
+        found
+    }
+
+    /// The overlays that contain the character at `pos`.
+    pub(crate) fn overlays_at<'ob>(&self, pos: usize, _cx: &'ob Context) -> Vec<&'ob LispOverlay> {
+        let mut found = Vec::new();
+        self.visit(self.root, 0, pos, pos + 1, &mut |overlay, _, _| {
+            let overlay: Gc<&'ob LispOverlay> = unsafe { overlay.with_lifetime() };
+            found.push(overlay.untag());
+        });
+        found
+    }
+
+    /// Adjust for `len` characters inserted at `pos`.
+    pub(crate) fn insert(&mut self, pos: usize, len: usize) {
+        if len == 0 || self.is_empty() {
+            return;
+        }
+        let (before, rest) = self.split(self.root, pos, false);
+        let (at, after) = self.split(rest, pos, true);
+        // everything that starts after `pos` moves
+        if after != NONE {
+            self.nodes[after].offset += len as isize;
+        }
+        let moves = |x: usize, advance: bool| x > pos || (x == pos && advance);
+        self.update(before, pos, &|node| {
+            if moves(node.end, node.overlay.untag().advances().1) {
+                node.end += len;
+            }
+        });
+        // overlays that start at `pos` may move past the others that do
+        let mut nodes = Vec::new();
+        self.take_nodes(at, &mut nodes);
+        let (mut stay, mut advance) = (NONE, NONE);
+        for idx in nodes {
+            let node = &mut self.nodes[idx];
+            let (front_advance, rear_advance) = node.overlay.untag().advances();
+            if moves(node.end, rear_advance) {
+                node.end += len;
+            }
+            if front_advance {
+                node.begin = (pos + len).min(node.end);
+            }
+            node.limit = node.end;
+            if node.begin == pos {
+                stay = self.merge(stay, idx);
+            } else {
+                advance = self.merge(advance, idx);
+            }
+        }
+        let root = self.merge(before, stay);
+        let root = self.merge(root, advance);
+        let root = self.merge(root, after);
+        self.set_root(root);
+    }
+
+    /// Adjust for the text between `beg` and `end` being deleted.
+    pub(crate) fn delete(&mut self, beg: usize, end: usize) {
+        if beg >= end || self.is_empty() {
+            return;
+        }
+        let adjust = move |pos: usize| {
+            if pos >= end {
+                pos - (end - beg)
+            } else {
+                pos.min(beg)
+            }
+        };
+        let (before, rest) = self.split(self.root, beg, false);
+        let (inside, after) = self.split(rest, end, false);
+        if after != NONE {
+            self.nodes[after].offset -= (end - beg) as isize;
+        }
+        self.update(before, beg + 1, &|node| node.end = adjust(node.end));
+        self.update(inside, 0, &|node| {
+            node.begin = beg;
+            node.end = adjust(node.end);
+        });
+        let root = self.merge(before, inside);
+        let root = self.merge(root, after);
+        self.set_root(root);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+
+    #[test]
+    fn edits() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let mut tree = OverlayTree::default();
+        let mut expect = Vec::new();
+        for i in 0..200 {
+            let (front_advance, rear_advance) = (i % 3 == 0, i % 2 == 0);
+            let overlay = cx.add_as(LispOverlay::new(front_advance, rear_advance));
+            let overlay = overlay.untag();
+            let (begin, end) = ((i * 7) % 100, (i * 7) % 100 + i % 5);
+            let idx = tree.add(overlay, begin, end);
+            expect.push((idx, front_advance, rear_advance, begin, end));
+        }
+        for (i, (idx, ..)) in expect.clone().into_iter().enumerate() {
+            if i % 4 == 1 {
+                tree.remove(idx);
+                expect.retain(|x| x.0 != idx);
+            }
+        }
+        let edits = [
+            (true, 10, 3),
+            (false, 20, 25),
+            (true, 0, 5),
+            (false, 50, 51),
+            (true, 47, 2),
+        ];
+        for (insert, pos, arg) in edits {
+            if insert {
+                tree.insert(pos, arg);
+            } else {
+                tree.delete(pos, arg);
+            }
+            for (_, front, rear, begin, end) in &mut expect {
+                if insert {
+                    let moves = |x: usize, advance: bool| x > pos || (x == pos && advance);
+                    if moves(*end, *rear) {
+                        *end += arg;
+                    }
+                    if moves(*begin, *front) {
+                        *begin = (*begin + arg).min(*end);
+                    }
+                } else {
+                    let adjust = |x: usize| {
+                        if x >= arg {
+                            x - (arg - pos)
+                        } else {
+                            x.min(pos)
+                        }
+                    };
+                    (*begin, *end) = (adjust(*begin), adjust(*end));
+                }
+            }
+            for (idx, _, _, begin, end) in &expect {
+                assert_eq!(tree.range(*idx), (*begin, *end));
+            }
+            assert_eq!(tree.iter().count(), expect.len());
+        }
+        let at = tree.overlays_at(30, cx).len();
+        let naive = expect.iter().filter(|x| x.3 <= 30 && 30 < x.4).count();
+        assert_eq!(at, naive);
+    }
+}
@@ -0,0 +1,556 @@
+//! Printing objects that contain other objects.
+use super::{ByteFn, GcObj, LispHashTable, LispString, ObjCell, Object, WithLifetime};
+use crate::core::env::{sym, Symbol};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+/// Options that control how objects are printed. The defaults are how objects
+/// are displayed from Rust.
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct PrintOptions {
+    /// Label shared and circular structure with `#N=` and refer back to it with
+    /// `#N#`, like `print-circle`.
+    pub(crate) circle: bool,
+    /// Print objects so that they can be read back, like `prin1`. Otherwise
+    /// strings are printed without quotes and symbols without escapes, like
+    /// `princ`.
+    pub(crate) escape: bool,
+    /// The number of elements of a list or vector to print before `...`, like
+    /// `print-length`.
+    pub(crate) length: Option<usize>,
+    /// How deeply lists and vectors are printed before they are shown as
+    /// `...`, like `print-level`.
+    pub(crate) level: Option<usize>,
+    /// Print newlines and form feeds in strings as `\n` and `\f`, like
+    /// `print-escape-newlines`.
+    pub(crate) escape_newlines: bool,
+    /// Print `(quote X)` as `'X`, and likewise for `function` and the
+    /// backquote forms, like `print-quoted`.
+    pub(crate) quoted: bool,
+    /// How floats are printed, like `float-output-format`. If `None` they use
+    /// the fewest digits that read back as the same float.
+    pub(crate) float_format: Option<FloatFormat>,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            circle: false,
+            escape: true,
+            length: None,
+            level: None,
+            escape_newlines: false,
+            quoted: false,
+            float_format: None,
+        }
+    }
+}
+
+/// A C-style float format of the form `%.PRECISIONe`, `%.PRECISIONf` or
+/// `%.PRECISIONg`, as used by `float-output-format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FloatFormat {
+    kind: char,
+    precision: Option<usize>,
+}
+
+impl FloatFormat {
+    /// Parse `format`, returning `None` if it is not a valid float format.
+    pub(crate) fn parse(format: &str) -> Option<Self> {
+        let spec = format.strip_prefix('%')?;
+        let (precision, kind) = spec.split_at(spec.len().checked_sub(1)?);
+        let kind = kind
+            .chars()
+            .next()
+            .filter(|x| matches!(x, 'e' | 'f' | 'g'))?;
+        let precision = match precision.strip_prefix('.') {
+            Some(digits) => Some(digits.parse().ok()?),
+            None if precision.is_empty() => None,
+            None => return None,
+        };
+        Some(Self { kind, precision })
+    }
+
+    fn format(self, float: f64) -> String {
+        let precision = self.precision.unwrap_or(6);
+        let string = format_float(self.kind, float, precision, false);
+        // make sure the result reads back as a float
+        if precision > 0 && string.bytes().all(|x| x.is_ascii_digit() || x == b'-') {
+            string + ".0"
+        } else {
+            string
+        }
+    }
+}
+
+/// Format `float` like the C `printf` conversion `kind`, which is one of `e`,
+/// `f` or `g`. If `alternate` is true the result always contains a decimal
+/// point, and `g` keeps its trailing zeros, like the `#` flag.
+pub(crate) fn format_float(kind: char, float: f64, precision: usize, alternate: bool) -> String {
+    let string = match kind {
+        'e' => exponent_notation(float, precision),
+        'f' => format!("{float:.precision$}"),
+        _ => general_notation(float, precision, !alternate),
+    };
+    if alternate && float.is_finite() && !string.contains('.') {
+        // the decimal point goes before the exponent
+        let idx = string.find('e').unwrap_or(string.len());
+        format!("{}.{}", &string[..idx], &string[idx..])
+    } else {
+        string
+    }
+}
+
+/// Format `float` like the C `%.PRECISIONe` format, with a sign and at least
+/// two digits in the exponent.
+fn exponent_notation(float: f64, precision: usize) -> String {
+    let string = format!("{float:.precision$e}");
+    let Some((mantissa, exponent)) = string.split_once('e') else { return string };
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    format!("{mantissa}e{sign}{digits:0>2}")
+}
+
+/// Format `float` like the C `%.PRECISIONg` format. This uses exponent notation
+/// for very large or small numbers, and drops trailing zeros if `trim` is true.
+fn general_notation(float: f64, precision: usize, trim: bool) -> String {
+    let precision = precision.max(1);
+    if float == 0.0 || !float.is_finite() {
+        return format!("{float}");
+    }
+    // the exponent after rounding to `precision` significant digits
+    let rounded = format!("{float:.prec$e}", prec = precision - 1);
+    let exponent: i32 = rounded.split_once('e').map_or(0, |x| x.1.parse().unwrap_or(0));
+    let trim = |string: String| {
+        if trim && string.contains('.') {
+            string.trim_end_matches('0').trim_end_matches('.').to_owned()
+        } else {
+            string
+        }
+    };
+    if exponent < -4 || exponent >= precision as i32 {
+        let string = exponent_notation(float, precision - 1);
+        let (mantissa, exponent) = string.split_once('e').unwrap();
+        format!("{}e{exponent}", trim(mantissa.to_owned()))
+    } else {
+        let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+        trim(format!("{float:.decimals$}"))
+    }
+}
+
+/// True if a symbol named `name` would be read as a number.
+fn looks_like_number(name: &str) -> bool {
+    let body = name.strip_prefix(['+', '-']).unwrap_or(name);
+    let mut prev = ' ';
+    body.contains(|x: char| x.is_ascii_digit())
+        && body.chars().all(|chr| {
+            let valid = chr.is_ascii_digit()
+                || matches!(chr, '.' | 'e' | 'E')
+                || (matches!(chr, '+' | '-') && matches!(prev, 'e' | 'E'));
+            prev = chr;
+            valid
+        })
+}
+
+/// Print `obj` to `out`. Conses, vectors, records, hash tables and byte-code
+/// functions can refer to themselves, so this never follows a cycle. When `print-circle` is off, a reference to an
+/// object that is still being printed is shown as `#N`, where `N` is how deeply
+/// that object is nested, and a list whose tail loops back is ended with `. #N`,
+/// like Emacs does.
+pub(crate) fn print_object(obj: GcObj, options: PrintOptions, out: &mut impl Write) -> fmt::Result {
+    let mut printer = Printer {
+        out,
+        being_printed: Vec::new(),
+        labels: HashMap::new(),
+        next_label: 0,
+        circle: options.circle,
+        options,
+    };
+    if printer.circle {
+        printer.find_shared(obj);
+    }
+    printer.print(obj)
+}
+
+/// Print `obj` to a new string.
+pub(crate) fn print_to_string(obj: GcObj, options: PrintOptions) -> String {
+    let mut string = String::new();
+    print_object(obj, options, &mut string).expect("printing to a string cannot fail");
+    string
+}
+
+/// Objects that can contain other objects, and so are part of a cycle or shared.
+fn is_container(obj: GcObj) -> bool {
+    matches!(
+        obj.untag(),
+        Object::Cons(_)
+            | Object::Vec(_)
+            | Object::Record(_)
+            | Object::HashTable(_)
+            | Object::ByteFn(_)
+    )
+}
+
+struct Printer<'a, W> {
+    out: &'a mut W,
+    /// The containers that are currently being printed, outermost first.
+    being_printed: Vec<*const u8>,
+    /// The containers that are reachable more than once, with the label they
+    /// were given when they were first printed.
+    labels: HashMap<*const u8, Option<usize>>,
+    next_label: usize,
+    circle: bool,
+    options: PrintOptions,
+}
+
+impl<W: Write> Printer<'_, W> {
+    /// Find the containers that can be reached by more than one path from
+    /// `obj`. These are the ones that need a label.
+    fn find_shared(&mut self, obj: GcObj) {
+        let mut seen: HashMap<*const u8, bool> = HashMap::new();
+        let mut stack = vec![obj];
+        while let Some(obj) = stack.pop() {
+            if !is_container(obj) {
+                continue;
+            }
+            if let Some(shared) = seen.get_mut(&obj.into_ptr()) {
+                *shared = true;
+                continue;
+            }
+            seen.insert(obj.into_ptr(), false);
+            match obj.untag() {
+                Object::Cons(cons) => stack.extend([cons.cdr(), cons.car()]),
+                Object::Vec(vec) => stack.extend(vec.iter().rev().map(ObjCell::get)),
+                Object::Record(record) => stack.extend(record.iter().rev().map(ObjCell::get)),
+                Object::HashTable(table) => {
+                    for (key, value) in table.borrow().iter() {
+                        // SAFETY: the entries live as long as the table
+                        stack.extend(unsafe { [value.get().with_lifetime(), key.with_lifetime()] });
+                    }
+                }
+                Object::ByteFn(func) => stack.push(func.constants().into()),
+                _ => unreachable!(),
+            }
+        }
+        self.labels = seen
+            .into_iter()
+            .filter(|(_, shared)| *shared)
+            .map(|(ptr, _)| (ptr, None))
+            .collect();
+    }
+
+    fn print(&mut self, obj: GcObj) -> fmt::Result {
+        if !is_container(obj) {
+            return self.print_atom(obj);
+        }
+        let depth = self.being_printed.len();
+        if self.options.level.is_some_and(|x| depth >= x) {
+            return self.out.write_str("...");
+        }
+        let ptr = obj.into_ptr();
+        if self.circle {
+            match self.labels.get_mut(&ptr) {
+                Some(Some(label)) => return write!(self.out, "#{label}#"),
+                Some(label) => {
+                    self.next_label += 1;
+                    *label = Some(self.next_label);
+                    write!(self.out, "#{}=", self.next_label)?;
+                }
+                None => {}
+            }
+        } else if let Some(depth) = self.being_printed.iter().position(|x| *x == ptr) {
+            return write!(self.out, "#{depth}");
+        }
+        self.being_printed.push(ptr);
+        match obj.untag() {
+            Object::Cons(_) => self.print_list(obj)?,
+            Object::Vec(vec) => {
+                self.out.write_char('[')?;
+                self.print_elements(vec)?;
+                self.out.write_char(']')?;
+            }
+            Object::Record(record) => {
+                self.out.write_str("#s(")?;
+                self.print_elements(record)?;
+                self.out.write_char(')')?;
+            }
+            Object::HashTable(table) => self.print_hash_table(table)?,
+            Object::ByteFn(func) => self.print_byte_fn(func)?,
+            _ => unreachable!(),
+        }
+        self.being_printed.pop();
+        Ok(())
+    }
+
+    fn print_atom(&mut self, obj: GcObj) -> fmt::Result {
+        match obj.untag() {
+            Object::String(string) => self.print_string(string),
+            Object::Symbol(symbol) => self.print_symbol(symbol),
+            Object::Float(float) => match self.options.float_format {
+                Some(format) if float.is_finite() => self.out.write_str(&format.format(**float)),
+                _ => write!(self.out, "{float}"),
+            },
+            _ => write!(self.out, "{obj}"),
+        }
+    }
+
+    fn print_string(&mut self, string: &LispString) -> fmt::Result {
+        if self.options.escape {
+            string.print(self.options.escape_newlines, self.out)
+        } else {
+            write!(self.out, "{}", &**string)
+        }
+    }
+
+    /// Print a symbol, escaping the characters that would otherwise not read
+    /// back as part of it.
+    fn print_symbol(&mut self, symbol: Symbol) -> fmt::Result {
+        let name = symbol.name();
+        if !self.options.escape {
+            return self.out.write_str(name);
+        }
+        if name.is_empty() {
+            return self.out.write_str("##");
+        }
+        if looks_like_number(name) || name == "." {
+            self.out.write_char('\\')?;
+        }
+        for (idx, chr) in name.chars().enumerate() {
+            let special = matches!(
+                chr,
+                '"' | '\\' | '\'' | ';' | '#' | '(' | ')' | ',' | '`' | '[' | ']'
+            );
+            if special || chr <= ' ' || (idx == 0 && chr == '?') {
+                self.out.write_char('\\')?;
+            }
+            self.out.write_char(chr)?;
+        }
+        Ok(())
+    }
+
+    /// The prefix that `list` is abbreviated with if it is a quoted form like
+    /// `(quote X)`.
+    fn quote_prefix(&self, list: GcObj) -> Option<&'static str> {
+        let Object::Cons(cons) = list.untag() else { return None };
+        let Object::Cons(rest) = cons.cdr().untag() else { return None };
+        if !rest.cdr().nil() || (self.circle && self.labels.contains_key(&cons.cdr().into_ptr())) {
+            return None;
+        }
+        let Object::Symbol(head) = cons.car().untag() else { return None };
+        match head {
+            sym::QUOTE => Some("'"),
+            sym::FUNCTION => Some("#'"),
+            sym::BACKQUOTE => Some("`"),
+            sym::UNQUOTE => Some(","),
+            sym::SPLICE => Some(",@"),
+            _ => None,
+        }
+    }
+
+    fn print_list(&mut self, list: GcObj) -> fmt::Result {
+        if self.options.quoted {
+            if let Some(prefix) = self.quote_prefix(list) {
+                self.out.write_str(prefix)?;
+                return self.print(list.as_cons().cdr().as_cons().car());
+            }
+        }
+        self.out.write_char('(')?;
+        let mut tail = list;
+        // the tortoise moves at half the speed of `tail`, so they meet if the
+        // list loops back on itself
+        let mut tortoise = list;
+        let mut count = 0;
+        while let Object::Cons(cons) = tail.untag() {
+            if count > 0 {
+                if self.circle {
+                    if self.labels.contains_key(&tail.into_ptr()) {
+                        self.out.write_str(" . ")?;
+                        self.print(tail)?;
+                        return self.out.write_char(')');
+                    }
+                } else if tail.ptr_eq(tortoise) {
+                    return write!(self.out, " . #{})", count / 2);
+                }
+                self.out.write_char(' ')?;
+            }
+            if self.options.length.is_some_and(|x| count >= x) {
+                return self.out.write_str("...)");
+            }
+            self.print(cons.car())?;
+            count += 1;
+            tail = cons.cdr();
+            if count % 2 == 0 {
+                if let Object::Cons(x) = tortoise.untag() {
+                    tortoise = x.cdr();
+                }
+            }
+        }
+        if !tail.nil() {
+            self.out.write_str(" . ")?;
+            self.print(tail)?;
+        }
+        self.out.write_char(')')
+    }
+
+    fn print_elements(&mut self, elements: &[ObjCell]) -> fmt::Result {
+        for (idx, elem) in elements.iter().enumerate() {
+            if idx > 0 {
+                self.out.write_char(' ')?;
+            }
+            if self.options.length.is_some_and(|x| idx >= x) {
+                return self.out.write_str("...");
+            }
+            self.print(elem.get())?;
+        }
+        Ok(())
+    }
+
+    /// Print a hash table as `#s(hash-table test equal data (KEY VALUE ...))`.
+    fn print_hash_table(&mut self, table: &LispHashTable) -> fmt::Result {
+        self.out.write_str("#s(hash-table test equal")?;
+        let table = table.borrow();
+        if !table.is_empty() {
+            self.out.write_str(" data (")?;
+            for (idx, (key, value)) in table.iter().enumerate() {
+                if idx > 0 {
+                    self.out.write_char(' ')?;
+                }
+                self.print(*key)?;
+                self.out.write_char(' ')?;
+                self.print(value.get())?;
+            }
+            self.out.write_char(')')?;
+        }
+        self.out.write_char(')')
+    }
+
+    /// Print a byte-code function as `#[ARGS CODE CONSTANTS DEPTH]`.
+    fn print_byte_fn(&mut self, func: &ByteFn) -> fmt::Result {
+        let args = func.args.into_arg_spec();
+        write!(self.out, "#[{args} {} ", func.codes())?;
+        self.print(func.constants().into())?;
+        write!(self.out, " {}]", func.depth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::intern;
+    use crate::core::gc::{Context, RootSet};
+    use crate::core::object::nil;
+
+    #[test]
+    fn test_print_cycles() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let circle = PrintOptions {
+            circle: true,
+            ..PrintOptions::default()
+        };
+        let list = list![1, 2, 3; cx];
+        let cons = list.as_cons();
+        assert_eq!(list.to_string(), "(1 2 3)");
+
+        // (1 2 3 1 2 3 ...)
+        let last = cons.cdr().as_cons().cdr().as_cons();
+        last.set_cdr(list).unwrap();
+        assert_eq!(list.to_string(), "(1 2 3 1 2 . #2)");
+        assert_eq!(print_to_string(list, circle), "#1=(1 2 3 . #1#)");
+
+        // a list that contains itself
+        cons.set_car(list).unwrap();
+        last.set_cdr(nil()).unwrap();
+        assert_eq!(list.to_string(), "(#0 2 3)");
+        assert_eq!(print_to_string(list, circle), "#1=(#1# 2 3)");
+
+        let shared = list![1, 2; cx];
+        let vec = cx.add(vec![shared, shared, list![shared; cx]]);
+        assert_eq!(vec.to_string(), "[(1 2) (1 2) ((1 2))]");
+        assert_eq!(print_to_string(vec, circle), "[#1=(1 2) #1# (#1#)]");
+        let Object::Vec(inner) = vec.untag() else {
+            unreachable!()
+        };
+        inner.try_mut().unwrap()[1].set(vec);
+        assert_eq!(vec.to_string(), "[(1 2) #0 ((1 2))]");
+        assert_eq!(print_to_string(vec, circle), "#1=[#2=(1 2) #1# (#2#)]");
+    }
+
+    #[test]
+    fn test_print_options() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let nested = list![1, list![2, list![3; cx]; cx], 4, 5; cx];
+        let limited = PrintOptions {
+            length: Some(2),
+            level: Some(2),
+            ..PrintOptions::default()
+        };
+        assert_eq!(print_to_string(nested, limited), "(1 (2 ...) ...)");
+        let vec = cx.add(vec![cx.add(1), cx.add(2), cx.add(3)]);
+        assert_eq!(print_to_string(vec, limited), "[1 2 ...]");
+
+        let quoted = PrintOptions {
+            quoted: true,
+            ..PrintOptions::default()
+        };
+        let form = list![sym::QUOTE, list![sym::FUNCTION, sym::NIL; cx]; cx];
+        assert_eq!(print_to_string(form, quoted), "'#'nil");
+        assert_eq!(
+            print_to_string(form, PrintOptions::default()),
+            "(quote (function nil))"
+        );
+        let form = list![sym::QUOTE, 1, 2; cx];
+        assert_eq!(print_to_string(form, quoted), "(quote 1 2)");
+
+        let string = cx.add("a\"b\nc");
+        assert_eq!(
+            print_to_string(string, PrintOptions::default()),
+            "\"a\\\"b\nc\""
+        );
+        let escape_newlines = PrintOptions {
+            escape_newlines: true,
+            ..PrintOptions::default()
+        };
+        assert_eq!(print_to_string(string, escape_newlines), "\"a\\\"b\\nc\"");
+        let princ = PrintOptions {
+            escape: false,
+            ..PrintOptions::default()
+        };
+        assert_eq!(print_to_string(string, princ), "a\"b\nc");
+    }
+
+    #[test]
+    fn test_print_symbols() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let print = |name| print_to_string(intern(name, cx).into(), PrintOptions::default());
+        assert_eq!(print("foo-bar"), "foo-bar");
+        assert_eq!(print("a b"), "a\\ b");
+        assert_eq!(print("(x)"), "\\(x\\)");
+        assert_eq!(print("?a"), "\\?a");
+        assert_eq!(print("a?"), "a?");
+        assert_eq!(print("12"), "\\12");
+        assert_eq!(print("-1.5e3"), "\\-1.5e3");
+        assert_eq!(print("1+"), "1+");
+        assert_eq!(print("."), "\\.");
+    }
+
+    #[test]
+    fn test_float_format() {
+        let format = |spec, float| FloatFormat::parse(spec).unwrap().format(float);
+        assert_eq!(format("%.3f", 1.5), "1.500");
+        assert_eq!(format("%.0f", 2.5), "2");
+        assert_eq!(format("%.2e", 1234.5), "1.23e+03");
+        assert_eq!(format("%e", 0.001), "1.000000e-03");
+        assert_eq!(format("%g", 100.0), "100.0");
+        assert_eq!(format("%.3g", 0.000_012_34), "1.23e-05");
+        assert_eq!(format("%g", 0.5), "0.5");
+        assert_eq!(FloatFormat::parse("%d"), None);
+        assert_eq!(FloatFormat::parse("%.xf"), None);
+        assert_eq!(FloatFormat::parse("f"), None);
+    }
+}
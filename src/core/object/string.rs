@@ -1,18 +1,33 @@
-use super::{CloneIn, IntoObject};
-use crate::core::gc::{Block, GcManaged, GcMark};
+use super::{CloneIn, Gc, IntervalTree, IntoObject, RawObj};
+use crate::core::gc::{Block, GcManaged, GcMark, Trace};
 use anyhow::Result;
 use bstr::{BStr, BString, ByteSlice};
-use fn_macros::Trace;
 use std::{
-    fmt::{Debug, Display},
+    borrow::Cow,
+    cell::{Ref, RefCell, RefMut},
+    fmt::{Debug, Display, Write},
     ops::Deref,
 };
 
-#[derive(PartialEq, Eq, Trace)]
 pub(crate) struct LispString {
     gc: GcMark,
-    #[no_trace]
     string: StrType,
+    props: RefCell<IntervalTree>,
+}
+
+// Text properties are ignored by `equal`
+impl PartialEq for LispString {
+    fn eq(&self, other: &Self) -> bool {
+        self.string == other.string
+    }
+}
+
+impl Eq for LispString {}
+
+impl PartialEq<str> for LispString {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
 }
 
 unsafe impl Sync for LispString {}
@@ -21,27 +36,216 @@ unsafe impl Sync for LispString {}
 enum StrType {
     String(String),
     BString(BString),
+    /// A multibyte string that contains raw bytes, see [`RawMultibyte`].
+    Raw(BString),
+}
+
+/// Raw bytes 0x80 to 0xFF are represented by the character codes starting at
+/// `BYTE8_OFFSET + 0x80`, as in GNU Emacs.
+pub(crate) const BYTE8_OFFSET: i64 = 0x3F_FF00;
+
+/// The contents of a multibyte string that contains raw bytes. Like the
+/// internal encoding of GNU Emacs, it is UTF-8 except that the raw byte `b` is
+/// the two bytes `0xC0 | (b >> 6 & 1)` and `0x80 | (b & 0x3F)`, which are not
+/// valid UTF-8.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RawMultibyte(Vec<u8>);
+
+impl RawMultibyte {
+    pub(crate) fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub(crate) fn push(&mut self, chr: char) {
+        self.0
+            .extend_from_slice(chr.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+
+    pub(crate) fn push_raw_byte(&mut self, byte: u8) {
+        self.0
+            .extend_from_slice(&[0xC0 | (byte >> 6 & 1), 0x80 | (byte & 0x3F)]);
+    }
+}
+
+/// A new string built from characters and raw bytes. It gets the type that the
+/// reader gives a string literal with the same contents: multibyte if there are
+/// no raw bytes, unibyte if there are raw bytes and only ASCII characters, and
+/// multibyte with raw byte characters otherwise.
+#[derive(Debug)]
+pub(crate) struct StringBuilder {
+    text: RawMultibyte,
+    raw: bool,
+    ascii: bool,
+}
+
+impl Default for StringBuilder {
+    fn default() -> Self {
+        Self {
+            text: RawMultibyte::new(),
+            raw: false,
+            ascii: true,
+        }
+    }
+}
+
+impl StringBuilder {
+    pub(crate) fn push(&mut self, chr: char) {
+        self.ascii &= chr.is_ascii();
+        self.text.push(chr);
+    }
+
+    pub(crate) fn push_raw_byte(&mut self, byte: u8) {
+        self.raw = true;
+        self.text.push_raw_byte(byte);
+    }
+
+    /// Push the character with `code`, which is a raw byte character if it is
+    /// past the Unicode range.
+    fn push_code(&mut self, code: i64) {
+        match u32::try_from(code).ok().and_then(char::from_u32) {
+            Some(chr) => self.push(chr),
+            None => self.push_raw_byte((code - BYTE8_OFFSET) as u8),
+        }
+    }
+
+    /// Push the contents of `string`. The bytes 0x80 to 0xFF of a unibyte
+    /// string are raw bytes.
+    pub(crate) fn push_str(&mut self, string: &LispString) {
+        match &string.string {
+            StrType::String(s) => s.chars().for_each(|x| self.push(x)),
+            StrType::BString(s) => s.iter().for_each(|&x| match x {
+                0..=0x7F => self.push(x.into()),
+                _ => self.push_raw_byte(x),
+            }),
+            StrType::Raw(s) => raw_multibyte_chars(s).for_each(|x| self.push_code(x)),
+        }
+    }
+
+    fn build(self) -> StrType {
+        let bytes = self.text.0;
+        if !self.raw {
+            let string = String::from_utf8(bytes).expect("text without raw bytes is UTF-8");
+            StrType::String(string)
+        } else if self.ascii {
+            let bytes = raw_multibyte_chars(&bytes).map(|x| match x {
+                0..=0x7F => x as u8,
+                _ => (x - BYTE8_OFFSET) as u8,
+            });
+            StrType::BString(bytes.collect())
+        } else {
+            StrType::Raw(bytes.into())
+        }
+    }
+}
+
+/// The character codes of the contents of a [`RawMultibyte`].
+fn raw_multibyte_chars(bytes: &[u8]) -> impl Iterator<Item = i64> + '_ {
+    let mut rest = bytes;
+    std::iter::from_fn(move || match *rest {
+        [] => None,
+        [lead @ (0xC0 | 0xC1), trail, ..] => {
+            rest = &rest[2..];
+            let byte = 0x80 | i64::from(lead & 1) << 6 | i64::from(trail & 0x3F);
+            Some(BYTE8_OFFSET + byte)
+        }
+        _ => {
+            let (chr, size) = bstr::decode_utf8(rest);
+            rest = &rest[size.max(1)..];
+            Some(chr.unwrap_or(char::REPLACEMENT_CHARACTER) as i64)
+        }
+    })
+}
+
+fn print_char(chr: char, escape_newlines: bool, f: &mut impl Write) -> std::fmt::Result {
+    match chr {
+        '"' | '\\' => write!(f, "\\{chr}"),
+        '\n' if escape_newlines => f.write_str("\\n"),
+        '\x0c' if escape_newlines => f.write_str("\\f"),
+        _ => f.write_char(chr),
+    }
 }
 
 impl LispString {
-    pub(crate) fn get_char_at(&self, idx: usize) -> Option<char> {
+    /// The code of the character at `idx`. The characters of a unibyte string
+    /// are its bytes.
+    pub(crate) fn get_char_at(&self, idx: usize) -> Option<i64> {
         match &self.string {
-            StrType::String(s) => s.chars().nth(idx),
-            StrType::BString(s) => s.chars().nth(idx),
+            StrType::String(s) => s.chars().nth(idx).map(|x| x as i64),
+            StrType::BString(s) => s.get(idx).map(|&x| x.into()),
+            StrType::Raw(s) => raw_multibyte_chars(s).nth(idx),
         }
     }
 
     pub(crate) fn len(&self) -> usize {
         match &self.string {
             StrType::String(s) => s.chars().count(),
-            StrType::BString(s) => s.chars().count(),
+            StrType::BString(s) => s.len(),
+            StrType::Raw(s) => raw_multibyte_chars(s).count(),
         }
     }
 
+    /// The string as text, with the replacement character in place of raw
+    /// bytes. Use this where only text makes sense, like the name of a
+    /// symbol.
+    pub(crate) fn to_str_lossy(&self) -> Cow<'_, str> {
+        match &self.string {
+            StrType::String(s) => Cow::Borrowed(s),
+            StrType::BString(s) => s.to_str_lossy(),
+            StrType::Raw(s) => {
+                let chars = raw_multibyte_chars(s).map(|x| {
+                    u32::try_from(x)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                });
+                Cow::Owned(chars.collect())
+            }
+        }
+    }
+
+    /// A new string with the characters from `beg` to `end`, without text
+    /// properties. It has the same type as this string, except that a slice of
+    /// a multibyte string with raw bytes that has none left is an ordinary
+    /// multibyte string.
+    pub(crate) fn slice<'new, const C: bool>(
+        &self,
+        beg: usize,
+        end: usize,
+        bk: &'new Block<C>,
+    ) -> Gc<&'new Self> {
+        match &self.string {
+            StrType::String(s) => {
+                let slice: String = s.chars().skip(beg).take(end - beg).collect();
+                slice.into_obj(bk)
+            }
+            StrType::BString(s) => s[beg..end].to_vec().into_obj(bk),
+            StrType::Raw(s) => {
+                let mut slice = StringBuilder::default();
+                raw_multibyte_chars(s)
+                    .skip(beg)
+                    .take(end - beg)
+                    .for_each(|x| slice.push_code(x));
+                // keep the slice multibyte
+                slice.ascii = false;
+                slice.into_obj(bk)
+            }
+        }
+    }
+
+    /// The text properties of the string.
+    pub(crate) fn props(&self) -> Ref<'_, IntervalTree> {
+        self.props.borrow()
+    }
+
+    pub(crate) fn props_mut(&self) -> RefMut<'_, IntervalTree> {
+        self.props.borrow_mut()
+    }
+
     pub(crate) unsafe fn from_string(value: String) -> Self {
         Self {
             gc: GcMark::default(),
             string: StrType::String(value),
+            props: RefCell::default(),
         }
     }
 
@@ -49,16 +253,87 @@ impl LispString {
         Self {
             gc: GcMark::default(),
             string: StrType::BString(BString::from(value)),
+            props: RefCell::default(),
+        }
+    }
+
+    pub(crate) unsafe fn from_raw_multibyte(value: RawMultibyte) -> Self {
+        Self {
+            gc: GcMark::default(),
+            string: StrType::Raw(BString::from(value.0)),
+            props: RefCell::default(),
+        }
+    }
+
+    pub(crate) unsafe fn from_builder(value: StringBuilder) -> Self {
+        Self {
+            gc: GcMark::default(),
+            string: value.build(),
+            props: RefCell::default(),
+        }
+    }
+
+    /// Print the string so that it reads back, with its text properties. If
+    /// `escape_newlines` is true, newlines and form feeds are written as `\n`
+    /// and `\f`.
+    pub(crate) fn print(&self, escape_newlines: bool, f: &mut impl Write) -> std::fmt::Result {
+        let props = self.props();
+        if !props.is_empty() {
+            write!(f, "#(")?;
+        }
+        f.write_char('"')?;
+        match &self.string {
+            StrType::String(s) => {
+                for chr in s.chars() {
+                    print_char(chr, escape_newlines, f)?;
+                }
+            }
+            StrType::Raw(s) => {
+                for code in raw_multibyte_chars(s) {
+                    match char::from_u32(code as u32) {
+                        Some(chr) => print_char(chr, escape_newlines, f)?,
+                        None => write!(f, "\\{:o}", code - BYTE8_OFFSET)?,
+                    }
+                }
+            }
+            // raw bytes are written as octal escapes so the string reads back
+            // as unibyte
+            StrType::BString(s) => {
+                for &byte in s.iter() {
+                    match byte {
+                        b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+                        b'\n' if escape_newlines => f.write_str("\\n")?,
+                        b'\x0c' if escape_newlines => f.write_str("\\f")?,
+                        0x80.. => write!(f, "\\{byte:o}")?,
+                        _ => f.write_char(byte as char)?,
+                    }
+                }
+            }
+        }
+        f.write_char('"')?;
+        if !props.is_empty() {
+            write!(f, "{props})")?;
         }
+        Ok(())
     }
 }
 
 impl<'new> CloneIn<'new, &'new Self> for LispString {
     fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> super::Gc<&'new Self> {
-        match &self.string {
+        let new = match &self.string {
             StrType::String(s) => s.clone().into_obj(bk),
             StrType::BString(s) => s.as_bytes().to_vec().into_obj(bk),
-        }
+            StrType::Raw(s) => RawMultibyte(s.as_bytes().to_vec()).into_obj(bk),
+        };
+        *new.untag().props_mut() = self.props().clone_in(bk);
+        new
+    }
+}
+
+impl Trace for LispString {
+    fn trace(&self, stack: &mut Vec<RawObj>) {
+        self.mark();
+        self.props().trace(stack);
     }
 }
 
@@ -74,20 +349,14 @@ impl Deref for LispString {
     fn deref(&self) -> &Self::Target {
         match &self.string {
             StrType::String(s) => BStr::new(s),
-            StrType::BString(s) => s.as_ref(),
+            StrType::BString(s) | StrType::Raw(s) => s.as_ref(),
         }
     }
 }
 
 impl Display for LispString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.string {
-            StrType::String(s) => write!(f, "\"{s}\""),
-            StrType::BString(s) => {
-                let bytes: &[u8] = s.as_ref();
-                write!(f, "\"{bytes:?}\"")
-            }
-        }
+        self.print(false, f)
     }
 }
 
@@ -103,7 +372,7 @@ impl<'a> TryFrom<&'a LispString> for &'a str {
     fn try_from(value: &'a LispString) -> Result<Self, Self::Error> {
         match &value.string {
             StrType::String(s) => Ok(s),
-            StrType::BString(s) => Ok(s.try_into()?),
+            StrType::BString(s) | StrType::Raw(s) => Ok(s.try_into()?),
         }
     }
 }
@@ -6,7 +6,8 @@ use super::super::{
     gc::{AllocObject, Block},
 };
 use super::{
-    ByteFn, HashTable, LispFloat, LispHashTable, LispString, LispVec, Record, RecordBuilder, SubrFn,
+    ByteFn, HashTable, LispBuffer, LispFloat, LispHashTable, LispMarker, LispOverlay, LispString,
+    LispVec, RawMultibyte, Record, RecordBuilder, StringBuilder, SubrFn,
 };
 use crate::core::env::sym;
 use crate::core::gc::{GcManaged, Trace};
@@ -311,6 +312,28 @@ impl IntoObject for Vec<u8> {
     }
 }
 
+impl IntoObject for RawMultibyte {
+    type Out<'ob> = <String as IntoObject>::Out<'ob>;
+
+    fn into_obj<const C: bool>(self, block: &Block<C>) -> Gc<Self::Out<'_>> {
+        unsafe {
+            let ptr = LispString::from_raw_multibyte(self).alloc_obj(block);
+            <&LispString>::tag_ptr(ptr)
+        }
+    }
+}
+
+impl IntoObject for StringBuilder {
+    type Out<'ob> = <String as IntoObject>::Out<'ob>;
+
+    fn into_obj<const C: bool>(self, block: &Block<C>) -> Gc<Self::Out<'_>> {
+        unsafe {
+            let ptr = LispString::from_builder(self).alloc_obj(block);
+            <&LispString>::tag_ptr(ptr)
+        }
+    }
+}
+
 impl<'a> IntoObject for Vec<GcObj<'a>> {
     type Out<'ob> = &'ob LispVec;
 
@@ -333,6 +356,33 @@ impl<'a> IntoObject for RecordBuilder<'a> {
     }
 }
 
+impl IntoObject for LispBuffer {
+    type Out<'ob> = &'ob Self;
+
+    fn into_obj<const C: bool>(self, block: &Block<C>) -> Gc<Self::Out<'_>> {
+        let ptr = self.alloc_obj(block);
+        unsafe { Self::Out::tag_ptr(ptr) }
+    }
+}
+
+impl IntoObject for LispMarker {
+    type Out<'ob> = &'ob Self;
+
+    fn into_obj<const C: bool>(self, block: &Block<C>) -> Gc<Self::Out<'_>> {
+        let ptr = self.alloc_obj(block);
+        unsafe { Self::Out::tag_ptr(ptr) }
+    }
+}
+
+impl IntoObject for LispOverlay {
+    type Out<'ob> = &'ob Self;
+
+    fn into_obj<const C: bool>(self, block: &Block<C>) -> Gc<Self::Out<'_>> {
+        let ptr = self.alloc_obj(block);
+        unsafe { Self::Out::tag_ptr(ptr) }
+    }
+}
+
 impl<'a> IntoObject for HashTable<'a> {
     type Out<'ob> = &'ob LispHashTable;
 
@@ -359,6 +409,9 @@ mod private {
         HashTable,
         SubrFn,
         ByteFn,
+        Buffer,
+        Marker,
+        Overlay,
     }
 
     pub(crate) trait TaggedPtr: Copy + for<'a> WithLifetime<'a> {
@@ -408,6 +461,9 @@ impl<'a> TaggedPtr for Object<'a> {
                 Tag::Vec => Object::Vec(<&LispVec>::from_obj_ptr(ptr)),
                 Tag::Record => Object::Record(<&Record>::from_obj_ptr(ptr)),
                 Tag::HashTable => Object::HashTable(<&LispHashTable>::from_obj_ptr(ptr)),
+                Tag::Buffer => Object::Buffer(<&LispBuffer>::from_obj_ptr(ptr)),
+                Tag::Marker => Object::Marker(<&LispMarker>::from_obj_ptr(ptr)),
+                Tag::Overlay => Object::Overlay(<&LispOverlay>::from_obj_ptr(ptr)),
             }
         }
     }
@@ -424,6 +480,9 @@ impl<'a> TaggedPtr for Object<'a> {
             Object::String(x) => TaggedPtr::tag(x).into(),
             Object::ByteFn(x) => TaggedPtr::tag(x).into(),
             Object::SubrFn(x) => TaggedPtr::tag(x).into(),
+            Object::Buffer(x) => TaggedPtr::tag(x).into(),
+            Object::Marker(x) => TaggedPtr::tag(x).into(),
+            Object::Overlay(x) => TaggedPtr::tag(x).into(),
         }
     }
 }
@@ -512,6 +571,11 @@ impl<'a> TaggedPtr for Number<'a> {
     }
 }
 
+/// The largest integer that fits in an object. The tag takes up the low 8 bits.
+pub(crate) const MOST_POSITIVE_FIXNUM: i64 = i64::MAX >> 8;
+/// The smallest integer that fits in an object.
+pub(crate) const MOST_NEGATIVE_FIXNUM: i64 = i64::MIN >> 8;
+
 impl TaggedPtr for i64 {
     type Ptr = i64;
     const TAG: Tag = Tag::Int;
@@ -638,6 +702,42 @@ impl TaggedPtr for &LispHashTable {
     }
 }
 
+impl TaggedPtr for &LispBuffer {
+    type Ptr = LispBuffer;
+    const TAG: Tag = Tag::Buffer;
+    unsafe fn from_obj_ptr(ptr: *const u8) -> Self {
+        &*ptr.cast::<Self::Ptr>()
+    }
+
+    fn get_ptr(self) -> *const Self::Ptr {
+        self as *const Self::Ptr
+    }
+}
+
+impl TaggedPtr for &LispMarker {
+    type Ptr = LispMarker;
+    const TAG: Tag = Tag::Marker;
+    unsafe fn from_obj_ptr(ptr: *const u8) -> Self {
+        &*ptr.cast::<Self::Ptr>()
+    }
+
+    fn get_ptr(self) -> *const Self::Ptr {
+        self as *const Self::Ptr
+    }
+}
+
+impl TaggedPtr for &LispOverlay {
+    type Ptr = LispOverlay;
+    const TAG: Tag = Tag::Overlay;
+    unsafe fn from_obj_ptr(ptr: *const u8) -> Self {
+        &*ptr.cast::<Self::Ptr>()
+    }
+
+    fn get_ptr(self) -> *const Self::Ptr {
+        std::ptr::from_ref(self)
+    }
+}
+
 macro_rules! cast_gc {
     ($supertype:ty => $($subtype:ty),+ $(,)?) => {
         $(
@@ -769,8 +869,11 @@ pub(crate) enum Object<'ob> {
     String(&'ob LispString) = Tag::String as u8,
     ByteFn(&'ob ByteFn) = Tag::ByteFn as u8,
     SubrFn(&'static SubrFn) = Tag::SubrFn as u8,
+    Buffer(&'ob LispBuffer) = Tag::Buffer as u8,
+    Marker(&'ob LispMarker) = Tag::Marker as u8,
+    Overlay(&'ob LispOverlay) = Tag::Overlay as u8,
 }
-cast_gc!(Object<'ob> => Number<'ob>, List<'ob>, Function<'ob>, i64, Symbol<'_>, &LispFloat, &'ob Cons, &'ob LispVec, &'ob Record, &'ob LispHashTable, &'ob LispString, &'ob ByteFn, &'ob SubrFn);
+cast_gc!(Object<'ob> => Number<'ob>, List<'ob>, Function<'ob>, i64, Symbol<'_>, &LispFloat, &'ob Cons, &'ob LispVec, &'ob Record, &'ob LispHashTable, &'ob LispString, &'ob ByteFn, &'ob SubrFn, &'ob LispBuffer, &'ob LispMarker, &'ob LispOverlay);
 
 impl Object<'_> {
     pub(crate) const NIL: Object<'static> = Object::Symbol(sym::NIL);
@@ -787,6 +890,9 @@ impl Object<'_> {
             Object::HashTable(_) => Type::HashTable,
             Object::String(_) => Type::String,
             Object::ByteFn(_) | Object::SubrFn(_) => Type::Func,
+            Object::Buffer(_) => Type::Buffer,
+            Object::Marker(_) => Type::Marker,
+            Object::Overlay(_) => Type::Overlay,
         }
     }
 }
@@ -974,6 +1080,51 @@ impl<'ob> TryFrom<GcObj<'ob>> for Gc<&'ob LispHashTable> {
     }
 }
 
+impl<'ob> TryFrom<GcObj<'ob>> for Gc<&'ob LispBuffer> {
+    type Error = TypeError;
+
+    fn try_from(value: GcObj<'ob>) -> Result<Self, Self::Error> {
+        match value.get_tag() {
+            Tag::Buffer => unsafe { Ok(cast_gc(value)) },
+            _ => Err(TypeError::new(Type::Buffer, value)),
+        }
+    }
+}
+
+impl<'ob> From<&'ob LispBuffer> for Gc<&'ob LispBuffer> {
+    fn from(x: &'ob LispBuffer) -> Self {
+        unsafe { <&LispBuffer>::tag_ptr(x.get_ptr()) }
+    }
+}
+
+impl<'ob> TryFrom<GcObj<'ob>> for Gc<&'ob LispMarker> {
+    type Error = TypeError;
+
+    fn try_from(value: GcObj<'ob>) -> Result<Self, Self::Error> {
+        match value.get_tag() {
+            Tag::Marker => unsafe { Ok(cast_gc(value)) },
+            _ => Err(TypeError::new(Type::Marker, value)),
+        }
+    }
+}
+
+impl<'ob> TryFrom<GcObj<'ob>> for Gc<&'ob LispOverlay> {
+    type Error = TypeError;
+
+    fn try_from(value: GcObj<'ob>) -> Result<Self, Self::Error> {
+        match value.get_tag() {
+            Tag::Overlay => unsafe { Ok(cast_gc(value)) },
+            _ => Err(TypeError::new(Type::Overlay, value)),
+        }
+    }
+}
+
+impl<'ob> From<&'ob LispOverlay> for Gc<&'ob LispOverlay> {
+    fn from(x: &'ob LispOverlay) -> Self {
+        unsafe { <&LispOverlay>::tag_ptr(x.get_ptr()) }
+    }
+}
+
 impl<'ob> TryFrom<GcObj<'ob>> for Gc<&'ob LispVec> {
     type Error = TypeError;
 
@@ -1018,6 +1169,9 @@ where
             Object::Vec(x) => x.clone_in(bk).into(),
             Object::Record(x) => x.clone_in(bk).into(),
             Object::HashTable(x) => x.clone_in(bk).into(),
+            Object::Buffer(x) => x.clone_in(bk).into(),
+            Object::Marker(x) => x.clone_in(bk).into(),
+            Object::Overlay(x) => x.clone_in(bk).into(),
         };
         let Ok(x) = Gc::<U>::try_from(obj) else {unreachable!()};
         x
@@ -1122,6 +1276,9 @@ impl fmt::Display for Object<'_> {
             Object::ByteFn(x) => D::fmt(x, f),
             Object::SubrFn(x) => D::fmt(x, f),
             Object::Float(x) => D::fmt(x, f),
+            Object::Buffer(x) => D::fmt(x, f),
+            Object::Marker(x) => D::fmt(x, f),
+            Object::Overlay(x) => D::fmt(x, f),
         }
     }
 }
@@ -1142,6 +1299,9 @@ impl<'ob> Gc<Object<'ob>> {
             Object::String(x) => x.is_marked(),
             Object::ByteFn(x) => x.is_marked(),
             Object::Symbol(x) => x.is_marked(),
+            Object::Buffer(x) => x.is_marked(),
+            Object::Marker(x) => x.is_marked(),
+            Object::Overlay(x) => x.is_marked(),
         }
     }
 
@@ -1149,13 +1309,16 @@ impl<'ob> Gc<Object<'ob>> {
         match self.untag() {
             Object::Int(_) | Object::SubrFn(_) => {}
             Object::Float(x) => x.mark(),
-            Object::String(x) => x.mark(),
+            Object::String(x) => x.trace(stack),
             Object::Vec(vec) => vec.trace(stack),
             Object::Record(x) => x.trace(stack),
             Object::HashTable(x) => x.trace(stack),
             Object::Cons(x) => x.trace(stack),
             Object::Symbol(x) => x.trace(stack),
             Object::ByteFn(x) => x.trace(stack),
+            Object::Buffer(x) => x.trace(stack),
+            Object::Marker(x) => x.trace(stack),
+            Object::Overlay(x) => x.trace(stack),
         }
     }
 }
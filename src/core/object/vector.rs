@@ -1,4 +1,4 @@
-use super::{display_slice, CloneIn, GcObj, IntoObject, WithLifetime};
+use super::{print_object, CloneIn, GcObj, IntoObject, PrintOptions, TagType, WithLifetime};
 use crate::core::gc::{GcManaged, GcMark, Trace};
 use anyhow::{anyhow, Result};
 use std::{cell::Cell, fmt::Debug, fmt::Display, ops::Deref};
@@ -147,7 +147,7 @@ impl Trace for LispVec {
 
 impl Display for LispVec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&display_slice(self))
+        print_object(self.tag().into(), PrintOptions::default(), f)
     }
 }
 
@@ -190,7 +190,6 @@ impl GcManaged for Record {
 
 impl Display for Record {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "#s")?;
-        f.write_str(&display_slice(self))
+        print_object(self.tag().into(), PrintOptions::default(), f)
     }
 }
@@ -1,12 +1,12 @@
 use crate::core::{
     cons::Cons,
-    env::{sym, Env, Symbol, INTERNED_SYMBOLS},
-    error::{Type, TypeError},
+    env::{is_local_var, sym, Env, Symbol, INTERNED_SYMBOLS},
+    error::{LispError, Type, TypeError},
     gc::{Context, IntoRoot, Rt},
-    object::{nil, Gc, GcObj, List, Number, Object, SubrFn},
+    object::{nil, Gc, GcObj, LispBuffer, List, Number, Object, SubrFn},
 };
 use crate::hashmap::HashSet;
-use anyhow::{anyhow, Result};
+use anyhow::{bail, ensure, Result};
 use fn_macros::defun;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
@@ -62,13 +62,58 @@ pub(crate) fn get<'ob>(
     env: &Rt<Env>,
     cx: &'ob Context,
 ) -> GcObj<'ob> {
-    match env.props.get(symbol) {
-        Some(plist) => match plist.iter().find(|x| x.0 == propname) {
-            Some(element) => cx.bind(element.1.bind(cx)),
-            None => nil(),
-        },
-        None => nil(),
+    env.prop(symbol, propname, cx).unwrap_or_else(nil)
+}
+
+/// Define `name` as an error with `message`. `parent` is the error, or list of
+/// errors, that it is a kind of, and defaults to `error`.
+#[defun]
+pub(crate) fn define_error<'ob>(
+    name: Symbol,
+    message: GcObj<'ob>,
+    parent: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let parent = parent
+        .filter(|x| !x.nil())
+        .unwrap_or_else(|| sym::ERROR.into());
+    let mut conditions = vec![name.into()];
+    if let Object::Cons(parents) = parent.untag() {
+        for parent in parents.elements() {
+            let parent: Symbol = parent?.try_into()?;
+            let Some(inherited) = env
+                .prop(parent, sym::ERROR_CONDITIONS, cx)
+                .filter(|x| !x.nil())
+            else {
+                bail!("Unknown signal `{parent}'");
+            };
+            conditions.push(parent.into());
+            for condition in inherited.as_list()? {
+                conditions.push(condition?);
+            }
+        }
+    } else {
+        let parent: Symbol = parent.try_into()?;
+        conditions.push(parent.into());
+        if let Some(inherited) = env.prop(parent, sym::ERROR_CONDITIONS, cx) {
+            for condition in inherited.as_list()? {
+                conditions.push(condition?);
+            }
+        }
     }
+    let mut unique: Vec<GcObj> = Vec::new();
+    for condition in conditions {
+        if !unique.contains(&condition) {
+            unique.push(condition);
+        }
+    }
+    let conditions = crate::fns::slice_into_list(&unique, None, cx);
+    env.set_prop(name, sym::ERROR_CONDITIONS, conditions);
+    if !message.nil() {
+        env.set_prop(name, sym::ERROR_MESSAGE, message);
+    }
+    Ok(message)
 }
 
 #[defun]
@@ -85,7 +130,7 @@ pub(crate) fn symbol_value<'ob>(
     env: &Rt<Env>,
     cx: &'ob Context,
 ) -> Option<GcObj<'ob>> {
-    env.vars.get(symbol).map(|x| x.bind(cx))
+    env.var(symbol, cx)
 }
 
 #[defun]
@@ -110,14 +155,18 @@ pub(crate) fn fmakunbound(symbol: Symbol) -> Symbol {
 }
 
 #[defun]
-pub(crate) fn boundp(symbol: Symbol, env: &Rt<Env>) -> bool {
-    env.vars.get(symbol).is_some()
+pub(crate) fn boundp(symbol: Symbol, env: &Rt<Env>, cx: &Context) -> bool {
+    env.var(symbol, cx).is_some()
 }
 
 #[defun]
-pub(crate) fn makunbound<'ob>(symbol: Symbol<'ob>, env: &mut Rt<Env>) -> Symbol<'ob> {
-    env.vars.remove(symbol);
-    symbol
+pub(crate) fn makunbound<'ob>(
+    symbol: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<Symbol<'ob>> {
+    env.make_unbound(symbol, cx)?;
+    Ok(symbol)
 }
 
 #[defun]
@@ -125,6 +174,18 @@ pub(crate) fn default_boundp(symbol: Symbol, env: &Rt<Env>) -> bool {
     env.vars.get(symbol).is_some()
 }
 
+#[defun]
+pub(crate) fn default_value<'ob>(
+    symbol: Symbol,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    match env.default_value(symbol, cx) {
+        Some(value) => Ok(value),
+        None => Err(LispError::new(sym::VOID_VARIABLE, [symbol.into()]).into()),
+    }
+}
+
 #[defun]
 pub(crate) fn listp(object: GcObj) -> bool {
     matches!(object.untag(), Object::NIL | Object::Cons(_))
@@ -166,9 +227,8 @@ pub(crate) fn numberp(object: GcObj) -> bool {
 }
 
 #[defun]
-pub(crate) fn markerp(_: GcObj) -> bool {
-    // TODO: implement
-    false
+pub(crate) fn markerp(object: GcObj) -> bool {
+    matches!(object.untag(), Object::Marker(_))
 }
 
 #[defun]
@@ -215,9 +275,8 @@ fn byte_code_function_p(object: GcObj) -> bool {
 }
 
 #[defun]
-fn bufferp(_object: GcObj) -> bool {
-    // TODO: Implement once buffers are added
-    false
+fn bufferp(object: GcObj) -> bool {
+    matches!(object.untag(), Object::Buffer(_))
 }
 
 #[defun]
@@ -246,11 +305,98 @@ pub(crate) fn defvar<'ob>(
 }
 
 #[defun]
-pub(crate) fn make_variable_buffer_local(variable: Symbol) -> Symbol {
-    // TODO: Implement
+pub(crate) fn make_variable_buffer_local(variable: Symbol) -> Result<Symbol> {
+    ensure!(
+        !variable.is_const(),
+        LispError::new(sym::SETTING_CONSTANT, [variable.into()])
+    );
+    variable.make_special();
+    variable.make_buffer_local();
+    Ok(variable)
+}
+
+/// Make `variable` have a separate value in the current buffer. It starts out
+/// with the default value, or nil if the variable is void.
+#[defun]
+pub(crate) fn make_local_variable<'ob>(
+    variable: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<Symbol<'ob>> {
+    ensure!(
+        !variable.is_const(),
+        LispError::new(sym::SETTING_CONSTANT, [variable.into()])
+    );
+    let buffer = env.current_buffer(cx);
+    if !is_local_var(buffer, variable) {
+        let value = env.default_value(variable, cx).unwrap_or_default();
+        buffer.set_local_var(variable, value);
+    }
+    Ok(variable)
+}
+
+/// Remove the value of `variable` in the current buffer, so that the default
+/// value is seen again.
+#[defun]
+pub(crate) fn kill_local_variable<'ob>(
+    variable: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Symbol<'ob> {
+    env.current_buffer(cx).kill_local_var(variable);
     variable
 }
 
+#[defun]
+pub(crate) fn local_variable_p(
+    variable: Symbol,
+    buffer: Option<&LispBuffer>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> bool {
+    let buffer = buffer.unwrap_or_else(|| env.current_buffer(cx));
+    is_local_var(buffer, variable)
+}
+
+#[defun]
+pub(crate) fn buffer_local_value<'ob>(
+    variable: Symbol,
+    buffer: &LispBuffer,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let value = match variable {
+        sym::BUFFER_UNDO_LIST => Some(buffer.undo_list(cx)),
+        _ if buffer.has_local_var(variable) => buffer.local_var(variable, cx),
+        _ => env.default_value(variable, cx),
+    };
+    match value {
+        Some(value) => Ok(value),
+        None => Err(LispError::new(sym::VOID_VARIABLE, [variable.into()]).into()),
+    }
+}
+
+/// Return an alist of the variables that are local to `buffer` and their
+/// values.
+#[defun]
+pub(crate) fn buffer_local_variables<'ob>(
+    buffer: Option<&LispBuffer>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> GcObj<'ob> {
+    let buffer = buffer.unwrap_or_else(|| env.current_buffer(cx));
+    let undo_list = crate::cons!(sym::BUFFER_UNDO_LIST, buffer.undo_list(cx); cx);
+    let mut vars = vec![undo_list];
+    for (var, value) in buffer.local_vars(cx) {
+        match value {
+            Some(value) => vars.push(crate::cons!(var, value; cx)),
+            // void variables are listed without a value
+            None => vars.push(var.into()),
+        }
+    }
+    crate::fns::slice_into_list(&vars, None, cx)
+}
+
 #[defun]
 fn subr_arity<'ob>(subr: &SubrFn, cx: &'ob Context) -> GcObj<'ob> {
     let min = subr.args.required as usize;
@@ -279,6 +425,11 @@ fn ash(value: i64, count: i64) -> i64 {
     }
 }
 
+/// The error for an index that is not in `array`.
+fn out_of_range(array: GcObj, idx: usize) -> anyhow::Error {
+    LispError::new(sym::ARGS_OUT_OF_RANGE, [array.into(), idx.into()]).into()
+}
+
 #[defun]
 pub(crate) fn aset<'ob>(array: GcObj<'ob>, idx: usize, newlet: GcObj<'ob>) -> Result<GcObj<'ob>> {
     match array.untag() {
@@ -288,8 +439,7 @@ pub(crate) fn aset<'ob>(array: GcObj<'ob>, idx: usize, newlet: GcObj<'ob>) -> Re
                 vec[idx].set(newlet);
                 Ok(newlet)
             } else {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
+                Err(out_of_range(array, idx))
             }
         }
         Object::Record(vec) => {
@@ -298,8 +448,7 @@ pub(crate) fn aset<'ob>(array: GcObj<'ob>, idx: usize, newlet: GcObj<'ob>) -> Re
                 vec[idx].set(newlet);
                 Ok(newlet)
             } else {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
+                Err(out_of_range(array, idx))
             }
         }
         x => Err(TypeError::new(Type::Sequence, x).into()),
@@ -311,28 +460,19 @@ pub(crate) fn aref(array: GcObj, idx: usize) -> Result<GcObj> {
     match array.untag() {
         Object::Vec(vec) => match vec.get(idx) {
             Some(x) => Ok(x.get()),
-            None => {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(out_of_range(array, idx)),
         },
         Object::Record(vec) => match vec.get(idx) {
             Some(x) => Ok(x.get()),
-            None => {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(out_of_range(array, idx)),
         },
         Object::String(string) => match string.get_char_at(idx) {
-            Some(x) => Ok((x as i64).into()),
-            None => {
-                let len = string.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            Some(x) => Ok(x.into()),
+            None => Err(out_of_range(array, idx)),
         },
         Object::ByteFn(fun) => match fun.index(idx) {
             Some(x) => Ok(x),
-            None => Err(anyhow!("index {idx} is out of bounds")),
+            None => Err(out_of_range(array, idx)),
         },
         x => Err(TypeError::new(Type::Sequence, x).into()),
     }
@@ -351,6 +491,9 @@ fn type_of(object: GcObj) -> GcObj {
         Object::HashTable(_) => sym::HASH_TABLE.into(),
         Object::String(_) => sym::STRING.into(),
         Object::SubrFn(_) => sym::SUBR.into(),
+        Object::Buffer(_) => sym::BUFFER.into(),
+        Object::Marker(_) => sym::MARKER.into(),
+        Object::Overlay(_) => sym::OVERLAY.into(),
     }
 }
 
@@ -426,6 +569,9 @@ pub(crate) fn cons<'ob>(car: GcObj, cdr: GcObj, cx: &'ob Context) -> GcObj<'ob>
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::core::gc::RootSet;
+    use crate::interpreter::test::check_interpreter;
+    use crate::root;
 
     #[test]
     fn test_ash() {
@@ -435,6 +581,66 @@ mod test {
         assert_eq!(ash(256, -8), 1);
         assert_eq!(ash(-8, 1), -16);
     }
+
+    #[test]
+    fn test_buffer_local_variables() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let list = list![2, 1, true; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-a 1) (make-local-variable 'bl-a) (setq bl-a 2) (list bl-a (default-value 'bl-a) (local-variable-p 'bl-a)))",
+            list,
+            cx,
+        );
+        let list = list![1, 2; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-b 1) (save-current-buffer (set-buffer (get-buffer-create \"bl-b\")) (make-local-variable 'bl-b) (setq bl-b 2)) (list bl-b (buffer-local-value 'bl-b (get-buffer \"bl-b\"))))",
+            list,
+            cx,
+        );
+        let inner = list![3, 1; cx];
+        let list = list![inner, 2; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-c 1) (make-local-variable 'bl-c) (setq bl-c 2) (list (let ((bl-c 3)) (list bl-c (default-value 'bl-c))) bl-c))",
+            list,
+            cx,
+        );
+        // setting an automatically local variable inside a `let` of its
+        // default value does not make it local
+        let inner = list![4, 1; cx];
+        let list = list![false, inner; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-d 1) (make-variable-buffer-local 'bl-d) (list (let ((bl-d 2)) (setq bl-d 3) (local-variable-p 'bl-d)) (progn (setq bl-d 4) (list bl-d (default-value 'bl-d)))))",
+            list,
+            cx,
+        );
+        let list = list![1, false; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-e 1) (make-local-variable 'bl-e) (setq bl-e 2) (kill-local-variable 'bl-e) (list bl-e (local-variable-p 'bl-e)))",
+            list,
+            cx,
+        );
+        let list = list![1, 5; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-f 1) (make-local-variable 'bl-f) (setq bl-f 1) (set-default 'bl-f 5) (list bl-f (default-value 'bl-f)))",
+            list,
+            cx,
+        );
+        // `makunbound` voids the local value and leaves the default alone
+        let list = list![false, true, 1, 3, false; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (defvar bl-g 1) (make-local-variable 'bl-g) (makunbound 'bl-g) (list (boundp 'bl-g) (local-variable-p 'bl-g) (default-value 'bl-g) (let ((bl-g 3)) bl-g) (boundp 'bl-g)))",
+            list,
+            cx,
+        );
+    }
 }
 
 defsym!(MANY);
@@ -444,3 +650,42 @@ defsym!(COMPILED_FUNCTION);
 defsym!(HASH_TABLE);
 defsym!(STRING);
 defsym!(SUBR);
+defsym!(BUFFER);
+defsym!(MARKER);
+defsym!(OVERLAY);
+defsym!(ERROR_CONDITIONS);
+defsym!(ERROR_MESSAGE);
+defsym!(QUIT);
+defsym!(USER_ERROR);
+defsym!(ARGS_OUT_OF_RANGE);
+defsym!(ARITH_ERROR);
+defsym!(DOMAIN_ERROR);
+defsym!(RANGE_ERROR);
+defsym!(SINGULARITY_ERROR);
+defsym!(OVERFLOW_ERROR);
+defsym!(UNDERFLOW_ERROR);
+defsym!(BEGINNING_OF_BUFFER);
+defsym!(END_OF_BUFFER);
+defsym!(BUFFER_READ_ONLY);
+defsym!(TEXT_READ_ONLY);
+defsym!(CIRCULAR_LIST);
+defsym!(CYCLIC_FUNCTION_INDIRECTION);
+defsym!(CYCLIC_VARIABLE_INDIRECTION);
+defsym!(END_OF_FILE);
+defsym!(INVALID_FUNCTION);
+defsym!(INVALID_READ_SYNTAX);
+defsym!(INVALID_REGEXP);
+defsym!(MARK_INACTIVE);
+defsym!(NO_CATCH);
+defsym!(SCAN_ERROR);
+defsym!(SEARCH_FAILED);
+defsym!(SETTING_CONSTANT);
+defsym!(VOID_FUNCTION);
+defsym!(VOID_VARIABLE);
+defsym!(WRONG_LENGTH_ARGUMENT);
+defsym!(WRONG_NUMBER_OF_ARGUMENTS);
+defsym!(WRONG_TYPE_ARGUMENT);
+defsym!(FILE_ERROR);
+defsym!(FILE_ALREADY_EXISTS);
+defsym!(FILE_MISSING);
+defsym!(PERMISSION_DENIED);
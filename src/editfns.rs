@@ -1,11 +1,20 @@
-use crate::core::object::{GcObj, Object};
-use anyhow::{bail, ensure, Result};
+use crate::core::{
+    env::{sym, Env},
+    error::{LispError, Type, TypeError},
+    gc::{Context, Rt},
+    object::{
+        format_float, nil, print_to_string, Buffer, GcObj, LispBuffer, LispString, Object,
+        PrintOptions,
+    },
+};
+use crate::marker::position_arg;
+use anyhow::{anyhow, bail, ensure, Result};
 use fn_macros::defun;
-use std::{fmt::Write as _, io::Write};
+use std::io::Write;
 
 #[defun]
-fn message(format_string: &str, args: &[GcObj]) -> Result<String> {
-    let message = format(format_string, args)?;
+fn message(format_string: &str, args: &[GcObj], env: &Rt<Env>, cx: &Context) -> Result<String> {
+    let message = format_message(format_string, args, env, cx)?;
     println!("MESSAGE: {message}");
     std::io::stdout().flush()?;
     Ok(message)
@@ -13,60 +22,552 @@ fn message(format_string: &str, args: &[GcObj]) -> Result<String> {
 
 defvar!(MESSAGE_NAME);
 defvar!(MESSAGE_TYPE, "new message");
+defvar!(TEXT_QUOTING_STYLE);
+defsym!(STRAIGHT);
+defsym!(GRAVE);
 
+/// The largest field width or precision allowed in a format specification, so
+/// that a bad format string can't exhaust memory.
+const MAX_FORMAT_WIDTH: usize = 1 << 20;
+
+/// A format specification of the form
+/// `%[FIELD$][FLAGS][WIDTH][.PRECISION]CONVERSION`, without the `%`.
+struct FormatSpec<'a> {
+    /// The argument to use, counting from 1.
+    field: Option<usize>,
+    flags: &'a str,
+    width: usize,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+impl<'a> FormatSpec<'a> {
+    /// Parse the specification at the start of `spec`, returning it and the
+    /// rest of the string.
+    fn parse(spec: &'a str) -> Result<(Self, &'a str)> {
+        let digits = |x: &str| x.find(|c: char| !c.is_ascii_digit()).unwrap_or(x.len());
+        let size = |x: &str| match x.parse() {
+            Ok(x) if x <= MAX_FORMAT_WIDTH => Ok(x),
+            _ => Err(anyhow!("Format width or precision too large")),
+        };
+        let mut rest = spec;
+        let len = digits(rest);
+        let field = if len > 0 && rest[len..].starts_with('$') {
+            let field = rest[..len].parse()?;
+            rest = &rest[len + 1..];
+            Some(field)
+        } else {
+            None
+        };
+        let len = rest
+            .find(|c| !matches!(c, '-' | '+' | ' ' | '#' | '0'))
+            .unwrap_or(rest.len());
+        let (flags, tail) = rest.split_at(len);
+        let len = digits(tail);
+        let width = if len > 0 { size(&tail[..len])? } else { 0 };
+        rest = &tail[len..];
+        let precision = match rest.strip_prefix('.') {
+            Some(tail) => {
+                let len = digits(tail);
+                rest = &tail[len..];
+                Some(if len > 0 { size(&tail[..len])? } else { 0 })
+            }
+            None => None,
+        };
+        let mut chars = rest.chars();
+        let Some(conversion) = chars.next() else {
+            bail!("Format string ends in middle of format specifier")
+        };
+        let spec = Self { field, flags, width, precision, conversion };
+        Ok((spec, chars.as_str()))
+    }
+
+    fn flag(&self, flag: char) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Write `prefix` and `body` to `out`, padded to the field width. If
+    /// `zero_pad` is true and the `0` flag was given, the padding is zeros
+    /// between the prefix and the body.
+    fn pad(&self, prefix: &str, body: &str, zero_pad: bool, out: &mut String) {
+        let len = prefix.chars().count() + body.chars().count();
+        let fill = self.width.saturating_sub(len);
+        if self.flag('-') {
+            out.push_str(prefix);
+            out.push_str(body);
+            out.push_str(&" ".repeat(fill));
+        } else if zero_pad && self.flag('0') {
+            out.push_str(prefix);
+            out.push_str(&"0".repeat(fill));
+            out.push_str(body);
+        } else {
+            out.push_str(&" ".repeat(fill));
+            out.push_str(prefix);
+            out.push_str(body);
+        }
+    }
+
+    /// The sign of a number, as controlled by the `+` and space flags.
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.flag('+') {
+            "+"
+        } else if self.flag(' ') {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Format `arg` according to this specification and write it to `out`.
+    fn format(&self, arg: GcObj, out: &mut String) -> Result<()> {
+        const MISMATCH: &str = "Format specifier doesn’t match argument type";
+        match self.conversion {
+            's' | 'S' => {
+                let options = PrintOptions {
+                    escape: self.conversion == 'S',
+                    quoted: true,
+                    ..PrintOptions::default()
+                };
+                let mut text = print_to_string(arg, options);
+                // the precision is the maximum number of characters
+                if let Some((idx, _)) = self.precision.and_then(|x| text.char_indices().nth(x)) {
+                    text.truncate(idx);
+                }
+                self.pad("", &text, false, out);
+            }
+            'c' => {
+                let Object::Int(chr) = arg.untag() else { bail!(MISMATCH) };
+                let Some(chr) = u32::try_from(chr).ok().and_then(char::from_u32) else {
+                    bail!("Invalid character: {chr}")
+                };
+                self.pad("", chr.encode_utf8(&mut [0; 4]), false, out);
+            }
+            'd' | 'i' | 'o' | 'x' | 'X' => {
+                let int = match arg.untag() {
+                    Object::Int(x) => x,
+                    // floats are truncated towards zero
+                    Object::Float(x) if x.is_finite() => **x as i64,
+                    _ => bail!(MISMATCH),
+                };
+                let magnitude = int.unsigned_abs();
+                let mut digits = match self.conversion {
+                    'd' | 'i' => magnitude.to_string(),
+                    'o' => format!("{magnitude:o}"),
+                    'x' => format!("{magnitude:x}"),
+                    _ => format!("{magnitude:X}"),
+                };
+                // the precision is the minimum number of digits
+                if let Some(precision) = self.precision {
+                    if digits.len() < precision {
+                        digits.insert_str(0, &"0".repeat(precision - digits.len()));
+                    }
+                }
+                let alternate = match self.conversion {
+                    'o' if !digits.starts_with('0') => "0",
+                    'x' if magnitude != 0 => "0x",
+                    'X' if magnitude != 0 => "0X",
+                    _ => "",
+                };
+                let sign = match self.conversion {
+                    'd' | 'i' => self.sign(int < 0),
+                    _ if int < 0 => "-",
+                    _ => "",
+                };
+                let alternate = if self.flag('#') { alternate } else { "" };
+                let prefix = format!("{sign}{alternate}");
+                self.pad(&prefix, &digits, self.precision.is_none(), out);
+            }
+            'e' | 'f' | 'g' => {
+                let float = match arg.untag() {
+                    Object::Int(x) => x as f64,
+                    Object::Float(x) => **x,
+                    _ => bail!(MISMATCH),
+                };
+                let body = if float.is_nan() {
+                    "nan".to_owned()
+                } else if float.is_infinite() {
+                    "inf".to_owned()
+                } else {
+                    let precision = self.precision.unwrap_or(6);
+                    format_float(self.conversion, float.abs(), precision, self.flag('#'))
+                };
+                let sign = self.sign(float.is_sign_negative() && !float.is_nan());
+                self.pad(sign, &body, float.is_finite(), out);
+            }
+            chr => bail!("Invalid format operation %{chr}"),
+        }
+        Ok(())
+    }
+}
+
+/// Format `objects` according to the format specifications in `string`.
 #[defun]
-fn format(string: &str, objects: &[GcObj]) -> Result<String> {
+pub(crate) fn format(string: &str, objects: &[GcObj]) -> Result<String> {
     let mut result = String::new();
-    let mut iter = objects.iter();
-    // "%%" inserts a single "%" in the output
-    for segment in string.split("%%") {
-        let mut last_end = 0;
-        let mut escaped = false;
-        let is_format_char = |c: char| {
-            if escaped {
-                escaped = false;
-                false
-            } else if c == '\\' {
-                escaped = true;
-                false
-            } else {
-                c == '%'
-            }
+    // the index of the next argument
+    let mut next = 0;
+    let mut rest = string;
+    while let Some(idx) = rest.find('%') {
+        result.push_str(&rest[..idx]);
+        let (spec, tail) = FormatSpec::parse(&rest[idx + 1..])?;
+        rest = tail;
+        if spec.conversion == '%' {
+            result.push('%');
+            continue;
+        }
+        if let Some(field) = spec.field {
+            let Some(field) = field.checked_sub(1) else { bail!("Invalid format field number 0") };
+            next = field;
+        }
+        let Some(arg) = objects.get(next) else {
+            bail!("Not enough arguments for format string")
         };
-        for (start, _) in segment.match_indices(is_format_char) {
-            result.push_str(&segment[last_end..start]);
-            // TODO: currently handles all format types the same. Need to check the modifier characters.
-            let Some(val) = iter.next() else {bail!("Not enough objects for format string")};
-            match val.untag() {
-                Object::String(s) => result.push_str(s.try_into()?),
-                obj => write!(result, "{obj}")?,
+        spec.format(*arg, &mut result)?;
+        next += 1;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Like `format`, but the grave accents and apostrophes in `string` are
+/// translated as specified by `text-quoting-style`. If it is `straight` they
+/// both become apostrophes, if it is `grave` they are left alone, and
+/// otherwise they become curved quotes.
+#[defun]
+fn format_message(string: &str, objects: &[GcObj], env: &Rt<Env>, cx: &Context) -> Result<String> {
+    let quotes = match env.var(sym::TEXT_QUOTING_STYLE, cx).map(GcObj::untag) {
+        Some(Object::Symbol(sym::GRAVE)) => None,
+        Some(Object::Symbol(sym::STRAIGHT)) => Some(('\'', '\'')),
+        _ => Some(('\u{2018}', '\u{2019}')),
+    };
+    let Some((open, close)) = quotes else { return format(string, objects) };
+    let string: String = string
+        .chars()
+        .map(|c| match c {
+            '`' => open,
+            '\'' => close,
+            c => c,
+        })
+        .collect();
+    format(&string, objects)
+}
+
+/// Check that the region between the lisp positions `start` and `end` is
+/// inside the accessible portion of `text`. The ends may be given in either
+/// order. Returns the region as ordered character indices.
+pub(crate) fn check_region(text: &Buffer, start: i64, end: i64) -> Result<(usize, usize)> {
+    let (start, end) = if start <= end { (start, end) } else { (end, start) };
+    let (min, max) = (text.begv() as i64 + 1, text.zv() as i64 + 1);
+    ensure!(
+        start >= min && end <= max,
+        LispError::new(sym::ARGS_OUT_OF_RANGE, [start.into(), end.into()])
+    );
+    Ok((start as usize - 1, end as usize - 1))
+}
+
+#[defun]
+pub(crate) fn point(env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.point() + 1)
+}
+
+#[defun]
+pub(crate) fn point_min(env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.begv() + 1)
+}
+
+#[defun]
+pub(crate) fn point_max(env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.zv() + 1)
+}
+
+#[defun]
+fn buffer_size(buffer: Option<&LispBuffer>, env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = match buffer {
+        Some(x) => x,
+        None => env.current_buffer(cx),
+    };
+    let data = buffer.lock()?;
+    Ok(data.text.len_chars())
+}
+
+#[defun]
+pub(crate) fn goto_char<'ob>(
+    position: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<GcObj<'ob>> {
+    let pos = position_arg(position)?;
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let (min, max) = (text.begv() as i64 + 1, text.zv() as i64 + 1);
+    text.set_point((pos.clamp(min, max) - 1) as usize)?;
+    Ok(position)
+}
+
+#[defun]
+pub(crate) fn insert<'ob>(
+    args: &[GcObj],
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    for arg in args {
+        match arg.untag() {
+            Object::String(string) => {
+                let start = buffer.text.point();
+                buffer.text.insert(string.try_into()?);
+                buffer.text.props.graft(&string.props(), start);
             }
-            last_end = start + 2;
+            Object::Int(chr) => {
+                let chr = u32::try_from(chr)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| anyhow!("Invalid character: {chr}"))?;
+                buffer.text.insert(chr.encode_utf8(&mut [0; 4]));
+            }
+            _ => bail!(TypeError::new(Type::String, *arg)),
+        }
+    }
+    Ok(nil())
+}
+
+#[defun]
+pub(crate) fn char_after<'ob>(
+    pos: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let pos = pos.map(position_arg).transpose()?;
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    let pos = match pos {
+        Some(pos) if pos <= text.begv() as i64 || pos > text.zv() as i64 => return Ok(nil()),
+        Some(pos) => pos as usize - 1,
+        None => text.point(),
+    };
+    Ok(match text.char_at(pos) {
+        Some(chr) if pos < text.zv() => (chr as i64).into(),
+        _ => nil(),
+    })
+}
+
+#[defun]
+pub(crate) fn following_char(env: &mut Rt<Env>, cx: &Context) -> Result<i64> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    if text.point() == text.zv() {
+        return Ok(0);
+    }
+    Ok(text.char_at(text.point()).map_or(0, |c| c as i64))
+}
+
+#[defun]
+pub(crate) fn preceding_char(env: &mut Rt<Env>, cx: &Context) -> Result<i64> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    match text.point() {
+        pt if pt == text.begv() => Ok(0),
+        pt => Ok(text.char_at(pt - 1).map_or(0, |c| c as i64)),
+    }
+}
+
+#[defun]
+pub(crate) fn bobp(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.point() == buffer.text.begv())
+}
+
+#[defun]
+pub(crate) fn eobp(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.point() == buffer.text.zv())
+}
+
+#[defun]
+pub(crate) fn bolp(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    Ok(text.point() == text.begv() || text.char_at(text.point() - 1) == Some('\n'))
+}
+
+#[defun]
+pub(crate) fn eolp(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    Ok(text.point() == text.zv() || text.char_at(text.point()) == Some('\n'))
+}
+
+/// The position of the start of the line `n - 1` lines from point, or the
+/// edge of the accessible portion of the buffer if there are not that many
+/// lines.
+fn line_position(text: &Buffer, n: i64, end: bool) -> usize {
+    let line = text.lines.line_at(text.point()) as i64 + n - 1;
+    let len = text.len_chars();
+    let pos = match usize::try_from(line) {
+        Ok(line) if end => text.lines.newline(line).unwrap_or(len),
+        Ok(line) => text.lines.line_start(line).unwrap_or(len),
+        Err(_) => 0,
+    };
+    pos.clamp(text.begv(), text.zv())
+}
+
+#[defun]
+fn line_beginning_position(n: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(line_position(&buffer.text, n.unwrap_or(1), false) + 1)
+}
+
+#[defun]
+fn line_end_position(n: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(line_position(&buffer.text, n.unwrap_or(1), true) + 1)
+}
+
+/// Return the line number of `position`, counting from 1 at the start of the
+/// accessible portion of the buffer, or of the whole buffer if `absolute` is
+/// non-nil.
+#[defun]
+fn line_number_at_pos(
+    position: Option<GcObj>,
+    absolute: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    let pos = match position {
+        Some(pos) => {
+            let pos = position_arg(pos)?;
+            ensure!(
+                (text.begv() as i64) < pos && pos <= text.zv() as i64 + 1,
+                LispError::new(sym::ARGS_OUT_OF_RANGE, [pos.into()])
+            );
+            pos as usize - 1
         }
-        result.push_str(&segment[last_end..segment.len()]);
-        result.push_str("%");
+        None => text.point(),
+    };
+    let start = if absolute.is_some() { 0 } else { text.begv() };
+    Ok(text.lines.line_at(pos) - text.lines.line_at(start) + 1)
+}
+
+/// Return the number of lines between `start` and `end`. A partial line at the
+/// end of the region counts as a line.
+#[defun]
+fn count_lines(start: GcObj, end: GcObj, env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    let (start, end) = check_region(text, position_arg(start)?, position_arg(end)?)?;
+    let lines = text.lines.line_at(end) - text.lines.line_at(start);
+    if end > start && text.char_at(end - 1) != Some('\n') {
+        Ok(lines + 1)
+    } else {
+        Ok(lines)
     }
-    result.pop();  // the last "%"
+}
+
+/// Copy the text between `start` and `end` into a new string, along with its
+/// text properties.
+fn substring_with_props<'ob>(
+    text: &Buffer,
+    start: usize,
+    end: usize,
+    cx: &'ob Context,
+) -> Result<&'ob LispString> {
+    let string: &LispString = cx.add_as(text.substring(start, end)?).untag();
+    *string.props_mut() = text.props.slice(start, end);
+    Ok(string)
+}
+
+#[defun]
+pub(crate) fn buffer_substring<'ob>(
+    start: GcObj,
+    end: GcObj,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispString> {
+    let (start, end) = (position_arg(start)?, position_arg(end)?);
+    let buffer = env.current_buffer(cx).lock()?;
+    let (start, end) = check_region(&buffer.text, start, end)?;
+    substring_with_props(&buffer.text, start, end, cx)
+}
+
+#[defun]
+fn buffer_substring_no_properties(
+    start: GcObj,
+    end: GcObj,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<String> {
+    let (start, end) = (position_arg(start)?, position_arg(end)?);
+    let buffer = env.current_buffer(cx).lock()?;
+    let (start, end) = check_region(&buffer.text, start, end)?;
+    buffer.text.substring(start, end)
+}
+
+#[defun]
+pub(crate) fn buffer_string<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<&'ob LispString> {
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    substring_with_props(text, text.begv(), text.zv(), cx)
+}
+
+#[defun]
+pub(crate) fn delete_region<'ob>(
+    start: GcObj,
+    end: GcObj,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let (start, end) = (position_arg(start)?, position_arg(end)?);
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let (start, end) = check_region(&buffer.text, start, end)?;
+    buffer.text.delete_range(start, end)?;
+    Ok(nil())
+}
+
+#[defun]
+pub(crate) fn narrow_to_region<'ob>(
+    start: GcObj,
+    end: GcObj,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let (start, end) = (position_arg(start)?, position_arg(end)?);
+    let (start, end) = if start <= end { (start, end) } else { (end, start) };
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let max = text.len_chars() as i64 + 1;
     ensure!(
-        iter.next().is_none(),
-        "Too many arguments for format string"
+        start >= 1 && end <= max,
+        LispError::new(sym::ARGS_OUT_OF_RANGE, [start.into(), end.into()])
     );
-    Ok(result)
+    text.narrow(start as usize - 1, end as usize - 1)?;
+    Ok(nil())
 }
 
 #[defun]
-fn format_message(string: &str, objects: &[GcObj]) -> Result<String> {
-    let formatted = format(string, objects)?;
-    // TODO: implement support for `text-quoting-style`.
-    Ok(formatted
-        .chars()
-        .map(|c| if matches!(c, '`' | '\'') { '"' } else { c })
-        .collect())
+pub(crate) fn widen<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    buffer.text.widen();
+    Ok(nil())
+}
+
+#[defun]
+fn buffer_narrowed_p(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let buffer = env.current_buffer(cx).lock()?;
+    Ok(buffer.text.is_narrowed())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::core::gc::RootSet;
+    use crate::root;
 
     #[test]
     fn test_format() {
@@ -76,10 +577,188 @@ mod test {
             &format("foo-%s %s", &[3.into(), 4.into()]).unwrap(),
             "foo-3 4"
         );
-        let sym = crate::core::env::sym::FUNCTION.into();
+        let sym = sym::FUNCTION.into();
         assert_eq!(&format("%s", &[sym]).unwrap(), "function");
 
         assert!(&format("%s", &[]).is_err());
-        assert!(&format("%s", &[1.into(), 2.into()]).is_err());
+        // like Emacs, extra arguments are ignored
+        assert_eq!(&format("%s", &[1.into(), 2.into()]).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_format_specs() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let check = |spec, arg: GcObj, expect| assert_eq!(format(spec, &[arg]).unwrap(), expect);
+        check("%d", 1.into(), "1");
+        check("%5d|", 2.into(), "    2|");
+        check("%-5d|", 3.into(), "3    |");
+        check("%05d", (-4).into(), "-0004");
+        check("%+d", 5.into(), "+5");
+        check("% d", 6.into(), " 6");
+        check("%.3i", 7.into(), "007");
+        check("%o|", 8.into(), "10|");
+        check("%#o", 8.into(), "010");
+        check("%x", 255.into(), "ff");
+        check("%#X", 255.into(), "0XFF");
+        check("%x", (-1).into(), "-1");
+        check("%d", cx.add(2.7), "2");
+        check("%d", cx.add(-2.7), "-2");
+        check("%c", ('λ' as i64).into(), "λ");
+        check("%.2f", cx.add(1.005), "1.00");
+        check("%8.3f", cx.add(1.23456), "   1.235");
+        check("%-8.1f|", cx.add(2.0), "2.0     |");
+        check("%08.2f", cx.add(-1.5), "-0001.50");
+        check("%e", cx.add(12345.678), "1.234568e+04");
+        check("%.2e", 1.into(), "1.00e+00");
+        check("%g", cx.add(0.0001), "0.0001");
+        check("%g", cx.add(1e-5), "1e-05");
+        check("%g", cx.add(123_456_789.0), "1.23457e+08");
+        check("%#g", 1.into(), "1.00000");
+        check("%f", cx.add(f64::NEG_INFINITY), "-inf");
+        check("%s", cx.add("a"), "a");
+        check("%S", cx.add("b"), "\"b\"");
+        check("%5s", cx.add("c"), "    c");
+        check("%-5S|", cx.add("d"), "\"d\"  |");
+        check("%.2s", cx.add("efg"), "ef");
+        let args = &[1.into(), 2.into()];
+        assert_eq!(format("%2$s %1$s %s", args).unwrap(), "2 1 2");
+        assert_eq!(format("100%%", &[]).unwrap(), "100%");
+
+        assert!(format("%d", &[cx.add("a")]).is_err());
+        assert!(format("%c", &[cx.add(1.5)]).is_err());
+        assert!(format("%f", &[sym::NIL.into()]).is_err());
+        assert!(format("%q", &[1.into()]).is_err());
+        assert!(format("%E", &[1.into()]).is_err());
+        assert!(format("%5", &[1.into()]).is_err());
+        assert!(format("%0$s", &[1.into()]).is_err());
+        assert!(format("%3$s", &[1.into(), 2.into()]).is_err());
+        assert!(format("%99999999999999999999d", &[1.into()]).is_err());
+        assert!(format("%.100000000d", &[1.into()]).is_err());
+    }
+
+    #[test]
+    fn test_format_message() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let args = &[cx.add("`b'")];
+        let curved = format_message("`a' %s", args, env, cx).unwrap();
+        assert_eq!(curved, "\u{2018}a\u{2019} `b'");
+        let style = sym::TEXT_QUOTING_STYLE;
+        env.set_var(style, sym::STRAIGHT.into()).unwrap();
+        assert_eq!(format_message("`a'", &[], env, cx).unwrap(), "'a'");
+        env.set_var(style, sym::GRAVE.into()).unwrap();
+        assert_eq!(format_message("`a'", &[], env, cx).unwrap(), "`a'");
+    }
+
+    #[test]
+    fn test_buffer_edits() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let args = &[cx.add("hello"), cx.add(' ' as i64), cx.add("world")];
+        insert(args, env, cx).unwrap();
+        assert_eq!(point(env, cx).unwrap(), 12);
+        assert!(eobp(env, cx).unwrap());
+        assert_eq!(goto_char(100.into(), env, cx).unwrap(), 100);
+        assert_eq!(point(env, cx).unwrap(), 12);
+        goto_char(6.into(), env, cx).unwrap();
+        assert_eq!(following_char(env, cx).unwrap(), ' ' as i64);
+        assert_eq!(preceding_char(env, cx).unwrap(), 'o' as i64);
+        assert_eq!(char_after(Some(1.into()), env, cx).unwrap(), 'h' as i64);
+        assert_eq!(char_after(Some(12.into()), env, cx).unwrap(), nil());
+        let (start, end) = (7.into(), 1.into());
+        assert_eq!(buffer_substring(start, end, env, cx).unwrap(), "hello ");
+        assert!(buffer_substring(0.into(), 3.into(), env, cx).is_err());
+        delete_region(1.into(), 7.into(), env, cx).unwrap();
+        assert_eq!(point(env, cx).unwrap(), 1);
+        assert_eq!(buffer_string(env, cx).unwrap(), "world");
+        assert!(bobp(env, cx).unwrap());
+    }
+
+    #[test]
+    fn test_narrowing() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("hello big world")], env, cx).unwrap();
+        assert!(narrow_to_region(0.into(), 100_000.into(), env, cx).is_err());
+        assert!(narrow_to_region(7.into(), 17.into(), env, cx).is_err());
+        assert!(!buffer_narrowed_p(env, cx).unwrap());
+        narrow_to_region(11.into(), 7.into(), env, cx).unwrap();
+        assert!(buffer_narrowed_p(env, cx).unwrap());
+        assert_eq!(point_min(env, cx).unwrap(), 7);
+        assert_eq!(point_max(env, cx).unwrap(), 11);
+        assert_eq!(point(env, cx).unwrap(), 11);
+        assert!(eobp(env, cx).unwrap());
+        assert_eq!(buffer_string(env, cx).unwrap(), "big ");
+        goto_char(1.into(), env, cx).unwrap();
+        assert_eq!(point(env, cx).unwrap(), 7);
+        assert!(bobp(env, cx).unwrap());
+        assert_eq!(preceding_char(env, cx).unwrap(), 0);
+        assert_eq!(char_after(Some(11.into()), env, cx).unwrap(), nil());
+        assert!(delete_region(1.into(), 8.into(), env, cx).is_err());
+        widen(env, cx).unwrap();
+        assert!(!buffer_narrowed_p(env, cx).unwrap());
+        assert_eq!(point_max(env, cx).unwrap(), 16);
+    }
+
+    #[test]
+    fn test_lines() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("foo\nbar\n\nbaz")], env, cx).unwrap();
+        assert_eq!(line_number_at_pos(None, None, env, cx).unwrap(), 4);
+        assert_eq!(line_beginning_position(None, env, cx).unwrap(), 10);
+        assert_eq!(line_beginning_position(Some(0), env, cx).unwrap(), 9);
+        assert_eq!(line_end_position(Some(-1), env, cx).unwrap(), 8);
+        assert_eq!(line_end_position(Some(3), env, cx).unwrap(), 13);
+        assert_eq!(count_lines(1.into(), 13.into(), env, cx).unwrap(), 4);
+        assert_eq!(count_lines(1.into(), 10.into(), env, cx).unwrap(), 3);
+        assert_eq!(count_lines(5.into(), 5.into(), env, cx).unwrap(), 0);
+
+        narrow_to_region(5.into(), 10.into(), env, cx).unwrap();
+        let pos = Some(10.into());
+        assert_eq!(line_number_at_pos(pos, None, env, cx).unwrap(), 3);
+        assert_eq!(line_number_at_pos(pos, Some(()), env, cx).unwrap(), 4);
+        assert!(line_number_at_pos(Some(1.into()), None, env, cx).is_err());
+        goto_char(6.into(), env, cx).unwrap();
+        assert_eq!(line_beginning_position(Some(-1), env, cx).unwrap(), 5);
+        assert_eq!(line_end_position(Some(5), env, cx).unwrap(), 10);
+        widen(env, cx).unwrap();
+
+        // edits keep the line index up to date
+        goto_char(1.into(), env, cx).unwrap();
+        insert(&[cx.add("\n\n")], env, cx).unwrap();
+        delete_region(7.into(), 10.into(), env, cx).unwrap();
+        let buffer = env.current_buffer(cx).lock().unwrap();
+        let string = buffer.text.substring(0, buffer.text.len_chars()).unwrap();
+        assert_eq!(string, "\n\nfoo\n\n\nbaz");
+        drop(buffer);
+        let line_number = |pos: i64, env: &mut Rt<Env>| {
+            line_number_at_pos(Some(pos.into()), None, env, cx).unwrap()
+        };
+        assert_eq!(line_number(7, env), 4);
+        assert_eq!(line_number(9, env), 6);
+        assert_eq!(count_lines(1.into(), 12.into(), env, cx).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_many_lines() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("line\n".repeat(200_000))], env, cx).unwrap();
+        goto_char(500_001.into(), env, cx).unwrap();
+        for _ in 0..1000 {
+            insert(&[cx.add("x\n")], env, cx).unwrap();
+            let bol = line_beginning_position(None, env, cx).unwrap();
+            assert_eq!(bol, point(env, cx).unwrap());
+        }
+        assert_eq!(line_number_at_pos(None, None, env, cx).unwrap(), 101_001);
+        let end = point_max(env, cx).unwrap();
+        assert_eq!(count_lines(1.into(), end.into(), env, cx).unwrap(), 201_000);
     }
 }
@@ -1,5 +1,5 @@
 use crate::core::env::{sym, Env, Symbol};
-use crate::core::error::{EvalError, Type, TypeError};
+use crate::core::error::{EvalError, LispError, Type, TypeError};
 use crate::core::gc::Rt;
 use crate::core::object::{nil, LispString, Object};
 use crate::core::{
@@ -57,8 +57,7 @@ fn run_hooks<'ob>(
     for hook in hooks {
         match hook.get(cx) {
             Object::Symbol(sym) => {
-                if let Some(val) = env.vars.get(sym) {
-                    let val = val.bind(cx);
+                if let Some(val) = env.var(sym, cx) {
                     match val.untag() {
                         Object::Cons(hook_list) => {
                             rooted_iter!(hooks, hook_list, cx);
@@ -113,7 +112,7 @@ pub(crate) fn autoload_do_load<'ob>(
                 "autoload arguments are not yet implemented"
             );
             root!(file, cx);
-            crate::lread::load(file, None, None, cx, env)?;
+            crate::lread::load(file, None, None, None, None, cx, env)?;
             match funname {
                 Some(func) => match func.get(cx).func(cx) {
                     Some(x) => Ok(x.into()),
@@ -197,11 +196,21 @@ fn internal__define_uninitialized_variable<'ob>(
 }
 
 #[defun]
-fn signal(mut error_symbol: GcObj, data: GcObj, env: &mut Rt<Env>) -> Result<bool> {
+fn signal(mut error_symbol: GcObj, data: GcObj, env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
     if error_symbol.nil() && data.nil() {
         error_symbol = sym::ERROR.into();
     }
-    Err(EvalError::signal(error_symbol, data, env).into())
+    Err(EvalError::signal(error_symbol, data, env, cx).into())
+}
+
+/// Exit to the innermost `catch` for `tag`, which returns `value`.
+#[defun]
+fn throw(tag: GcObj, value: GcObj, env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    ensure!(
+        env.catch_stack.iter().any(|x| x.bind(cx) == tag),
+        LispError::new(sym::NO_CATCH, [tag.into(), value.into()])
+    );
+    Err(EvalError::throw(tag, value, env).into())
 }
 
 #[defun]
@@ -215,7 +224,7 @@ fn set_default_toplevel_value<'ob>(
     value: GcObj,
     env: &'ob mut Rt<Env>,
 ) -> Result<GcObj<'ob>> {
-    env.set_var(symbol, value)?;
+    env.set_default(symbol, value)?;
     Ok(nil())
 }
 
@@ -225,8 +234,7 @@ fn set_default<'ob>(
     value: GcObj<'ob>,
     env: &'ob mut Rt<Env>,
 ) -> Result<GcObj<'ob>> {
-    // TODO: implement buffer local variables
-    env.set_var(symbol, value)?;
+    env.set_default(symbol, value)?;
     Ok(value)
 }
 
@@ -242,6 +250,9 @@ defsym!(LAMBDA);
 defsym!(CLOSURE);
 defsym!(CONDITION_CASE);
 defsym!(UNWIND_PROTECT);
+defsym!(SAVE_CURRENT_BUFFER);
+defsym!(SAVE_EXCURSION);
+defsym!(SAVE_RESTRICTION);
 defsym!(WHILE);
 defsym!(INLINE);
 defsym!(PROGN);
@@ -257,8 +268,8 @@ defsym!(AND);
 defsym!(OR);
 defsym!(INTERACTIVE);
 defsym!(CATCH);
-defsym!(THROW);
 defsym!(ERROR);
 defsym!(DEBUG);
+defsym!(KW_SUCCESS);
 
 defvar!(DEBUG_ON_ERROR, false);
@@ -1,5 +1,6 @@
 use crate::core::{
     env::{sym, Env},
+    error::{Type, TypeError},
     gc::{Context, Rt},
     object::Object,
 };
@@ -22,14 +23,14 @@ pub(crate) fn expand_file_name(
         let path = Path::new(dir);
         Ok(path.join(name).to_string_lossy().to_string())
     } else {
-        let dir = env.vars.get(sym::DEFAULT_DIRECTORY).unwrap();
-        match dir.get(cx) {
+        let dir = env.var(sym::DEFAULT_DIRECTORY, cx).unwrap_or_default();
+        match dir.untag() {
             Object::String(s) => {
-                let name: &str = s.try_into()?;
-                let path = Path::new(name);
+                let dir: &str = s.try_into()?;
+                let path = Path::new(dir);
                 Ok(path.join(name).to_string_lossy().to_string())
             }
-            _ => unreachable!("`default-directory' should be a string"),
+            _ => Err(TypeError::new(Type::String, dir).into()),
         }
     }
 }
@@ -42,3 +43,23 @@ fn file_directory_p(filename: &str) -> bool {
         Path::new(filename).is_dir()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::root;
+
+    #[test]
+    fn test_expand_file_name() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        env.set_var(sym::DEFAULT_DIRECTORY, cx.add("lisp/"))
+            .unwrap();
+        let expand = |name, dir| expand_file_name(name, dir, env, cx).unwrap();
+        assert_eq!(expand("foo.el", None), "lisp/foo.el");
+        assert_eq!(expand("foo.el", Some("/tmp")), "/tmp/foo.el");
+        assert_eq!(expand("/etc/foo.el", None), "/etc/foo.el");
+    }
+}
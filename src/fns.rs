@@ -2,14 +2,16 @@ use crate::{
     core::{
         cons::Cons,
         env::{sym, Env, Symbol},
-        error::{Type, TypeError},
+        error::{LispError, Type, TypeError},
         gc::{Context, IntoRoot, Rt},
         object::{
-            nil, Function, Gc, GcObj, HashTable, IntoObject, LispHashTable, LispString, LispVec,
-            List, ObjCell, Object,
+            nil, print_to_string, CloneIn, Function, Gc, GcObj, HashTable, IntervalTree,
+            IntoObject, LispHashTable, LispString, LispVec, List, ObjCell, Object, PrintOptions,
+            StringBuilder,
         },
     },
     data::aref,
+    print,
 };
 use crate::{root, rooted_iter};
 use anyhow::{bail, ensure, Result};
@@ -33,7 +35,11 @@ pub(crate) fn slice_into_list<'ob>(
 
 #[defun]
 pub(crate) fn eq(obj1: GcObj, obj2: GcObj) -> bool {
-    obj1.ptr_eq(obj2)
+    match (obj1.untag(), obj2.untag()) {
+        // A buffer copied into another block is still the same buffer
+        (Object::Buffer(b1), Object::Buffer(b2)) => b1 == b2,
+        _ => obj1.ptr_eq(obj2),
+    }
 }
 
 #[defun]
@@ -45,14 +51,29 @@ pub(crate) fn equal<'ob>(obj1: GcObj<'ob>, obj2: GcObj<'ob>) -> bool {
 pub(crate) fn eql<'ob>(obj1: GcObj<'ob>, obj2: GcObj<'ob>) -> bool {
     match (obj1.untag(), obj2.untag()) {
         (Object::Float(f1), Object::Float(f2)) => f1.to_bits() == f2.to_bits(),
-        _ => obj1.ptr_eq(obj2),
+        _ => eq(obj1, obj2),
     }
 }
 
 #[defun]
 fn equal_including_properties<'ob>(o1: GcObj<'ob>, o2: GcObj<'ob>) -> bool {
-    // TODO: implement text properties
-    equal(o1, o2)
+    equal(o1, o2) && equal_properties(o1, o2)
+}
+
+/// Compare the text properties of all strings in two objects that are
+/// already known to be `equal`.
+fn equal_properties(o1: GcObj, o2: GcObj) -> bool {
+    match (o1.untag(), o2.untag()) {
+        (Object::String(s1), Object::String(s2)) => s1.props().equal(&s2.props()),
+        (Object::Cons(c1), Object::Cons(c2)) => {
+            equal_properties(c1.car(), c2.car()) && equal_properties(c1.cdr(), c2.cdr())
+        }
+        (Object::Vec(v1), Object::Vec(v2)) => v1
+            .iter()
+            .zip(v2.iter())
+            .all(|(x, y)| equal_properties(x.get(), y.get())),
+        _ => true,
+    }
 }
 
 #[defun]
@@ -69,8 +90,17 @@ fn plist_get<'ob>(plist: Gc<List<'ob>>, prop: GcObj<'ob>) -> Result<GcObj<'ob>>
 }
 
 #[defun]
-pub(crate) fn prin1_to_string(object: GcObj, _noescape: Option<GcObj>) -> String {
-    format!("{object}")
+pub(crate) fn prin1_to_string(
+    object: GcObj,
+    noescape: Option<()>,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> String {
+    let options = PrintOptions {
+        escape: noescape.is_none(),
+        ..print::print_options(env, cx)
+    };
+    print_to_string(object, options)
 }
 
 #[defun]
@@ -387,7 +417,7 @@ fn require<'ob>(
     };
     let file = file.into_obj(cx);
     root!(file, cx);
-    match crate::lread::load(file, None, None, cx, env) {
+    match crate::lread::load(file, None, None, None, None, cx, env) {
         Ok(_) => Ok(feature.get(cx)),
         Err(e) => match noerror {
             Some(()) => Ok(sym::NIL),
@@ -397,14 +427,22 @@ fn require<'ob>(
 }
 
 #[defun]
-pub(crate) fn concat(sequences: &[GcObj]) -> Result<String> {
-    let mut concat = String::new();
+pub(crate) fn concat<'ob>(sequences: &[GcObj], cx: &'ob Context) -> Result<&'ob LispString> {
+    let mut concat = StringBuilder::default();
+    let mut props = IntervalTree::default();
+    let mut offset = 0;
     for elt in sequences {
         match elt.untag() {
-            Object::String(string) => concat.push_str(string.try_into()?),
+            Object::String(string) => {
+                concat.push_str(string);
+                props.graft(&string.props(), offset);
+                offset += string.len();
+            }
             _ => bail!("Currently only concatenating strings are supported"),
         }
     }
+    let concat: &LispString = cx.add_as(concat).untag();
+    *concat.props_mut() = props;
     Ok(concat)
 }
 
@@ -568,27 +606,34 @@ fn copy_sequence<'ob>(arg: GcObj<'ob>, cx: &'ob Context) -> Result<GcObj<'ob>> {
             }
             Ok(slice_into_list(&elements, tail, cx))
         }
-        Object::String(x) => {
-            let string: Result<&str, _> = x.try_into();
-            match string {
-                Ok(s) => Ok(cx.add(s)),
-                Err(_) => Ok(cx.add(x.to_vec())),
-            }
-        }
+        Object::String(x) => Ok(x.clone_in(cx).into()),
         Object::NIL => Ok(nil()),
         _ => Err(TypeError::new(Type::Sequence, arg).into()),
     }
 }
 
 #[defun]
-fn substring(string: &str, from: Option<usize>, to: Option<usize>) -> String {
-    let new_string = match (from, to) {
-        (None, None) => string,
-        (None, Some(t)) => &string[..t],
-        (Some(f), None) => &string[f..],
-        (Some(f), Some(t)) => &string[f..t],
-    };
-    new_string.to_owned()
+pub(crate) fn substring<'ob>(
+    string: &LispString,
+    from: Option<i64>,
+    to: Option<i64>,
+    cx: &'ob Context,
+) -> Result<&'ob LispString> {
+    // negative indices count from the end of the string
+    let len = string.len() as i64;
+    let index = |x: i64| if x < 0 { len + x } else { x };
+    let (beg, end) = (from.map_or(0, index), to.map_or(len, index));
+    ensure!(
+        0 <= beg && beg <= end && end <= len,
+        LispError::new(
+            sym::ARGS_OUT_OF_RANGE,
+            [string.into(), from.into(), to.into()]
+        )
+    );
+    let (beg, end) = (beg as usize, end as usize);
+    let new = string.slice(beg, end, cx).untag();
+    *new.props_mut() = string.props().slice(beg, end);
+    Ok(new)
 }
 
 #[defun]
@@ -614,6 +659,23 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn test_eq_buffer_copy() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        // defalias copies the buffer in the body into the global block
+        let form = "(let ((b (get-buffer-create \"eq-copy\")))
+                      (defalias 'eq-buffer-copy-test (list 'closure '(t) nil b))
+                      (list (eq (eq-buffer-copy-test) b)
+                            (eql (eq-buffer-copy-test) b)
+                            (eq (eq-buffer-copy-test) (get-buffer-create \"other\"))))";
+        let obj = crate::reader::read(form, cx).unwrap().0;
+        root!(obj, cx);
+        let result = rebind!(crate::interpreter::eval(obj, None, env, cx).unwrap(), cx);
+        assert_eq!(result, list![true, true, false; cx]);
+    }
+
     #[test]
     fn test_delq() {
         let roots = &RootSet::default();
@@ -630,6 +692,64 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_string_properties() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let face: GcObj = crate::core::env::intern("face", cx).into();
+        let hello: &LispString = cx.add_as("hello").untag();
+        hello.props_mut().put(1, 4, face, qtrue());
+        let world = cx.add("world");
+
+        let sub = substring(hello, Some(2), Some(-1), cx).unwrap();
+        assert_eq!(sub.to_string(), "#(\"ll\" 0 2 (face t))");
+        assert!(substring(hello, Some(3), Some(2), cx).is_err());
+
+        let concat = concat(&[world, hello.into()], cx).unwrap();
+        assert_eq!(concat.to_string(), "#(\"worldhello\" 6 9 (face t))");
+        let plain = cx.add("worldhello");
+        assert!(equal(concat.into(), plain));
+        assert!(!equal_including_properties(concat.into(), plain));
+        let list = list![concat; cx];
+        assert!(equal_including_properties(list, list![concat; cx]));
+    }
+
+    #[test]
+    fn test_raw_byte_strings() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let read = |x: &str| crate::reader::read(x, cx).unwrap().0;
+        let unibyte = read(r#""a\377""#);
+        let raw = read(r#""λ\377""#);
+        let unibyte_str: &LispString = unibyte.try_into().unwrap();
+        let raw_str: &LispString = raw.try_into().unwrap();
+        assert_eq!(unibyte_str.get_char_at(1), Some(0xFF));
+        assert_eq!(raw_str.get_char_at(1), Some(0x3F_FFFF));
+
+        // unibyte strings stay unibyte, and with other non-ASCII characters
+        // their raw bytes become raw byte characters
+        let result = concat(&[unibyte, unibyte], cx).unwrap();
+        assert!(equal(result.into(), read(r#""a\377a\377""#)));
+        let result = concat(&[unibyte, raw], cx).unwrap();
+        assert!(equal(result.into(), read(r#""a\377λ\377""#)));
+        let result = concat(&[read(r#""λ""#), unibyte], cx).unwrap();
+        assert!(equal(result.into(), read(r#""λa\377""#)));
+
+        let sub = substring(unibyte_str, Some(1), None, cx).unwrap();
+        assert_eq!(sub.get_char_at(0), Some(0xFF));
+        let sub = substring(raw_str, Some(1), None, cx).unwrap();
+        assert_eq!(sub.get_char_at(0), Some(0x3F_FFFF));
+        let sub = substring(raw_str, Some(0), Some(1), cx).unwrap();
+        assert!(equal(sub.into(), read(r#""λ""#)));
+
+        let copy = copy_sequence(raw, cx).unwrap();
+        assert!(equal(copy, raw));
+        assert!(!eq(copy, raw));
+
+        let symbol = crate::lread::intern(raw_str, cx);
+        assert_eq!(symbol.name(), "λ\u{FFFD}");
+    }
+
     #[test]
     fn test_nthcdr() {
         let roots = &RootSet::default();
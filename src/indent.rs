@@ -0,0 +1,166 @@
+//! Columns and indentation.
+use crate::core::{
+    env::{sym, Env},
+    gc::{Context, Rt},
+    object::{Buffer, GcObj, Object},
+};
+use anyhow::Result;
+use fn_macros::defun;
+
+defvar!(TAB_WIDTH, 8);
+defvar_bool!(INDENT_TABS_MODE, true);
+
+/// The value of `tab-width`, falling back to 8 if it is not a sensible width.
+fn tab_width(env: &Rt<Env>, cx: &Context) -> usize {
+    match env.var(sym::TAB_WIDTH, cx).map(GcObj::untag) {
+        Some(Object::Int(width @ 1..=1000)) => width as usize,
+        _ => 8,
+    }
+}
+
+fn indent_tabs_mode(env: &Rt<Env>, cx: &Context) -> bool {
+    !env.var(sym::INDENT_TABS_MODE, cx).is_some_and(GcObj::nil)
+}
+
+/// The column after `chr` when it starts at column `col`.
+fn next_column(chr: char, col: usize, tab_width: usize) -> usize {
+    if chr == '\t' {
+        (col / tab_width + 1) * tab_width
+    } else {
+        col + 1
+    }
+}
+
+/// The column of `pos`, counting from the start of its line.
+fn column_at(text: &Buffer, pos: usize, tab_width: usize) -> Result<usize> {
+    let bol = text.line_beginning(pos).max(text.begv());
+    let (front, back) = text.slice(bol, pos)?;
+    Ok(front
+        .chars()
+        .chain(back.chars())
+        .fold(0, |col, chr| next_column(chr, col, tab_width)))
+}
+
+/// Insert whitespace at point to get from column `from` to column `to`, using
+/// tabs if `use_tabs` is true.
+fn insert_indentation(text: &mut Buffer, from: usize, to: usize, tab_width: usize, use_tabs: bool) {
+    let mut from = from;
+    if use_tabs && to / tab_width > from / tab_width {
+        let tabs = to / tab_width - from / tab_width;
+        text.insert(&"\t".repeat(tabs));
+        from = to / tab_width * tab_width;
+    }
+    text.insert(&" ".repeat(to - from));
+}
+
+#[defun]
+pub(crate) fn current_column(env: &mut Rt<Env>, cx: &Context) -> Result<usize> {
+    let tab_width = tab_width(env, cx);
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    column_at(text, text.point(), tab_width)
+}
+
+/// Indent from point with tabs and spaces until `column` is reached. At least
+/// `minimum` spaces are inserted. Returns the column that was reached.
+#[defun]
+pub(crate) fn indent_to(
+    column: usize,
+    minimum: Option<usize>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<usize> {
+    let tab_width = tab_width(env, cx);
+    let use_tabs = indent_tabs_mode(env, cx);
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let from = column_at(text, text.point(), tab_width)?;
+    let to = column.max(from + minimum.unwrap_or(0));
+    insert_indentation(text, from, to, tab_width, use_tabs);
+    Ok(to)
+}
+
+/// Move point to `column` in the current line, or as close as the line allows.
+/// If `column` is in the middle of a character, point is moved after it. If
+/// `force` is non-nil and the line is too short, whitespace is added to reach
+/// `column`, and if it is t a tab that spans `column` is converted to spaces.
+/// Returns the column that was reached.
+#[defun]
+pub(crate) fn move_to_column(
+    column: usize,
+    force: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<usize> {
+    let tab_width = tab_width(env, cx);
+    let use_tabs = indent_tabs_mode(env, cx);
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let point = text.point();
+    let bol = text.line_beginning(point).max(text.begv());
+    let eol = text.line_end(point).min(text.zv());
+    let (mut pos, mut col, mut prev_col) = (bol, 0, 0);
+    let (front, back) = text.slice(bol, eol)?;
+    for chr in front.chars().chain(back.chars()) {
+        if col >= column {
+            break;
+        }
+        prev_col = col;
+        col = next_column(chr, col, tab_width);
+        pos += 1;
+    }
+    text.set_point(pos)?;
+    let force = force.filter(|x| !x.nil());
+    if col > column && force.is_some_and(|x| x == sym::TRUE) && text.char_at(pos - 1) == Some('\t')
+    {
+        // replace the tab with spaces so that `column` can be reached exactly
+        text.delete_range(pos - 1, pos)?;
+        text.insert(&" ".repeat(col - prev_col));
+        text.set_point(pos - 1 + column - prev_col)?;
+        col = column;
+    } else if col < column && force.is_some() {
+        insert_indentation(text, col, column, tab_width, use_tabs);
+        col = column;
+    }
+    Ok(col)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::editfns::{goto_char, insert};
+    use crate::root;
+
+    #[test]
+    fn test_columns() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("foo\n\tbar\nbaz")], env, cx).unwrap();
+        assert_eq!(current_column(env, cx).unwrap(), 3);
+        goto_char(9.into(), env, cx).unwrap();
+        assert_eq!(current_column(env, cx).unwrap(), 11);
+
+        assert_eq!(move_to_column(4, None, env, cx).unwrap(), 8);
+        assert_eq!(current_column(env, cx).unwrap(), 8);
+        assert_eq!(move_to_column(20, None, env, cx).unwrap(), 11);
+        assert_eq!(move_to_column(13, Some(cx.add(1)), env, cx).unwrap(), 13);
+        assert_eq!(
+            move_to_column(4, Some(sym::TRUE.into()), env, cx).unwrap(),
+            4
+        );
+
+        let buffer = env.current_buffer(cx).lock().unwrap();
+        assert_eq!(
+            buffer.text.substring(0, buffer.text.len_chars()).unwrap(),
+            "foo\n        bar  \nbaz"
+        );
+        drop(buffer);
+        goto_char(4.into(), env, cx).unwrap();
+        assert_eq!(indent_to(10, None, env, cx).unwrap(), 10);
+        assert_eq!(indent_to(10, Some(1), env, cx).unwrap(), 11);
+        let buffer = env.current_buffer(cx).lock().unwrap();
+        assert_eq!(buffer.text.substring(0, 8).unwrap(), "foo\t   \n");
+    }
+}
@@ -1,7 +1,9 @@
 use crate::core::{
     cons::{Cons, ElemStreamIter},
     env::{sym, Env, Symbol},
-    error::{ArgError, ErrorType, EvalError, EvalResult, Type, TypeError},
+    error::{
+        condition_matches, ArgError, ErrorType, EvalError, EvalResult, LispError, Type, TypeError,
+    },
     gc::{Context, Rt},
     object::{nil, qtrue, Function, Gc, GcObj, List, Object},
 };
@@ -33,10 +35,16 @@ pub(crate) fn eval<'ob>(
 impl Interpreter<'_> {
     fn eval_form<'ob>(&mut self, rt: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         match rt.get(cx) {
-            Object::Symbol(sym) => self.var_ref(sym, cx),
-            Object::Cons(_) => {
+            Object::Symbol(sym) => self
+                .var_ref(sym, cx)
+                .map_err(|e| e.into_signal(self.env, cx)),
+            Object::Cons(cons) => {
+                let addr = std::ptr::from_ref(cons).addr();
                 let x = rt.try_into().unwrap();
-                self.eval_sexp(x, cx)
+                match self.eval_sexp(x, cx) {
+                    Ok(x) => Ok(rebind!(x, cx)),
+                    Err(e) => Err(e.into_signal(self.env, cx).add_form(addr)),
+                }
             }
             _ => Ok(rt.bind(cx)),
         }
@@ -71,20 +79,27 @@ impl Interpreter<'_> {
                 sym::THROW => self.throw(forms.bind(cx), cx),
                 sym::CONDITION_CASE => self.condition_case(forms, cx),
                 sym::UNWIND_PROTECT => self.unwind_protect(forms, cx),
+                sym::SAVE_CURRENT_BUFFER => self.save_current_buffer(forms, cx),
+                sym::SAVE_EXCURSION => self.save_excursion(forms, cx),
+                sym::SAVE_RESTRICTION => self.save_restriction(forms, cx),
                 _ => {
                     root!(sym, cx);
                     self.eval_call(sym, forms, cx)
                 }
             },
-            other => Err(error!("Invalid Function: {other}")),
+            other => Err(error!(LispError::new(
+                sym::INVALID_FUNCTION,
+                [other.into()]
+            ))),
         }
     }
 
     fn catch<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, obj, cx);
-        let Some(tag) = forms.next() else {bail_err!(ArgError::new(1, 0, "catch"))};
+        let Some(tag) = forms.next() else {bail_err!(ArgError::new(1, 0, sym::CATCH))};
         // push this tag on the catch stack
         self.env.catch_stack.push(tag);
+        let depth = self.env.binding_depth();
         let result = match self.implicit_progn(forms, cx) {
             Ok(x) => Ok(rebind!(x, cx)),
             Err(e) => {
@@ -92,7 +107,9 @@ impl Interpreter<'_> {
                     if let Some((throw_tag, data)) = self.env.get_exception(id) {
                         let catch_tag = self.env.catch_stack.last().unwrap();
                         if catch_tag == throw_tag {
-                            return Ok(data.bind(cx));
+                            let data = data.bind(cx);
+                            self.env.unbind_to(depth, cx);
+                            return Ok(data);
                         }
                     }
                 }
@@ -108,7 +125,7 @@ impl Interpreter<'_> {
         let mut forms = obj.as_list()?;
         let len = forms.len() as u16;
         if len != 2 {
-            bail_err!(ArgError::new(2, len, "throw"));
+            bail_err!(ArgError::new(2, len, sym::THROW));
         }
         let tag = forms.next().unwrap()?;
         let value = forms.next().unwrap()?;
@@ -117,14 +134,14 @@ impl Interpreter<'_> {
         if self.env.catch_stack.iter().any(|x| x.bind(cx) == tag) {
             Err(EvalError::throw(tag, value, self.env))
         } else {
-            Err(error!("No catch for {tag}"))
+            Err(error!(LispError::new(sym::NO_CATCH, [tag.into(), value.into()])))
         }
     }
 
     fn defvar<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, obj, cx);
         // (defvar x ...)                 // (defvar)
-        let Some(sym) = forms.next() else {bail_err!(ArgError::new(1, 0, "defvar"))};
+        let Some(sym) = forms.next() else {bail_err!(ArgError::new(1, 0, sym::DEFVAR))};
         let name: Symbol = sym.bind(cx).try_into()?;
         root!(name, cx);
         let value = match forms.next() {
@@ -143,7 +160,7 @@ impl Interpreter<'_> {
         args: &Rt<GcObj>,
         cx: &'ob mut Context,
     ) -> EvalResult<'ob> {
-        let Some(func) = sym.bind(cx).follow_indirect(cx) else {bail_err!("Invalid function: {sym}")};
+        let Some(func) = sym.bind(cx).follow_indirect(cx) else {bail_err!(LispError::new(sym::VOID_FUNCTION, [sym.bind(cx).into()]))};
         root!(func, cx);
 
         match func.get(cx) {
@@ -178,7 +195,7 @@ impl Interpreter<'_> {
         let mut forms = obj.as_list()?;
         let len = forms.len() as u16;
         if len != 1 {
-            bail_err!(ArgError::new(1, len, "function"))
+            bail_err!(ArgError::new(1, len, sym::FUNCTION))
         }
 
         let form = forms.next().unwrap()?;
@@ -220,9 +237,9 @@ impl Interpreter<'_> {
             Some(x) => Ok(x.bind(cx)),
             None => {
                 let name = match prog_num {
-                    1 => "prog1",
-                    2 => "prog2",
-                    _ => "progn",
+                    1 => sym::PROG1,
+                    2 => sym::PROG2,
+                    _ => sym::PROGN,
                 };
                 Err(ArgError::new(prog_num, count, name).into())
             }
@@ -238,7 +255,7 @@ impl Interpreter<'_> {
         let (condition, body) = {
             let list: Gc<List> = obj.bind(cx).try_into()?;
             match list.untag() {
-                List::Nil => bail_err!(ArgError::new(1, 0, "while")),
+                List::Nil => bail_err!(ArgError::new(1, 0, sym::WHILE)),
                 List::Cons(cons) => (cons.car(), cons.cdr()),
             }
         };
@@ -295,9 +312,9 @@ impl Interpreter<'_> {
 
     fn eval_if<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, obj, cx);
-        let Some(condition) = forms.next() else {bail_err!(ArgError::new(2, 0, "if"))};
+        let Some(condition) = forms.next() else {bail_err!(ArgError::new(2, 0, sym::IF))};
         root!(condition, cx);
-        let Some(true_branch) = forms.next() else {bail_err!(ArgError::new(2, 1, "if"))};
+        let Some(true_branch) = forms.next() else {bail_err!(ArgError::new(2, 1, sym::IF))};
         root!(true_branch, cx);
         #[allow(clippy::if_not_else)]
         if self.eval_form(condition, cx)? != nil() {
@@ -321,12 +338,12 @@ impl Interpreter<'_> {
                     last_value.set(val);
                 }
                 (_, Some(_)) => bail_err!(TypeError::new(Type::Symbol, var)),
-                (_, None) => bail_err!(ArgError::new(arg_cnt, arg_cnt + 1, "setq")),
+                (_, None) => bail_err!(ArgError::new(arg_cnt, arg_cnt + 1, sym::SETQ)),
             }
             arg_cnt += 2;
         }
         if arg_cnt < 2 {
-            Err(ArgError::new(2, 0, "setq").into())
+            Err(ArgError::new(2, 0, sym::SETQ).into())
         } else {
             Ok(last_value.bind(cx))
         }
@@ -348,9 +365,9 @@ impl Interpreter<'_> {
             let mut iter = self.vars.iter().rev();
             match iter.find_map(|cons| (cons.car(cx) == sym).then(|| cons.cdr(cx))) {
                 Some(value) => Ok(value),
-                None => match self.env.vars.get(sym) {
-                    Some(v) => Ok(v.bind(cx)),
-                    None => Err(error!("Void variable: {sym}")),
+                None => match self.env.var(sym, cx) {
+                    Some(v) => Ok(v),
+                    None => Err(error!(LispError::new(sym::VOID_VARIABLE, [sym.into()]))),
                 },
             }
         }
@@ -374,7 +391,7 @@ impl Interpreter<'_> {
         let mut forms = value.as_list()?;
         match forms.len() {
             1 => Ok(forms.next().unwrap()?),
-            x => Err(ArgError::new(1, x as u16, "quote").into()),
+            x => Err(ArgError::new(1, x as u16, sym::QUOTE).into()),
         }
     }
 
@@ -387,17 +404,21 @@ impl Interpreter<'_> {
         rooted_iter!(iter, form, cx);
         let prev_len = self.vars.len();
         // (let x ...)                   // (let)
-        let Some(obj) = iter.next() else {bail_err!(ArgError::new(1, 0, "let"))};
+        let name = if parallel { sym::LET } else { sym::LET_STAR };
+        let Some(obj) = iter.next() else {bail_err!(ArgError::new(1, 0, name))};
         let varbind_count = if parallel {
             self.let_bind_parallel(obj, cx)
         } else {
             self.let_bind_serial(obj, cx)
         }?;
-        let obj = rebind!(self.implicit_progn(iter, cx)?, cx);
+        let result = match self.implicit_progn(iter, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
         // Remove old bindings
         self.vars.truncate(prev_len);
         self.env.unbind(varbind_count, cx);
-        Ok(obj)
+        result
     }
 
     fn let_bind_serial(&mut self, form: &Rt<GcObj>, cx: &mut Context) -> Result<u16, EvalError> {
@@ -506,7 +527,7 @@ impl Interpreter<'_> {
 
     fn unwind_protect<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, obj, cx);
-        let Some(body) = forms.next() else {bail_err!(ArgError::new(1, 0, "unwind-protect"))};
+        let Some(body) = forms.next() else {bail_err!(ArgError::new(1, 0, sym::UNWIND_PROTECT))};
         let result = match self.eval_form(body, cx) {
             Ok(x) => Ok(rebind!(x, cx)),
             Err(e) => Err(e),
@@ -515,46 +536,75 @@ impl Interpreter<'_> {
         result
     }
 
+    fn save_current_buffer<'ob>(
+        &mut self,
+        obj: &Rt<GcObj>,
+        cx: &'ob mut Context,
+    ) -> EvalResult<'ob> {
+        self.env.save_current_buffer(cx);
+        let result = match self.eval_progn(obj, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
+        self.env.unbind(1, cx);
+        result
+    }
+
+    fn save_excursion<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
+        self.env.save_excursion(cx)?;
+        let result = match self.eval_progn(obj, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
+        self.env.unbind(1, cx);
+        result
+    }
+
+    fn save_restriction<'ob>(&mut self, obj: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
+        self.env.save_restriction(cx)?;
+        let result = match self.eval_progn(obj, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
+        self.env.unbind(1, cx);
+        result
+    }
+
     fn condition_case<'ob>(&mut self, form: &Rt<GcObj>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, form, cx);
-        let Some(var) = forms.next() else {bail_err!(ArgError::new(2, 0, "condition-case"))};
+        let Some(var) = forms.next() else {bail_err!(ArgError::new(2, 0, sym::CONDITION_CASE))};
         root!(var, cx);
-        let Some(bodyform) = forms.next() else {bail_err!(ArgError::new(2, 1, "condition-case"))};
-        let err = match self.eval_form(bodyform, cx) {
-            Ok(x) => return Ok(rebind!(x, cx)),
-            Err(e) => e,
+        let Some(bodyform) = forms.next() else {bail_err!(ArgError::new(2, 1, sym::CONDITION_CASE))};
+        let depth = self.env.binding_depth();
+        // On success the value of the body is passed to a `:success` handler,
+        // and on error the error symbol and data are passed to the first
+        // handler whose condition matches the error.
+        let (value, err) = match self.eval_form(bodyform, cx) {
+            Ok(x) => (x, None),
+            Err(e) if matches!(e.error, ErrorType::Throw(_)) => return Err(e),
+            Err(e) => {
+                let Some((symbol, data)) = e.signal_data(self.env, cx) else {unreachable!("Exception not found")};
+                (cons!(symbol, data; cx), Some(e))
+            }
         };
-        if matches!(err.error, ErrorType::Throw(_)) {
-            return Err(err);
-        }
+        root!(value, cx);
         while let Some(handler) = forms.next() {
             match handler.get(cx) {
                 Object::Cons(cons) => {
-                    // Check that conditions match
                     let condition = cons.car();
-                    match condition.untag() {
-                        Object::Symbol(sym::ERROR) => {}
-                        Object::Cons(cons) => {
-                            for x in cons.elements() {
-                                let x = x?;
-                                // TODO: Handle different error symbols
-                                if x != sym::DEBUG && x != sym::ERROR {
-                                    bail_err!("non-error conditions {x} not yet supported")
-                                }
-                            }
+                    let matches = match (&err, value.get(cx)) {
+                        (Some(_), Object::Cons(error)) => {
+                            condition_matches(condition, error.car(), self.env, cx)
                         }
-                        _ => bail_err!("Invalid condition handler: {condition}"),
-                    }
-                    // Call handlers with error
-                    let error = if let ErrorType::Signal(id) = err.error {
-                        let Some((sym, data)) = self.env.get_exception(id) else {unreachable!("Exception not found")};
-                        cons!(sym, data; cx)
-                    } else {
-                        // TODO: Need to remove the anyhow branch once
-                        // full errors are implemented
-                        cons!(sym::ERROR, format!("{err}"); cx)
+                        _ => condition == sym::KW_SUCCESS,
                     };
-                    let binding = list!(var, error; cx).as_cons();
+                    if !matches {
+                        continue;
+                    }
+                    // Undo any bindings made by the body before running the
+                    // handler
+                    self.env.unbind_to(depth, cx);
+                    let binding = cons!(var, value; cx).as_cons();
                     self.vars.push(binding);
                     let list: Gc<List> = match cons.cdr().try_into() {
                         Ok(x) => x,
@@ -569,7 +619,10 @@ impl Interpreter<'_> {
                 invalid => bail_err!("Invalid condition handler: {invalid}"),
             }
         }
-        Err(err)
+        match err {
+            Some(err) => Err(err),
+            None => Ok(value.bind(cx)),
+        }
     }
 }
 
@@ -580,6 +633,19 @@ impl Rt<Gc<Function<'_>>> {
         env: &mut Rt<Env>,
         cx: &'ob mut Context,
         name: Option<&str>,
+    ) -> EvalResult<'ob> {
+        match self.call_inner(args, env, cx, name) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e.into_signal(env, cx)),
+        }
+    }
+
+    fn call_inner<'ob>(
+        &self,
+        args: &mut Rt<Vec<GcObj<'static>>>,
+        env: &mut Rt<Env>,
+        cx: &'ob mut Context,
+        name: Option<&str>,
     ) -> EvalResult<'ob> {
         let name = name.unwrap_or("lambda");
         let arg_cnt = args.len();
@@ -587,7 +653,7 @@ impl Rt<Gc<Function<'_>>> {
         match self.get(cx) {
             Function::ByteFn(f) => {
                 root!(f, cx);
-                crate::bytecode::call(f, args, name, env, cx)
+                crate::bytecode::call(f, args, env, cx)
                     .map_err(|e| e.add_trace(name, &args[..arg_cnt]))
             }
             Function::SubrFn(f) => {
@@ -597,10 +663,10 @@ impl Rt<Gc<Function<'_>>> {
                         Err(e) => EvalError::with_trace(e, name, &args[..arg_cnt]),
                     })
             }
-            Function::Cons(_) => call_closure(self.try_into().unwrap(), args, name, env, cx)
+            Function::Cons(_) => call_closure(self.try_into().unwrap(), args, env, cx)
                 .map_err(|e| e.add_trace(name, args)),
             Function::Symbol(sym) => {
-                let Some(func) = sym.follow_indirect(cx) else {bail_err!("Void Function: {sym}")};
+                let Some(func) = sym.follow_indirect(cx) else {bail_err!(LispError::new(sym::VOID_FUNCTION, [sym.into()]))};
                 match func.untag() {
                     Function::Cons(cons) if cons.car() == sym::AUTOLOAD => {
                         // TODO: inifinite loop if autoload does not resolve
@@ -623,20 +689,19 @@ impl Rt<Gc<Function<'_>>> {
 }
 
 fn call_closure<'ob>(
-    closure: &Rt<Gc<&Cons>>,
+    rt: &Rt<Gc<&Cons>>,
     args: &Rt<Vec<GcObj>>,
-    name: &str,
     env: &mut Rt<Env>,
     cx: &'ob mut Context,
 ) -> EvalResult<'ob> {
     cx.garbage_collect(false);
-    let closure: &Cons = closure.get(cx);
+    let closure: &Cons = rt.get(cx);
     match closure.car().untag() {
         Object::Symbol(sym::CLOSURE) => {
             rooted_iter!(forms, closure.cdr(), cx);
             // TODO: remove this temp vector
             let args = args.iter().map(|x| x.bind(cx)).collect();
-            let vars = bind_variables(&mut forms, args, name, cx)?;
+            let vars = bind_variables(&mut forms, args, rt.get(cx), cx)?;
             root!(vars, move(vars), cx);
             Interpreter { vars, env }.implicit_progn(forms, cx)
         }
@@ -647,7 +712,7 @@ fn call_closure<'ob>(
 fn bind_variables<'a>(
     forms: &mut ElemStreamIter<'_>,
     args: Vec<GcObj<'a>>,
-    name: &str,
+    closure: &Cons,
     cx: &'a Context,
 ) -> AnyResult<Vec<&'a Cons>> {
     // Add closure environment to variables
@@ -660,7 +725,7 @@ fn bind_variables<'a>(
     // (closure (t) (x y &rest z) ...)
     //              ^^^^^^^^^^^^^
     let Some(arg_list) = forms.next() else {bail!("Closure missing argument list")};
-    bind_args(arg_list.bind(cx), args, &mut vars, name, cx)?;
+    bind_args(arg_list.bind(cx), args, &mut vars, closure, cx)?;
     Ok(vars)
 }
 
@@ -683,7 +748,7 @@ fn bind_args<'a>(
     arg_list: GcObj,
     args: Vec<GcObj<'a>>,
     vars: &mut Vec<&'a Cons>,
-    name: &str,
+    closure: &Cons,
     cx: &'a Context,
 ) -> AnyResult<()> {
     let (required, optional, rest) = parse_arg_list(arg_list)?;
@@ -694,7 +759,7 @@ fn bind_args<'a>(
     // Ensure the minimum number of arguments is present
     ensure!(
         num_actual_args >= num_required_args,
-        ArgError::new(num_required_args, num_actual_args, name)
+        ArgError::new(num_required_args, num_actual_args, Object::Cons(closure))
     );
 
     let mut arg_values = args.into_iter();
@@ -717,7 +782,11 @@ fn bind_args<'a>(
         // Ensure too many args were not provided
         ensure!(
             arg_values.next().is_none(),
-            ArgError::new(num_required_args + num_optional_args, num_actual_args, name)
+            ArgError::new(
+                num_required_args + num_optional_args,
+                num_actual_args,
+                Object::Cons(closure)
+            )
         );
     }
     Ok(())
@@ -751,12 +820,12 @@ fn parse_arg_list(bindings: GcObj) -> AnyResult<(Vec<Symbol>, Vec<Symbol>, Optio
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use crate::core::{env::intern, gc::RootSet, object::IntoObject};
 
     use super::*;
 
-    fn check_interpreter<T>(test_str: &str, expect: T, cx: &mut Context)
+    pub(crate) fn check_interpreter<T>(test_str: &str, expect: T, cx: &mut Context)
     where
         T: IntoObject,
     {
@@ -1009,6 +1078,126 @@ mod test {
         check_error("(condition-case nil (if))", cx);
         check_error("(condition-case nil (if) nil)", cx);
         check_error("(condition-case nil (if) 5 (error 7))", cx);
+
+        check_interpreter(
+            "(condition-case nil (car 1) (void-variable 1) (wrong-type-argument 2))",
+            2,
+            cx,
+        );
+        check_interpreter(
+            "(condition-case nil (car 1) ((void-variable wrong-type-argument) 3))",
+            3,
+            cx,
+        );
+        check_interpreter(
+            "(condition-case nil (/ 1 0) (arith-error 4) (error 5))",
+            4,
+            cx,
+        );
+        check_interpreter("(condition-case nil (car 1) (t 6))", 6, cx);
+        check_interpreter("(condition-case nil (signal 'foo nil) (t 7))", 7, cx);
+        check_error("(condition-case nil (signal 'foo nil) (error 7))", cx);
+        check_error("(condition-case nil (car 1) (void-variable 1))", cx);
+        check_interpreter(
+            "(condition-case nil (condition-case nil (car 1) (arith-error 1)) (error 8))",
+            8,
+            cx,
+        );
+        check_interpreter(
+            "(condition-case x (+ 1 2) (:success (+ x 1)) (error 9))",
+            4,
+            cx,
+        );
+        check_interpreter("(condition-case x (+ 1 2) (error 9))", 3, cx);
+        check_interpreter(
+            "(condition-case x (car 1) (:success 10) (error 11))",
+            11,
+            cx,
+        );
+        check_interpreter(
+            "(progn (define-error 'my-error \"My error\" 'file-error)
+                    (condition-case x (signal 'my-error '(1)) (file-error (equal (cdr x) '(1)))))",
+            true,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_condition_case_new_env() {
+        // The standard errors are defined in a new environment
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let form = "(condition-case nil (car 1) (error 1))";
+        let obj = crate::reader::read(form, cx).unwrap().0;
+        root!(obj, cx);
+        let value = rebind!(eval(obj, None, env, cx).unwrap(), cx);
+        assert_eq!(value, 1);
+        let form = "(get 'arith-error 'error-conditions)";
+        let obj = crate::reader::read(form, cx).unwrap().0;
+        root!(obj, cx);
+        let value = rebind!(eval(obj, None, env, cx).unwrap(), cx);
+        assert_eq!(value, list![sym::ARITH_ERROR, sym::ERROR; cx]);
+    }
+
+    #[test]
+    fn test_error_symbols() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let check = |form: &str, expect: &str, cx: &mut Context| {
+            let test = format!("(equal (condition-case err {form} (t err)) '{expect})");
+            check_interpreter(&test, true, cx);
+        };
+        check("(car 5)", "(wrong-type-argument listp 5)", cx);
+        check(
+            "(1+ \"a\")",
+            "(wrong-type-argument number-or-marker-p \"a\")",
+            cx,
+        );
+        check(
+            "(no-such-variable-x)",
+            "(void-function no-such-variable-x)",
+            cx,
+        );
+        check(
+            "no-such-variable-x",
+            "(void-variable no-such-variable-x)",
+            cx,
+        );
+        check("(setq t 1)", "(setting-constant t)", cx);
+        check("(/ 5 0)", "(arith-error)", cx);
+        check("(mod 5 0)", "(arith-error)", cx);
+        check(
+            "(substring \"abc\" 2 5)",
+            "(args-out-of-range \"abc\" 2 5)",
+            cx,
+        );
+        check("(5 1)", "(invalid-function 5)", cx);
+        check("(throw 1 2)", "(no-catch 1 2)", cx);
+        check("(signal 'my-error '(1 2))", "(my-error 1 2)", cx);
+        check("(if)", "(wrong-number-of-arguments if 0)", cx);
+        check("(aref [1 2] 9)", "(args-out-of-range [1 2] 9)", cx);
+        // the data is the object itself, not a copy of it
+        check_interpreter(
+            "(let ((f #'(lambda (x) x))) (condition-case e (funcall f) (error (eq (nth 1 e) f))))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((v [1 2])) (condition-case e (aref v 9) (error (eq (nth 1 e) v))))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((s (make-symbol \"x\"))) (condition-case e (car s) (error (eq (nth 2 e) s))))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((s (propertize \"abc\" 'face 'bold))) (condition-case e (substring s 2 5) (error (eq (get-text-property 0 'face (nth 1 e)) 'bold))))",
+            true,
+            cx,
+        );
     }
 
     #[test]
@@ -1031,4 +1220,90 @@ mod test {
         check_error("(throw 1 2)", cx);
         check_error("(catch 2 (throw 3 4))", cx);
     }
+
+    #[test]
+    fn test_save_current_buffer() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let list = list!["foo", "*scratch*"; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(list (save-current-buffer (set-buffer (get-buffer-create \"foo\")) (buffer-name)) (buffer-name))",
+            list,
+            cx,
+        );
+        check_interpreter(
+            "(progn (catch 1 (save-current-buffer (set-buffer (get-buffer-create \"foo\")) (throw 1 2))) (buffer-name))",
+            "*scratch*",
+            cx,
+        );
+        check_interpreter(
+            "(save-current-buffer (set-buffer (get-buffer-create \"foo\")) (kill-buffer) (bufferp (current-buffer)))",
+            true,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_save_restriction() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let list = list![true, false; cx];
+        root!(list, cx);
+        let bounds = list![5, 10; cx];
+        root!(bounds, cx);
+        check_interpreter(
+            "(progn (insert \"hello world\") (list (save-restriction (narrow-to-region 2 4) (buffer-narrowed-p)) (buffer-narrowed-p)))",
+            list,
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello world\") (narrow-to-region 3 8) (save-restriction (widen) (goto-char 1) (insert \"ab\")) (list (point-min) (point-max)))",
+            bounds,
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello world\") (catch 1 (save-restriction (narrow-to-region 2 4) (throw 1 2))) (buffer-string))",
+            "hello world",
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello\") (condition-case nil (narrow-to-region 0 100000) (args-out-of-range (point-max))))",
+            6,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_save_excursion() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let list = list![2, 6; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(progn (insert \"hello\") (list (save-excursion (goto-char 2) (point)) (point)))",
+            list,
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello\") (goto-char 3) (save-excursion (goto-char 1) (insert \"ab\")) (point))",
+            5,
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello\") (catch 1 (save-excursion (goto-char 2) (throw 1 2))) (point))",
+            6,
+            cx,
+        );
+        check_interpreter(
+            "(progn (insert \"hello\") (condition-case nil (save-excursion (goto-char 2) (forward-char -5)) (error nil)) (point))",
+            6,
+            cx,
+        );
+        check_interpreter(
+            "(progn (save-excursion (set-buffer (get-buffer-create \"foo\"))) (buffer-name))",
+            "*scratch*",
+            cx,
+        );
+    }
 }
@@ -1,16 +1,20 @@
 use crate::core::env::Symbol;
 use crate::core::env::{sym, Env};
-use crate::core::error::{Type, TypeError};
+use crate::core::error::{EvalError, Type, TypeError};
 use crate::core::gc::Context;
 use crate::core::gc::Rt;
-use crate::core::object::{nil, Gc, GcObj, LispString, Object, WithLifetime};
-use crate::reader;
+use crate::core::object::{nil, Function, Gc, GcObj, LispBuffer, LispString, Object, WithLifetime};
+use crate::reader::{self, CharSource};
 use crate::{interpreter, root};
 use anyhow::{anyhow, Context as _};
 use anyhow::{bail, ensure, Result};
 use fn_macros::defun;
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::iter::Peekable;
 use std::path::{Path, PathBuf};
+use std::str;
+use std::time::SystemTime;
 
 fn check_lower_bounds(idx: Option<i64>, len: usize) -> Result<usize> {
     let len = len as i64;
@@ -41,66 +45,482 @@ pub(crate) fn read_from_string<'ob>(
     end: Option<i64>,
     cx: &'ob Context,
 ) -> Result<GcObj<'ob>> {
-    let len = string.len();
+    let len = string.chars().count();
     let start = check_lower_bounds(start, len)?;
     let end = check_upper_bounds(end, len)?;
 
-    let (obj, new_pos) = match reader::read(&string[start..end], cx) {
+    let chars = string.chars().take(end).skip(start).peekable();
+    let (obj, new_pos) = match reader::read_source(chars, cx) {
         Ok((obj, pos)) => (obj, pos),
         Err(mut e) => {
             e.update_pos(start);
             bail!(e);
         }
     };
-    Ok(cons!(obj, new_pos as i64; cx))
+    Ok(cons!(obj, (start + new_pos) as i64; cx))
 }
 
-pub(crate) fn load_internal(contents: &str, cx: &mut Context, env: &mut Rt<Env>) -> Result<bool> {
-    let mut pos = 0;
+/// Read an object from the text of `buffer` starting at `start`, and return it
+/// with the position after it.
+fn read_from_buffer<'ob>(
+    buffer: &LispBuffer,
+    start: usize,
+    cx: &'ob Context,
+) -> Result<(GcObj<'ob>, usize)> {
+    let data = buffer.lock()?;
+    let text = &data.text;
+    let start = start.clamp(text.begv(), text.zv());
+    let (front, back) = text.slice(start, text.zv())?;
+    let source = front.chars().chain(back.chars()).peekable();
+    let (obj, len) = reader::read_source(source, cx).map_err(|mut e| {
+        e.update_pos(start);
+        e
+    })?;
+    Ok((obj, start + len))
+}
+
+/// A character source that keeps the text that was consumed from it.
+struct Recorded<S> {
+    source: S,
+    text: String,
+}
+
+impl<S: CharSource> CharSource for Recorded<S> {
+    fn peek_char(&mut self) -> Option<char> {
+        self.source.peek_char()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let chr = self.source.next_char()?;
+        self.text.push(chr);
+        Some(chr)
+    }
+}
+
+/// Consume the text of one object from `source` and return it. This is for
+/// sources that call Lisp or wait for input, which can't be done while the
+/// reader is allocating, so the text is read afterwards.
+fn object_text(source: impl CharSource) -> String {
+    let mut recorded = Recorded {
+        source,
+        text: String::new(),
+    };
+    reader::skip_object(&mut recorded);
+    recorded.text
+}
+
+/// A function stream. The function is called with no arguments to get each
+/// character, and with a character to put it back.
+struct FunctionSource<'a, 'rt> {
+    func: &'a Rt<Gc<Function<'static>>>,
+    env: &'a mut Rt<Env>,
+    cx: &'a mut Context<'rt>,
+    /// The character that was fetched to look at but not consumed yet.
+    peeked: Option<char>,
+    /// The error from calling the function, which ends the stream.
+    error: Option<anyhow::Error>,
+}
+
+impl FunctionSource<'_, '_> {
+    fn fetch(&mut self) -> Result<Option<char>> {
+        let cx = &mut *self.cx;
+        root!(args, Vec::new(), cx);
+        let chr = self.func.call(args, self.env, cx, None)?;
+        match chr.untag() {
+            Object::Int(code) => match u32::try_from(code).ok().and_then(char::from_u32) {
+                Some(chr) => Ok(Some(chr)),
+                None => bail!("Invalid character from stream: {code}"),
+            },
+            Object::NIL => Ok(None),
+            _ => bail!(TypeError::new(Type::Int, chr)),
+        }
+    }
+
+    /// Give back the character that was looked at but not consumed.
+    fn unread(&mut self) -> Result<()> {
+        if let Some(chr) = self.peeked.take() {
+            let cx = &mut *self.cx;
+            root!(args, move(vec![cx.add(chr as i64)]), cx);
+            self.func.call(args, self.env, cx, None)?;
+        }
+        Ok(())
+    }
+}
+
+impl CharSource for FunctionSource<'_, '_> {
+    fn peek_char(&mut self) -> Option<char> {
+        if self.peeked.is_none() && self.error.is_none() {
+            match self.fetch() {
+                Ok(chr) => self.peeked = chr,
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self.peeked
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let chr = self.peek_char();
+        self.peeked = None;
+        chr
+    }
+}
+
+/// Read an object from a function stream. Characters are fetched as they are
+/// needed, and the one that was looked at past the end of the object is given
+/// back.
+fn read_from_function<'ob>(
+    func: &Rt<Gc<Function<'static>>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<GcObj<'ob>> {
+    let mut source = FunctionSource {
+        func,
+        env,
+        cx,
+        peeked: None,
+        error: None,
+    };
+    let text = object_text(&mut source);
+    if let Some(e) = source.error.take() {
+        return Err(e);
+    }
+    source.unread()?;
+    Ok(reader::read(&text, cx)?.0)
+}
+
+/// Standard input, read a line at a time.
+struct StdinSource {
+    line: Peekable<std::vec::IntoIter<char>>,
+    error: Option<io::Error>,
+}
+
+impl CharSource for StdinSource {
+    fn peek_char(&mut self) -> Option<char> {
+        while self.line.peek().is_none() && self.error.is_none() {
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => self.line = line.chars().collect::<Vec<_>>().into_iter().peekable(),
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self.line.peek().copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        self.peek_char()?;
+        self.line.next()
+    }
+}
+
+/// Read an object from standard input, a line at a time. Text on the last line
+/// after the object is discarded.
+fn read_from_stdin<'ob>(cx: &'ob Context) -> Result<GcObj<'ob>> {
+    let mut source = StdinSource {
+        line: Vec::new().into_iter().peekable(),
+        error: None,
+    };
+    let text = object_text(&mut source);
+    if let Some(e) = source.error {
+        return Err(e.into());
+    }
+    Ok(reader::read(&text, cx)?.0)
+}
+
+/// Read one Lisp expression from `stream`. The stream can be a buffer, which
+/// is read from point and has point moved past the expression, a marker,
+/// which is read from and moved the same way, a string, a function that
+/// returns characters, or `t` for standard input. It defaults to the value of
+/// `standard-input`.
+#[defun]
+pub(crate) fn read<'ob>(
+    stream: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<GcObj<'ob>> {
+    let stream = match stream.map(|x| x.bind(cx)).filter(|x| !x.nil()) {
+        Some(stream) => stream,
+        None => env.var(sym::STANDARD_INPUT, cx).unwrap_or_else(nil),
+    };
+    match stream.untag() {
+        Object::Buffer(buffer) => {
+            let point = buffer.lock()?.text.point();
+            let (obj, end) = read_from_buffer(buffer, point, cx)?;
+            buffer.lock()?.text.set_point(end)?;
+            Ok(obj)
+        }
+        Object::Marker(marker) => {
+            let (Some(buffer), Some(pos)) = (marker.buffer(), marker.position()) else {
+                bail!("Marker does not point anywhere");
+            };
+            let (obj, end) = read_from_buffer(buffer, pos, cx)?;
+            marker.set(buffer, end)?;
+            Ok(obj)
+        }
+        Object::String(string) => Ok(reader::read(string.try_into()?, cx)?.0),
+        Object::Symbol(sym::TRUE) | Object::NIL => read_from_stdin(cx),
+        _ => {
+            let func: Gc<Function> = stream.try_into()?;
+            root!(func, cx);
+            read_from_function(func, env, cx)
+        }
+    }
+}
+
+/// A character source that remembers where its lines start, so that positions
+/// in it can be turned into a line and column.
+struct SourceLines<S> {
+    source: S,
+    pos: usize,
+    newlines: Vec<usize>,
+}
+
+impl<S: CharSource> SourceLines<S> {
+    fn new(source: S) -> Self {
+        Self {
+            source,
+            pos: 0,
+            newlines: Vec::new(),
+        }
+    }
+
+    /// The location of `pos` as `FILE:LINE:COLUMN`, with lines and columns
+    /// counted from 1.
+    fn location(&self, file: &str, pos: usize) -> String {
+        let line = self.newlines.partition_point(|x| *x < pos);
+        let bol = match line {
+            0 => 0,
+            line => self.newlines[line - 1] + 1,
+        };
+        format!("{file}:{}:{}", line + 1, pos - bol + 1)
+    }
+}
+
+impl<S: CharSource> CharSource for SourceLines<S> {
+    fn peek_char(&mut self) -> Option<char> {
+        self.source.peek_char()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let chr = self.source.next_char()?;
+        if chr == '\n' {
+            self.newlines.push(self.pos);
+        }
+        self.pos += 1;
+        Some(chr)
+    }
+}
+
+/// Read and evaluate the forms in `source`. If `file` is given, the reader
+/// records where every form came from, and errors are reported with the
+/// location in `file` of the innermost form that was being evaluated.
+pub(crate) fn load_internal(
+    source: impl CharSource,
+    file: Option<&str>,
+    cx: &mut Context,
+    env: &mut Rt<Env>,
+) -> Result<bool> {
+    let mut source = SourceLines::new(source);
     loop {
-        let (obj, new_pos) = match reader::read(&contents[pos..], cx) {
-            Ok((obj, pos)) => (obj, pos),
+        let pos = source.pos;
+        let read = match file {
+            Some(file) => reader::read_with_positions(&mut source, file, cx)
+                .map(|(obj, pos, positions)| (obj, pos, Some(positions))),
+            None => reader::read_source(&mut source, cx).map(|(obj, pos)| (obj, pos, None)),
+        };
+        let (obj, new_pos, positions) = match read {
+            Ok(x) => x,
             Err(reader::Error::EmptyStream) => return Ok(true),
             Err(mut e) => {
                 e.update_pos(pos);
+                if let Some(file) = file {
+                    let location = source.location(file, e.position());
+                    bail!("{location}: {e}");
+                }
                 bail!(e);
             }
         };
         if crate::debug::debug_enabled() {
-            let content = &contents[pos..(new_pos + pos)];
-            println!("-----READ START-----\n {content}");
+            println!("-----READ START-----\n {obj}");
             println!("-----READ END-----");
         }
         root!(obj, cx);
-        interpreter::eval(obj, None, env, cx)?;
+        if let Err(mut e) = interpreter::eval(obj, None, env, cx) {
+            if let (Some(file), Some(positions)) = (file, positions) {
+                // an error from a nested `load' already has its location
+                if let Some(err) = e
+                    .downcast_mut::<EvalError>()
+                    .filter(|x| x.location().is_none())
+                {
+                    let offset = err
+                        .forms()
+                        .iter()
+                        .find_map(|x| positions.get(*x))
+                        .unwrap_or_else(|| positions.start());
+                    err.set_location(source.location(file, pos + offset));
+                }
+            }
+            return Err(e);
+        }
         assert_ne!(new_pos, 0);
-        pos += new_pos;
     }
 }
 
-fn file_in_path(file: &str, path: &str) -> Option<PathBuf> {
-    let path = Path::new(path).join(file);
-    if path.exists() {
-        Some(path)
-    } else {
-        let with_ext = path.with_extension("el");
-        with_ext.exists().then_some(with_ext)
+/// The characters of a UTF-8 file, decoded as they are needed so the file is
+/// never held in memory. Invalid bytes are read as the replacement character,
+/// except for the raw bytes of `utf-8-emacs`. Reading stops at the first IO
+/// error, which is kept in `error`.
+struct FileChars {
+    bytes: Peekable<io::Bytes<BufReader<File>>>,
+    error: Option<io::Error>,
+    /// Characters that have been decoded but not returned yet, last first.
+    pending: Vec<char>,
+}
+
+impl FileChars {
+    fn new(file: File) -> Self {
+        Self {
+            bytes: BufReader::new(file).bytes().peekable(),
+            error: None,
+            pending: Vec::new(),
+        }
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        match self.bytes.next()? {
+            Ok(byte) => Some(byte),
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Consume the next byte only if it is a UTF-8 continuation byte.
+    fn continuation_byte(&mut self) -> Option<u8> {
+        self.bytes.next_if(|x| matches!(x, Ok(0x80..=0xBF)))?.ok()
     }
 }
 
-fn find_file_in_load_path(file: &str, cx: &Context, env: &Rt<Env>) -> Result<PathBuf> {
-    let load_path = env.vars.get(sym::LOAD_PATH).unwrap();
-    let paths = load_path
-        .bind(cx)
-        .as_list()
-        .context("`load-path' was not a list")?;
-    let mut final_file = None;
+impl Iterator for FileChars {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(chr) = self.pending.pop() {
+            return Some(chr);
+        }
+        let first = self.byte()?;
+        let len = match first {
+            0..=0x7F => return Some(first.into()),
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return Some(char::REPLACEMENT_CHARACTER),
+        };
+        let mut buf = [first, 0, 0, 0];
+        for byte in &mut buf[1..len] {
+            // An invalid or truncated sequence leaves the byte that ends it
+            // for the next character
+            let Some(next) = self.continuation_byte() else {
+                return Some(char::REPLACEMENT_CHARACTER);
+            };
+            *byte = next;
+        }
+        if let 0xC0 | 0xC1 = first {
+            // A raw byte, as Emacs encodes it in `utf-8-emacs`. Characters
+            // cannot hold raw bytes, so it is passed to the reader as the
+            // octal escape that reads as the raw byte.
+            let byte = 0x80 | (first & 1) << 6 | (buf[1] & 0x3F);
+            self.pending.extend(format!("\\{byte:o}").chars().rev());
+            return self.pending.pop();
+        }
+        let chr = str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|x| x.chars().next());
+        Some(chr.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|x| x.modified()).ok()
+}
+
+/// Look in `dir` for `file` with each of `suffixes` added. If `prefer_newer`
+/// is true the most recently modified of the files is used, otherwise the
+/// first one that exists.
+fn file_in_path(file: &str, dir: &Path, suffixes: &[&str], prefer_newer: bool) -> Option<PathBuf> {
+    let mut found = suffixes
+        .iter()
+        .map(|suffix| dir.join(format!("{file}{suffix}")))
+        .filter(|x| x.is_file());
+    if !prefer_newer {
+        return found.next();
+    }
+    found.reduce(|newest, x| {
+        if modified(&x) > modified(&newest) {
+            x
+        } else {
+            newest
+        }
+    })
+}
+
+/// The suffixes to try when looking for `file` to load. These are the ones in
+/// `load-suffixes` followed by the empty suffix, unless `nosuffix` is true and
+/// only the empty suffix is tried, or `must_suffix` is true and the empty
+/// suffix is left out. A file that already ends in `.el` or `.elc`, or has a
+/// directory in it, does not need a suffix.
+fn load_suffixes<'ob>(
+    file: &str,
+    nosuffix: bool,
+    must_suffix: bool,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Vec<&'ob str>> {
+    if nosuffix {
+        return Ok(vec![""]);
+    }
+    let mut suffixes = Vec::new();
+    if let Some(list) = env.var(sym::LOAD_SUFFIXES, cx) {
+        for suffix in list.as_list().context("`load-suffixes' was not a list")? {
+            suffixes.push(suffix?.try_into()?);
+        }
+    }
+    let path = Path::new(file);
+    let has_suffix = path.extension().is_some_and(|x| x == "el" || x == "elc");
+    let has_dir = path.parent().is_some_and(|x| !x.as_os_str().is_empty());
+    if !must_suffix || has_suffix || has_dir {
+        suffixes.push("");
+    }
+    Ok(suffixes)
+}
+
+/// Find the file to load for `file`. It is looked for relative to the current
+/// directory first and then in each directory of `load-path`, trying each of
+/// `suffixes` in a directory before moving on to the next.
+fn find_file_in_load_path(
+    file: &str,
+    suffixes: &[&str],
+    cx: &Context,
+    env: &Rt<Env>,
+) -> Result<PathBuf> {
+    let prefer_newer = env
+        .var(sym::LOAD_PREFER_NEWER, cx)
+        .is_some_and(|x| !x.nil());
+    if let Some(x) = file_in_path(file, Path::new(""), suffixes, prefer_newer) {
+        return Ok(x);
+    }
+    if Path::new(file).is_absolute() {
+        bail!("Unable to find file `{file}'");
+    }
+    let load_path = env.var(sym::LOAD_PATH, cx).unwrap_or_default();
+    let paths = load_path.as_list().context("`load-path' was not a list")?;
     for path in paths {
         match path?.untag() {
             Object::String(path) => {
-                if let Some(x) = file_in_path(file, path.try_into()?) {
-                    final_file = Some(x);
-                    break;
+                let dir: &str = path.try_into()?;
+                if let Some(x) = file_in_path(file, Path::new(dir), suffixes, prefer_newer) {
+                    return Ok(x);
                 }
             }
             x => {
@@ -109,63 +529,110 @@ fn find_file_in_load_path(file: &str, cx: &Context, env: &Rt<Env>) -> Result<Pat
             }
         }
     }
-    final_file.ok_or_else(|| anyhow!("Unable to find file `{file}' in load-path"))
+    bail!("Unable to find file `{file}' in load-path")
+}
+
+/// The oldest format of byte-compiled files that can be loaded. Older ones
+/// describe the arguments of functions with a list instead of a number.
+const MIN_ELC_VERSION: u8 = 23;
+
+/// Check the header of the byte-compiled file `path`, which is `;ELC` followed
+/// by the version of the format as a byte.
+fn check_elc_header(path: &Path) -> Result<()> {
+    let mut header = [0; 5];
+    File::open(path)
+        .and_then(|mut x| x.read_exact(&mut header))
+        .with_context(|| format!("Couldn't read file {}", path.display()))?;
+    let version = header[4];
+    ensure!(
+        header.starts_with(b";ELC") && version != 0,
+        "File `{}' was not compiled in Emacs",
+        path.display()
+    );
+    ensure!(
+        version >= MIN_ELC_VERSION,
+        "File `{}' was compiled with an unsupported format version {version}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Check whether the byte-compiled file `elc` has a source file next to it that
+/// was modified after it.
+fn source_is_newer(elc: &Path) -> bool {
+    let source = elc.with_extension("el");
+    source.is_file() && modified(&source) > modified(elc)
 }
 
+/// Load the Lisp file `file`, which is looked for with each of the suffixes in
+/// `load-suffixes` and then as it is. `nosuffix` means that only `file` itself
+/// is tried, and `must-suffix` that it must have one of the suffixes. If more
+/// than one of the files exists in a directory, the first is loaded, unless
+/// `load-prefer-newer` is non-nil and the newest is loaded. Byte-compiled
+/// `.elc` files are checked to have been compiled by Emacs.
 #[defun]
 pub(crate) fn load(
     file: &Rt<Gc<&LispString>>,
     noerror: Option<()>,
     nomessage: Option<()>,
+    nosuffix: Option<()>,
+    must_suffix: Option<()>,
     cx: &mut Context,
     env: &mut Rt<Env>,
 ) -> Result<bool> {
     let noerror = noerror.is_some();
     let nomessage = nomessage.is_some();
     let file: &str = file.get(cx).try_into()?;
-    let final_file = if Path::new(file).exists() {
-        PathBuf::from(file)
-    } else {
-        match find_file_in_load_path(file, cx, env) {
-            Ok(x) => x,
-            Err(e) => {
-                return match noerror {
-                    true => Ok(false),
-                    false => Err(e),
-                };
-            }
+    let suffixes = load_suffixes(file, nosuffix.is_some(), must_suffix.is_some(), env, cx)?;
+    let final_file = match find_file_in_load_path(file, &suffixes, cx, env) {
+        Ok(x) => x,
+        Err(e) => {
+            return match noerror {
+                true => Ok(false),
+                false => Err(e),
+            };
         }
     };
+    let compiled = final_file.extension().is_some_and(|x| x == "elc");
+    if compiled {
+        check_elc_header(&final_file)?;
+    }
 
     if !nomessage {
-        println!("Loading {file}...");
+        if compiled && source_is_newer(&final_file) {
+            println!("Loading {file} (compiled; note, source file is newer)...");
+        } else {
+            println!("Loading {file}...");
+        }
     }
     let new_load_file = cx.add(final_file.to_string_lossy().to_string());
-    let prev_load_file = match env.vars.get_mut(sym::LOAD_FILE_NAME) {
-        Some(val) => {
-            let prev = val.bind(cx);
-            val.set(new_load_file);
-            prev
-        }
-        None => nil(),
-    };
-    root!(prev_load_file, cx);
-    let result = match fs::read_to_string(&final_file)
+    let depth = env.binding_depth();
+    env.varbind(sym::LOAD_FILE_NAME, new_load_file, cx);
+    let result = match File::open(&final_file)
         .with_context(|| format!("Couldn't open file {:?}", final_file.as_os_str()))
     {
-        Ok(content) => load_internal(&content, cx, env),
+        Ok(file) => {
+            let mut chars = FileChars::new(file);
+            let name = final_file.to_string_lossy();
+            let result = load_internal((&mut chars).peekable(), Some(&name), cx, env);
+            match chars.error {
+                Some(e) => Err(anyhow!(e))
+                    .with_context(|| format!("Couldn't read file {}", final_file.display())),
+                None => result,
+            }
+        }
         Err(e) => match noerror {
             true => Ok(false),
             false => Err(e),
         },
     };
-    env.vars.insert(sym::LOAD_FILE_NAME, &*prev_load_file);
+    env.unbind_to(depth, cx);
     result
 }
 
 #[defun]
-pub(crate) fn intern<'ob>(string: &str, cx: &'ob Context) -> Symbol<'ob> {
-    crate::core::env::intern(string, cx)
+pub(crate) fn intern<'ob>(string: &LispString, cx: &'ob Context) -> Symbol<'ob> {
+    crate::core::env::intern(&string.to_str_lossy(), cx)
 }
 
 #[defun]
@@ -194,14 +661,18 @@ defvar!(LEXICAL_BINDING, true);
 defvar!(CURRENT_LOAD_LIST);
 defvar!(LOAD_HISTORY);
 defvar!(LOAD_PATH, list!["lisp"]);
+defvar!(LOAD_SUFFIXES, list![".elc", ".el"]);
+defvar_bool!(LOAD_PREFER_NEWER, false);
 defvar!(LOAD_FILE_NAME);
 defvar!(BYTE_BOOLEAN_VARS);
+defvar!(STANDARD_INPUT, true);
 
 #[cfg(test)]
 mod test {
 
     use super::*;
     use crate::core::gc::RootSet;
+    use crate::interpreter::test::check_interpreter;
     use crate::root;
 
     #[test]
@@ -210,11 +681,272 @@ mod test {
         let roots = &RootSet::default();
         let cx = &mut Context::new(roots);
         root!(env, Env::default(), cx);
-        load_internal("(setq foo 1) (setq bar 2) (setq baz 1.5)", cx, env).unwrap();
+        let source = "(setq foo 1) (setq bar 2) (setq baz 1.5)";
+        load_internal(source.chars().peekable(), None, cx, env).unwrap();
 
         let obj = reader::read("(+ foo bar baz)", cx).unwrap().0;
         root!(obj, cx);
         let val = interpreter::eval(obj, None, env, cx).unwrap();
         assert_eq!(val, 4.5);
     }
+
+    #[test]
+    fn test_read_chars() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        // positions are counted in characters
+        let obj = read_from_string("λ (b) c", Some(1), None, cx).unwrap();
+        assert_eq!(
+            obj,
+            cons!(list![crate::core::env::intern("b", cx); cx], 5; cx)
+        );
+
+        let path = std::env::temp_dir().join(format!("rune-read-chars-{}", std::process::id()));
+        std::fs::write(&path, b"\xce\xbb (a)\n\xff").unwrap();
+        let chars: String = FileChars::new(File::open(&path).unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(chars, "λ (a)\n\u{FFFD}");
+
+        let file_chars = |bytes: &[u8]| -> String {
+            std::fs::write(&path, bytes).unwrap();
+            let chars = FileChars::new(File::open(&path).unwrap()).collect();
+            std::fs::remove_file(&path).unwrap();
+            chars
+        };
+        // an invalid sequence does not consume the byte that ends it
+        assert_eq!(file_chars(b"\xC3(a)"), "\u{FFFD}(a)");
+        assert_eq!(file_chars(b"\xE2\x82(a)"), "\u{FFFD}(a)");
+        // a sequence cut short by the end of the file
+        assert_eq!(file_chars(b"(a)\xE2\x82"), "(a)\u{FFFD}");
+        // raw bytes in utf-8-emacs
+        assert_eq!(file_chars(b"\"\xC1\xBF\xC0\x80\""), "\"\\377\\200\"");
+    }
+
+    #[test]
+    fn test_load_raw_bytes() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let path = std::env::temp_dir().join(format!("rune-raw-bytes-{}", std::process::id()));
+        std::fs::write(&path, b"(setq raw \"\xCE\xBB\xC1\xBF\")").unwrap();
+        let chars = FileChars::new(File::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        load_internal(chars.peekable(), None, cx, env).unwrap();
+
+        let obj = reader::read("(list (length raw) (aref raw 1))", cx)
+            .unwrap()
+            .0;
+        root!(obj, cx);
+        let val = rebind!(interpreter::eval(obj, None, env, cx).unwrap(), cx);
+        assert_eq!(val, list![2, 0x3F_FFFF; cx]);
+    }
+
+    #[test]
+    fn test_load_error_location() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let error = |contents: &str, cx: &mut Context, env: &mut Rt<Env>| {
+            let source = contents.chars().peekable();
+            let err = load_internal(source, Some("test.el"), cx, env).unwrap_err();
+            err.to_string()
+        };
+        let err = error("(setq a 1)\n(progn\n  (car 1))", cx, env);
+        assert!(err.starts_with("test.el:3:3: "), "{err}");
+        let err = error("(progn\n  '(1 2) (λ 1))", cx, env);
+        assert!(err.starts_with("test.el:2:10: "), "{err}");
+        let err = error("\n\n  void-var", cx, env);
+        assert!(err.starts_with("test.el:3:3: "), "{err}");
+        let err = error("(setq a 1)\n  (foo", cx, env);
+        assert!(err.starts_with("test.el:2:3: "), "{err}");
+
+        let err = load_internal("(car 1)".chars().peekable(), None, cx, env).unwrap_err();
+        let message = "Wrong type argument: listp, 1";
+        assert!(err.to_string().starts_with(message), "{err}");
+    }
+
+    #[test]
+    fn test_load_elc() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let eval = |source: &str, cx: &mut Context, env: &mut Rt<Env>| {
+            let obj = reader::read(source, cx).unwrap().0;
+            root!(obj, cx);
+            rebind!(interpreter::eval(obj, None, env, cx).unwrap(), cx)
+        };
+        let load = |file: &Path, must_suffix, cx: &mut Context, env: &mut Rt<Env>| {
+            let file: Gc<&LispString> = cx.add(file.to_str().unwrap()).try_into().unwrap();
+            root!(file, cx);
+            load(file, None, Some(()), None, must_suffix, cx, env)
+        };
+        eval("(setq load-suffixes '(\".elc\" \".el\"))", cx, env);
+
+        let dir = std::env::temp_dir().join(format!("rune-load-elc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let elc = "\
+;ELC\x17\0\0\0
+;;; Compiled
+;;; in Emacs version 29.1
+
+#@20 Return X plus one.\x1f
+(defalias 'elc-test-add1 #[257 \"\\211T\\207\" [] 2 (#$ . 58)])
+(byte-code \"\\300\\301\\302\\\"\\207\" [set elc-test 2] 3)
+";
+        std::fs::write(dir.join("foo.elc"), elc).unwrap();
+        std::fs::write(dir.join("foo.el"), "(setq elc-test 1)").unwrap();
+        let hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let file = File::options()
+            .write(true)
+            .open(dir.join("foo.elc"))
+            .unwrap();
+        file.set_modified(hour_ago).unwrap();
+
+        // the compiled file is used even though the source is newer
+        assert!(load(&dir.join("foo"), None, cx, env).unwrap());
+        let value = eval("(list elc-test (elc-test-add1 4))", cx, env);
+        assert_eq!(value, list![2, 5; cx]);
+        eval("(setq load-prefer-newer t)", cx, env);
+        assert!(load(&dir.join("foo"), None, cx, env).unwrap());
+        assert_eq!(eval("elc-test", cx, env), 1);
+
+        std::fs::write(dir.join("bar.el"), "(setq elc-test 3)").unwrap();
+        assert!(load(&dir.join("bar.el"), Some(()), cx, env).unwrap());
+        assert_eq!(eval("elc-test", cx, env), 3);
+        // a file without a directory is looked for in `load-path'
+        std::fs::write(dir.join("baz"), "(setq elc-test 4)").unwrap();
+        eval(
+            &format!("(setq load-path '({:?}))", dir.to_str().unwrap()),
+            cx,
+            env,
+        );
+        assert!(load(Path::new("baz"), Some(()), cx, env).is_err());
+        assert!(load(Path::new("baz"), None, cx, env).unwrap());
+        assert_eq!(eval("elc-test", cx, env), 4);
+
+        std::fs::write(dir.join("bad.elc"), "(setq elc-test 5)").unwrap();
+        let err = load(&dir.join("bad"), None, cx, env).unwrap_err();
+        assert!(
+            err.to_string().contains("was not compiled in Emacs"),
+            "{err}"
+        );
+        std::fs::write(dir.join("old.elc"), ";ELC\x12\0\0\0\n").unwrap();
+        let err = load(&dir.join("old"), None, cx, env).unwrap_err();
+        assert!(
+            err.to_string().contains("unsupported format version 18"),
+            "{err}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_emacs_elc() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let dir = std::env::temp_dir().join(format!("rune-emacs-elc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // a documented `defvar' and `defun' as Emacs 29.1 compiles them, byte
+        // for byte, so the `#$' docstring offsets point at the docstrings
+        let elc = "\
+;ELC\x1d\0\0\0
+;;; Compiled
+;;; in Emacs version 29.1
+;;; with all optimizations.
+
+
+
+#@18 Number of calls.\x1f
+(defvar elc-sample-count 0 (#$ . 84))
+#@27 Return X doubled.
+
+(fn X)\x1f
+(defalias 'elc-sample-double #[257 \"\\10T\\20\\211\\\\\\207\" [elc-sample-count] 3 (#$ . 145)])
+(provide 'elc-sample)
+";
+        let elc_file = dir.join("elc-sample.elc");
+        std::fs::write(&elc_file, elc).unwrap();
+        let source = format!(
+            "(list (load {:?} nil t) (elc-sample-double 4) (elc-sample-double 5) elc-sample-count)",
+            elc_file.to_str().unwrap()
+        );
+        let expect = list![true, 8, 10, 2; cx];
+        root!(expect, cx);
+        check_interpreter(&source, expect, cx);
+
+        // errors in a compiled file report where in the file they happened
+        let bad = ";ELC\x1d\0\0\0\n\n(byte-code \"\\63\\207\" [] 1)\n";
+        let bad_file = dir.join("bad-op.elc");
+        std::fs::write(&bad_file, bad).unwrap();
+        let file: Gc<&LispString> = cx.add(bad_file.to_str().unwrap()).try_into().unwrap();
+        root!(file, cx);
+        let err = load(file, None, Some(()), None, None, cx, env).unwrap_err();
+        let location = format!("{}:3:1: ", bad_file.display());
+        assert!(err.to_string().starts_with(&location), "{err}");
+        assert!(
+            err.to_string().contains("Invalid byte opcode: op=51"),
+            "{err}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_streams() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_interpreter(
+            "(progn (set-buffer (get-buffer-create \"read-streams\")) (insert \"(a b) c\") (goto-char 1) (equal (list (read (current-buffer)) (point) (read (current-buffer)) (point)) '((a b) 6 c 8)))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((m (make-marker))) (insert \"(a b) c\") (set-marker m 6) (equal (list (read m) (marker-position m)) '(c 8)))",
+            true,
+            cx,
+        );
+        check_interpreter("(equal (read \"(foo) bar\") '(foo))", true, cx);
+        check_interpreter(
+            "(condition-case nil (read \"36028797018963968\") (overflow-error 1))",
+            1,
+            cx,
+        );
+        check_interpreter(
+            "(let ((chars (list ?\\( ?a ?\\s ?b ?\\) ?c ?\\s)) (unread nil)) (let ((stream #'(lambda (&optional ch) (if ch (setq unread ch) (if unread (prog1 unread (setq unread nil)) (prog1 (car chars) (setq chars (cdr chars)))))))) (equal (list (read stream) (read stream) unread) '((a b) c 32))))",
+            true,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_read_function_stream() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // `test` is evaluated with `obj` bound to what was read from `text`
+        // and `unread` to the character that was given back
+        let check = |text: &str, test: &str, cx: &mut Context| {
+            let chars: Vec<_> = text.chars().map(|x| (x as u32).to_string()).collect();
+            let chars = chars.join(" ");
+            let form = format!(
+                "(let ((chars (list {chars})) (unread nil)) (let* ((stream #'(lambda (&optional ch) (if ch (setq unread ch) (prog1 (car chars) (setq chars (cdr chars)))))) (obj (read stream))) {test}))"
+            );
+            check_interpreter(&form, true, cx);
+        };
+        check("#x1F ", "(equal (list obj unread) '(31 32))", cx);
+        check("#b-101)", "(equal (list obj unread) '(-5 41))", cx);
+        check("#24r1k", "(equal (list obj unread) '(44 nil))", cx);
+        check(
+            "#s(foo 1 2) x",
+            "(and (eq (type-of obj) 'foo) (null unread))",
+            cx,
+        );
+        check("#&3\"\\5\"", "(equal obj [t nil t])", cx);
+        check("#1=(a . #1#)", "(eq obj (cdr obj))", cx);
+        check("#'car", "(equal obj '(function car))", cx);
+        check(
+            "#(\"ab\" 0 1 (face bold))",
+            "(eq (get-text-property 0 'face obj) 'bold)",
+            cx,
+        );
+        check("foo(", "(equal (list obj unread) '(foo 40))", cx);
+    }
 }
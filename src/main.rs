@@ -48,7 +48,9 @@ mod alloc;
 mod arith;
 mod buffer;
 mod bytecode;
+mod casefiddle;
 mod character;
+mod cmds;
 mod data;
 mod editfns;
 mod emacs;
@@ -57,13 +59,19 @@ mod fileio;
 mod floatfns;
 mod fns;
 mod hashmap;
+mod indent;
 mod interpreter;
 mod keymap;
 mod lread;
+mod marker;
+mod overlay;
 mod print;
 mod reader;
 mod search;
+mod syntax;
+mod textprop;
 mod threads;
+mod undo;
 
 use crate::core::{
     env::{intern, Env},
@@ -124,7 +132,7 @@ fn load(env: &mut Rt<Env>, cx: &mut Context) {
 
     let buffer = String::from(r#"(load "lisp/bootstrap.el")"#);
 
-    match crate::lread::load_internal(&buffer, cx, env) {
+    match crate::lread::load_internal(buffer.chars().peekable(), None, cx, env) {
         Ok(val) => println!("{val}"),
         Err(e) => println!("Error: {e}"),
     }
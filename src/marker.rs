@@ -0,0 +1,169 @@
+use crate::core::{
+    env::Env,
+    error::{Type, TypeError},
+    gc::{Context, Rt},
+    object::{nil, Buffer, Gc, GcObj, LispBuffer, LispMarker, Object},
+};
+use anyhow::{bail, Result};
+use fn_macros::defun;
+
+/// Convert an integer or marker argument into a lisp position.
+pub(crate) fn position_arg(obj: GcObj) -> Result<i64> {
+    match obj.untag() {
+        Object::Int(pos) => Ok(pos),
+        Object::Marker(marker) => match marker.position() {
+            Some(pos) => Ok(pos as i64 + 1),
+            None => bail!("Marker does not point anywhere"),
+        },
+        _ => Err(TypeError::new(Type::IntOrMarker, obj).into()),
+    }
+}
+
+#[defun]
+fn make_marker<'ob>(cx: &'ob Context) -> &'ob LispMarker {
+    cx.add_as(LispMarker::new()).untag()
+}
+
+#[defun]
+fn marker_position(marker: &LispMarker) -> GcObj<'static> {
+    match marker.position() {
+        Some(pos) => (pos as i64 + 1).into(),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn marker_buffer(marker: &LispMarker) -> GcObj<'_> {
+    match marker.buffer() {
+        Some(buffer) => buffer.into(),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn marker_insertion_type(marker: &LispMarker) -> bool {
+    marker.insertion_type()
+}
+
+#[defun]
+fn set_marker_insertion_type<'ob>(marker: &LispMarker, itype: GcObj<'ob>) -> GcObj<'ob> {
+    marker.set_insertion_type(!itype.nil());
+    itype
+}
+
+#[defun]
+pub(crate) fn set_marker<'ob>(
+    marker: &'ob LispMarker,
+    position: GcObj,
+    buffer: Option<&LispBuffer>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispMarker> {
+    if position.nil() {
+        marker.detach();
+        return Ok(marker);
+    }
+    let pos = position_arg(position)?;
+    let buffer = match buffer {
+        Some(x) => x,
+        None => env.current_buffer(cx),
+    };
+    if buffer.is_live() {
+        marker.set(buffer, (pos - 1).max(0) as usize)?;
+    } else {
+        marker.detach();
+    }
+    Ok(marker)
+}
+
+#[defun]
+fn copy_marker<'ob>(
+    marker: Option<GcObj>,
+    itype: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispMarker> {
+    let new: &LispMarker = cx.add_as(LispMarker::new()).untag();
+    new.set_insertion_type(itype.is_some());
+    match marker.map(Gc::untag) {
+        None => {}
+        Some(Object::Marker(marker)) => {
+            if let (Some(buffer), Some(pos)) = (marker.buffer(), marker.position()) {
+                new.set(buffer, pos)?;
+            }
+        }
+        Some(Object::Int(pos)) => {
+            new.set(env.current_buffer(cx), (pos - 1).max(0) as usize)?;
+        }
+        Some(x) => bail!(TypeError::new(Type::IntOrMarker, x)),
+    }
+    Ok(new)
+}
+
+/// Create a new marker in the current buffer at the position returned by `pos`.
+fn new_marker_at<'ob>(
+    pos: impl FnOnce(&Buffer) -> usize,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispMarker> {
+    let buffer = env.current_buffer(cx);
+    let pos = pos(&buffer.lock()?.text);
+    let marker: &LispMarker = cx.add_as(LispMarker::new()).untag();
+    marker.set(buffer, pos)?;
+    Ok(marker)
+}
+
+#[defun]
+pub(crate) fn point_marker<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<&'ob LispMarker> {
+    new_marker_at(Buffer::point, env, cx)
+}
+
+#[defun]
+fn point_min_marker<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<&'ob LispMarker> {
+    new_marker_at(Buffer::begv, env, cx)
+}
+
+#[defun]
+fn point_max_marker<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<&'ob LispMarker> {
+    new_marker_at(Buffer::zv, env, cx)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::editfns::{delete_region, goto_char, insert};
+    use crate::root;
+
+    #[test]
+    fn test_markers() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("hello world")], env, cx).unwrap();
+        let marker = make_marker(cx);
+        assert_eq!(marker_position(marker), nil());
+        set_marker(marker, 7.into(), None, env, cx).unwrap();
+        let end = point_marker(env, cx).unwrap();
+        set_marker_insertion_type(end, true.into());
+        assert_eq!(marker_position(end), 12);
+
+        goto_char(1.into(), env, cx).unwrap();
+        insert(&[cx.add("oh ")], env, cx).unwrap();
+        assert_eq!(marker_position(marker), 10);
+        delete_region(1.into(), 4.into(), env, cx).unwrap();
+        assert_eq!(marker_position(marker), 7);
+
+        goto_char(end.into(), env, cx).unwrap();
+        insert(&[cx.add("!")], env, cx).unwrap();
+        assert_eq!(marker_position(end), 13);
+
+        let copy = copy_marker(Some(marker.into()), None, env, cx).unwrap();
+        assert_eq!(marker_position(copy), 7);
+        assert!(crate::fns::equal(copy.into(), marker.into()));
+        set_marker(marker, nil(), None, env, cx).unwrap();
+        assert_eq!(marker_position(marker), nil());
+        assert_eq!(marker_position(copy), 7);
+        assert_eq!(format!("{copy}"), "#<marker at 7 in *scratch*>");
+    }
+}
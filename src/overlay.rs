@@ -0,0 +1,232 @@
+//! Buffer overlays.
+use crate::core::{
+    env::{sym, Env},
+    gc::{Context, Rt},
+    object::{nil, GcObj, LispBuffer, LispOverlay, Object},
+};
+use crate::editfns::check_region;
+use crate::fns::slice_into_list;
+use crate::marker::position_arg;
+use anyhow::Result;
+use fn_macros::defun;
+
+defsym!(PRIORITY);
+
+fn overlay_list<'ob>(overlays: Vec<&'ob LispOverlay>, cx: &'ob Context) -> GcObj<'ob> {
+    let overlays: Vec<GcObj> = overlays.into_iter().map(Into::into).collect();
+    slice_into_list(&overlays, None, cx)
+}
+
+#[defun]
+fn overlayp(object: GcObj) -> bool {
+    matches!(object.untag(), Object::Overlay(_))
+}
+
+#[defun]
+fn make_overlay<'ob>(
+    beg: GcObj,
+    end: GcObj,
+    buffer: Option<&LispBuffer>,
+    front_advance: Option<()>,
+    rear_advance: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispOverlay> {
+    let (beg, end) = (position_arg(beg)?, position_arg(end)?);
+    let buffer = match buffer {
+        Some(x) => x,
+        None => env.current_buffer(cx),
+    };
+    let overlay = LispOverlay::new(front_advance.is_some(), rear_advance.is_some());
+    let overlay: &LispOverlay = cx.add_as(overlay).untag();
+    overlay.set(buffer, (beg - 1).max(0) as usize, (end - 1).max(0) as usize)?;
+    Ok(overlay)
+}
+
+#[defun]
+fn move_overlay<'ob>(
+    overlay: &'ob LispOverlay,
+    beg: GcObj,
+    end: GcObj,
+    buffer: Option<&LispBuffer>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<&'ob LispOverlay> {
+    let (beg, end) = (position_arg(beg)?, position_arg(end)?);
+    let buffer = match (buffer, overlay.buffer()) {
+        (Some(x), _) | (None, Some(x)) => x,
+        (None, None) => env.current_buffer(cx),
+    };
+    overlay.set(buffer, (beg - 1).max(0) as usize, (end - 1).max(0) as usize)?;
+    Ok(overlay)
+}
+
+#[defun]
+fn delete_overlay(overlay: &LispOverlay) -> GcObj<'static> {
+    overlay.detach();
+    nil()
+}
+
+#[defun]
+fn overlay_start(overlay: &LispOverlay) -> GcObj<'static> {
+    match overlay.position() {
+        Some((beg, _)) => (beg as i64 + 1).into(),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn overlay_end(overlay: &LispOverlay) -> GcObj<'static> {
+    match overlay.position() {
+        Some((_, end)) => (end as i64 + 1).into(),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn overlay_buffer(overlay: &LispOverlay) -> GcObj<'_> {
+    match overlay.buffer() {
+        Some(buffer) => buffer.into(),
+        None => nil(),
+    }
+}
+
+#[defun]
+fn overlay_put<'ob>(overlay: &LispOverlay, prop: GcObj, value: GcObj<'ob>) -> GcObj<'ob> {
+    overlay.put(prop, value);
+    value
+}
+
+#[defun]
+fn overlay_get<'ob>(overlay: &LispOverlay, prop: GcObj, cx: &'ob Context) -> GcObj<'ob> {
+    overlay.get(prop, cx)
+}
+
+#[defun]
+fn overlay_properties<'ob>(overlay: &LispOverlay, cx: &'ob Context) -> GcObj<'ob> {
+    let plist: Vec<_> = overlay
+        .properties(cx)
+        .into_iter()
+        .flat_map(|(k, v)| [k, v])
+        .collect();
+    slice_into_list(&plist, None, cx)
+}
+
+/// Return the overlays in the current buffer that overlap the region between
+/// `beg` and `end`.
+#[defun]
+fn overlays_in<'ob>(
+    beg: GcObj,
+    end: GcObj,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let (beg, end) = (position_arg(beg)?, position_arg(end)?);
+    let buffer = env.current_buffer(cx).lock()?;
+    let text = &buffer.text;
+    let (beg, end) = check_region(text, beg, end)?;
+    let overlays = text.overlays.overlays_in(beg, end, text.zv(), cx);
+    Ok(overlay_list(overlays, cx))
+}
+
+/// Return the overlays in the current buffer that contain the character at
+/// `pos`. If `sorted` is non-nil they are sorted by decreasing priority.
+#[defun]
+fn overlays_at<'ob>(
+    pos: GcObj,
+    sorted: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let pos = position_arg(pos)?;
+    let buffer = env.current_buffer(cx).lock()?;
+    let mut overlays = match usize::try_from(pos - 1) {
+        Ok(pos) => buffer.text.overlays.overlays_at(pos, cx),
+        Err(_) => Vec::new(),
+    };
+    if sorted.is_some() {
+        let priority = |overlay: &LispOverlay| match overlay.get(sym::PRIORITY.into(), cx).untag() {
+            Object::Int(x) => x,
+            _ => 0,
+        };
+        overlays.sort_by_key(|x| std::cmp::Reverse(priority(x)));
+    }
+    Ok(overlay_list(overlays, cx))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::intern;
+    use crate::core::gc::RootSet;
+    use crate::core::object::{Gc, List};
+    use crate::editfns::{delete_region, goto_char, insert};
+    use crate::root;
+
+    #[test]
+    fn test_overlays() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("hello world")], env, cx).unwrap();
+        let hello = make_overlay(1.into(), 6.into(), None, None, None, env, cx).unwrap();
+        let world = make_overlay(7.into(), 12.into(), None, Some(()), Some(()), env, cx).unwrap();
+        let empty = make_overlay(6.into(), 6.into(), None, None, None, env, cx).unwrap();
+        let face: GcObj = intern("face", cx).into();
+        overlay_put(hello, face, sym::TRUE.into());
+        assert_eq!(overlay_get(hello, face, cx), sym::TRUE);
+        assert_eq!(overlay_get(world, face, cx), nil());
+        assert_eq!(hello.to_string(), "#<overlay from 1 to 6 in *scratch*>");
+
+        let at = overlays_at(3.into(), None, env, cx).unwrap();
+        assert_eq!(at, list![hello; cx]);
+        let at = overlays_at(6.into(), None, env, cx).unwrap();
+        assert_eq!(at, nil());
+        let within = overlays_in(5.into(), 8.into(), env, cx).unwrap();
+        assert_eq!(within, list![hello, empty, world; cx]);
+
+        // text inserted at the start of `world` is not part of it, but text at
+        // the end is
+        goto_char(7.into(), env, cx).unwrap();
+        insert(&[cx.add("big ")], env, cx).unwrap();
+        goto_char(16.into(), env, cx).unwrap();
+        insert(&[cx.add("!")], env, cx).unwrap();
+        assert_eq!(overlay_start(world), 11);
+        assert_eq!(overlay_end(world), 17);
+        delete_region(1.into(), 7.into(), env, cx).unwrap();
+        assert_eq!(overlay_start(hello), 1);
+        assert_eq!(overlay_end(hello), 1);
+
+        move_overlay(hello, 2.into(), 4.into(), None, env, cx).unwrap();
+        let at = overlays_at(3.into(), None, env, cx).unwrap();
+        assert_eq!(at, list![hello; cx]);
+        delete_overlay(hello);
+        assert_eq!(overlay_start(hello), nil());
+        assert_eq!(overlays_at(3.into(), None, env, cx).unwrap(), nil());
+    }
+
+    #[test]
+    fn test_many_overlays() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("x".repeat(10_000))], env, cx).unwrap();
+        for i in 0..2_000 {
+            let beg = i * 5 + 1;
+            make_overlay(beg.into(), (beg + 10).into(), None, None, None, env, cx).unwrap();
+        }
+        let count = |x: GcObj| {
+            let list: Gc<List> = x.try_into().unwrap();
+            list.elements().count()
+        };
+        assert_eq!(count(overlays_at(501.into(), None, env, cx).unwrap()), 2);
+        assert_eq!(
+            count(overlays_in(1.into(), 51.into(), env, cx).unwrap()),
+            10
+        );
+        goto_char(1.into(), env, cx).unwrap();
+        insert(&[cx.add("abc")], env, cx).unwrap();
+        assert_eq!(count(overlays_at(504.into(), None, env, cx).unwrap()), 2);
+        assert_eq!(count(overlays_at(3.into(), None, env, cx).unwrap()), 1);
+    }
+}
@@ -1,12 +1,309 @@
-use crate::core::object::GcObj;
+//! Printing objects to output streams.
+use crate::core::{
+    env::{sym, Env},
+    error::error_message,
+    gc::{Context, Rt},
+    object::{
+        nil, print_to_string, FloatFormat, Function, Gc, GcObj, LispBuffer, Object, PrintOptions,
+    },
+};
+use crate::root;
+use anyhow::{bail, Result};
 use fn_macros::defun;
+use std::io::Write;
 
+/// The message that is shown for the error `obj`, which is a list of an error
+/// symbol and its data like the ones `condition-case` binds.
 #[defun]
-fn error_message_string(obj: GcObj) -> String {
-    // TODO: implement
-    format!("Error: {obj}")
+fn error_message_string(obj: GcObj, env: &Rt<Env>, cx: &Context) -> String {
+    match obj.untag() {
+        Object::Cons(cons) => error_message(cons.car(), cons.cdr(), env, cx),
+        _ => error_message(obj, nil(), env, cx),
+    }
 }
 
 defvar!(PRINT_LENGTH);
 defvar!(PRINT_LEVEL);
 defvar_bool!(PRINT_ESCAPE_NEWLINES, false);
+defvar_bool!(PRINT_CIRCLE, false);
+defvar_bool!(PRINT_QUOTED, true);
+defvar!(FLOAT_OUTPUT_FORMAT);
+defvar!(STANDARD_OUTPUT, true);
+
+/// The printer options set by the printer variables.
+pub(crate) fn print_options(env: &Rt<Env>, cx: &Context) -> PrintOptions {
+    let var = |var| env.var(var, cx).filter(|x| !x.nil());
+    let limit = |var: Option<GcObj>| match var.map(GcObj::untag) {
+        Some(Object::Int(x)) => usize::try_from(x).ok(),
+        _ => None,
+    };
+    let float_format = match var(sym::FLOAT_OUTPUT_FORMAT).map(GcObj::untag) {
+        Some(Object::String(x)) => x.try_into().ok().and_then(FloatFormat::parse),
+        _ => None,
+    };
+    PrintOptions {
+        circle: var(sym::PRINT_CIRCLE).is_some(),
+        escape: true,
+        length: limit(var(sym::PRINT_LENGTH)),
+        level: limit(var(sym::PRINT_LEVEL)),
+        escape_newlines: var(sym::PRINT_ESCAPE_NEWLINES).is_some(),
+        quoted: !env.var(sym::PRINT_QUOTED, cx).is_some_and(GcObj::nil),
+        float_format,
+    }
+}
+
+/// Insert `text` at `pos` in `buffer`, and return the position after it.
+fn insert_at(buffer: &LispBuffer, pos: usize, text: &str) -> Result<usize> {
+    let mut data = buffer.lock()?;
+    let text_buf = &mut data.text;
+    let point = text_buf.point();
+    let pos = pos.clamp(text_buf.begv(), text_buf.zv());
+    text_buf.set_point(pos)?;
+    text_buf.insert(text);
+    let end = text_buf.point();
+    let len = end - pos;
+    text_buf.set_point(if point >= pos { point + len } else { point })?;
+    Ok(end)
+}
+
+/// Send `text` to the output stream `printcharfun`, which defaults to the
+/// value of `standard-output`. The stream can be a buffer, which is inserted
+/// into at point, a marker, which is inserted at and moved past the text, a
+/// function that is called with each character, or `t` for standard output.
+fn write_to_stream(
+    text: &str,
+    printcharfun: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<()> {
+    let stream = match printcharfun.map(|x| x.bind(cx)).filter(|x| !x.nil()) {
+        Some(stream) => stream,
+        None => env
+            .var(sym::STANDARD_OUTPUT, cx)
+            .unwrap_or_else(|| sym::TRUE.into()),
+    };
+    match stream.untag() {
+        Object::Buffer(buffer) => {
+            buffer.lock()?.text.insert(text);
+        }
+        Object::Marker(marker) => {
+            let (Some(buffer), Some(pos)) = (marker.buffer(), marker.position()) else {
+                bail!("Marker does not point anywhere");
+            };
+            let end = insert_at(buffer, pos, text)?;
+            marker.set(buffer, end)?;
+        }
+        Object::Symbol(sym::TRUE) | Object::NIL => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()?;
+        }
+        _ => {
+            let func: Gc<Function> = stream.try_into()?;
+            root!(func, cx);
+            for chr in text.chars() {
+                root!(args, move(vec![cx.add(chr as i64)]), cx);
+                func.call(args, env, cx, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print `object` to `printcharfun` so that it can be read back.
+#[defun]
+fn prin1<'ob>(
+    object: &Rt<GcObj>,
+    printcharfun: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<GcObj<'ob>> {
+    let text = print_to_string(object.bind(cx), print_options(env, cx));
+    write_to_stream(&text, printcharfun, env, cx)?;
+    Ok(object.bind(cx))
+}
+
+/// Print `object` to `printcharfun` for people to read. Strings are printed
+/// without quotes and symbols without escapes.
+#[defun]
+fn princ<'ob>(
+    object: &Rt<GcObj>,
+    printcharfun: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<GcObj<'ob>> {
+    let options = PrintOptions {
+        escape: false,
+        ..print_options(env, cx)
+    };
+    let text = print_to_string(object.bind(cx), options);
+    write_to_stream(&text, printcharfun, env, cx)?;
+    Ok(object.bind(cx))
+}
+
+/// Print `object` to `printcharfun` like `prin1`, with a newline before and
+/// after it.
+#[defun]
+fn print<'ob>(
+    object: &Rt<GcObj>,
+    printcharfun: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<GcObj<'ob>> {
+    let text = print_to_string(object.bind(cx), print_options(env, cx));
+    write_to_stream(&format!("\n{text}\n"), printcharfun, env, cx)?;
+    Ok(object.bind(cx))
+}
+
+/// Output a newline to `printcharfun`. If `ensure` is non-nil and the stream
+/// is a buffer or marker that is already at the start of a line, nothing is
+/// output.
+#[defun]
+fn terpri(
+    printcharfun: Option<&Rt<GcObj>>,
+    ensure: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<bool> {
+    if ensure.is_some() {
+        let stream = printcharfun.map(|x| x.bind(cx));
+        let stream = match stream.filter(|x| !x.nil()) {
+            Some(x) => Some(x),
+            None => env.var(sym::STANDARD_OUTPUT, cx),
+        };
+        // the position defaults to point
+        let at_bol = |buffer: &LispBuffer, pos: Option<usize>| -> Result<bool> {
+            let data = buffer.lock()?;
+            let pos = pos.unwrap_or_else(|| data.text.point());
+            Ok(pos == 0 || data.text.char_at(pos - 1) == Some('\n'))
+        };
+        let at_bol = match stream.map(GcObj::untag) {
+            Some(Object::Buffer(buffer)) => at_bol(buffer, None)?,
+            Some(Object::Marker(marker)) => match (marker.buffer(), marker.position()) {
+                (Some(buffer), pos @ Some(_)) => at_bol(buffer, pos)?,
+                _ => false,
+            },
+            _ => false,
+        };
+        if at_bol {
+            return Ok(false);
+        }
+    }
+    write_to_stream("\n", printcharfun, env, cx)?;
+    Ok(true)
+}
+
+/// Output `character` to `printcharfun`.
+#[defun]
+fn write_char(
+    character: &Rt<GcObj>,
+    printcharfun: Option<&Rt<GcObj>>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<i64> {
+    let character: i64 = character.bind(cx).try_into()?;
+    let Some(chr) = u32::try_from(character).ok().and_then(char::from_u32) else {
+        bail!("Invalid character: {character}");
+    };
+    write_to_stream(chr.encode_utf8(&mut [0; 4]), printcharfun, env, cx)?;
+    Ok(character)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{error::EvalError, gc::RootSet};
+    use crate::data::define_error;
+    use crate::interpreter::test::check_interpreter;
+    use crate::root;
+
+    #[test]
+    fn test_error_message_string() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let message = |obj, env: &Rt<Env>, cx: &Context| error_message_string(obj, env, cx);
+
+        let error = list![sym::WRONG_TYPE_ARGUMENT, sym::STRINGP, 5; cx];
+        assert_eq!(message(error, env, cx), "Wrong type argument: stringp, 5");
+        let error = list![sym::ERROR, "Foo %s", "bar"; cx];
+        assert_eq!(message(error, env, cx), "Foo %s: \"bar\"");
+        let error = list![sym::USER_ERROR, "Oops"; cx];
+        assert_eq!(message(error, env, cx), "Oops");
+        let error = list![sym::FILE_MISSING, "Opening input file", "No such file", "/x"; cx];
+        assert_eq!(
+            message(error, env, cx),
+            "Opening input file: No such file, /x"
+        );
+        let error = list![sym::ARITH_ERROR; cx];
+        assert_eq!(message(error, env, cx), "Arithmetic error");
+        let error = list![sym::NIL, 1; cx];
+        assert_eq!(message(error, env, cx), "peculiar error: 1");
+
+        let name = crate::core::env::intern("my-error", cx);
+        let parents = list![sym::FILE_ERROR, sym::ARITH_ERROR; cx];
+        define_error(name, cx.add("My error"), Some(parents), env, cx).unwrap();
+        let conditions = env.prop(name, sym::ERROR_CONDITIONS, cx).unwrap();
+        let expect = list![name, sym::FILE_ERROR, sym::ERROR, sym::ARITH_ERROR; cx];
+        assert_eq!(conditions, expect);
+        let error = list![name, "Reading", "x"; cx];
+        assert_eq!(message(error, env, cx), "Reading: x");
+        let unknown = list![crate::core::env::intern("no-such-error", cx); cx];
+        assert!(define_error(name, nil(), Some(unknown), env, cx).is_err());
+
+        let error = EvalError::signal(sym::VOID_VARIABLE.into(), list![sym::TRUE; cx], env, cx);
+        assert!(error
+            .to_string()
+            .starts_with("Symbol\u{2019}s value as variable is void: t\n"));
+    }
+
+    #[test]
+    fn test_print_circle() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let list = list!["(1 2 1 . #1)", "#1=(1 2 . #1#)", true; cx];
+        root!(list, cx);
+        check_interpreter(
+            "(let ((x (list 1 2))) (setcdr (cdr x) x) (list (prin1-to-string x) (progn (setq print-circle t) (prin1-to-string x)) (let ((y (car (read-from-string (prin1-to-string x))))) (eq (cdr (cdr y)) y))))",
+            list,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_print_streams() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_interpreter(
+            "(progn (set-buffer (get-buffer-create \"print-streams\")) (prin1 \"a\nb\" (current-buffer)) (princ \"c\" (current-buffer)) (terpri (current-buffer)) (terpri (current-buffer) t) (write-char ?x (current-buffer)) (print '(quote y) (current-buffer)) (equal (buffer-string) \"\\\"a\nb\\\"c\nx\n'y\n\"))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((m (make-marker))) (insert \"ab\") (set-marker m 2) (prin1 1.5 m) (equal (list (buffer-string) (marker-position m)) '(\"a1.5b\" 5)))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((chars nil)) (prin1 'a\\ b #'(lambda (c) (setq chars (cons c chars)))) (equal (nreverse chars) '(?a ?\\\\ ?\\s ?b)))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((standard-output (current-buffer)) (print-length 2) (print-level 1) (print-escape-newlines t) (float-output-format \"%.2f\")) (prin1 '(1 (2) 3)) (prin1 \"\n\") (prin1 0.5) (equal (buffer-string) \"(1 ... ...)\\\"\\\\n\\\"0.50\"))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((x '(a\\ b \\?c \\12 \"d\\\"e\" 1.5 [f (quote g)] #'h))) (equal (car (read-from-string (prin1-to-string x))) x))",
+            true,
+            cx,
+        );
+        check_interpreter("(prin1-to-string ''a)", "'a", cx);
+        check_interpreter(
+            "(let ((print-quoted nil)) (prin1-to-string ''a))",
+            "(quote a)",
+            cx,
+        );
+    }
+}
@@ -1,16 +1,23 @@
-//! Lisp reader that reads an object from a string.
+//! Lisp reader that reads an object from a stream of characters.
 use crate::core::{
     env::{intern, sym, Symbol},
     gc::Context,
-    object::GcObj,
+    object::{
+        nil, GcObj, HashTable, LispVec, MutObjCell, ObjCell, Object, RawMultibyte, RecordBuilder,
+        BYTE8_OFFSET, MOST_NEGATIVE_FIXNUM, MOST_POSITIVE_FIXNUM,
+    },
 };
-use crate::fns;
+use crate::{alloc, fns};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::str;
-use std::{fmt, iter::Peekable, str::CharIndices};
+use std::{fmt, iter::Peekable};
 
 type Result<T> = std::result::Result<T, Error>;
 
+defsym!(TEST);
+defsym!(DATA);
+
 /// Errors that can occur during reading a sexp from a string
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub(crate) enum Error {
@@ -24,6 +31,16 @@ pub(crate) enum Error {
     UnexpectedChar(char, usize),
     UnknownMacroCharacter(char, usize),
     ParseInt(u8, usize),
+    InvalidStringProps(usize),
+    InvalidEscape(usize),
+    UnsupportedCharName(usize),
+    UndefinedLabel(usize, usize),
+    InvalidRecord(usize),
+    InvalidHashTable(usize),
+    InvalidByteCode(usize),
+    InvalidBoolVector(usize),
+    InvalidRadix(usize),
+    IntegerOverflow(usize),
     EmptyStream,
 }
 
@@ -45,6 +62,21 @@ impl Display for Error {
             Error::UnknownMacroCharacter(chr, i) => {
                 write!(f, "Unkown reader macro character {chr}: at {i}")
             }
+            Error::InvalidStringProps(i) => write!(f, "Invalid string property list: at {i}"),
+            Error::InvalidEscape(i) => write!(f, "Invalid escape character syntax: at {i}"),
+            Error::UnsupportedCharName(i) => {
+                write!(
+                    f,
+                    "Unicode character names are not supported, use \\N{{U+X}} instead: at {i}"
+                )
+            }
+            Error::UndefinedLabel(label, i) => write!(f, "Undefined label #{label}#: at {i}"),
+            Error::InvalidRecord(i) => write!(f, "Invalid record: at {i}"),
+            Error::InvalidHashTable(i) => write!(f, "Invalid hash table: at {i}"),
+            Error::InvalidByteCode(i) => write!(f, "Invalid byte-code object: at {i}"),
+            Error::InvalidBoolVector(i) => write!(f, "Invalid bool vector: at {i}"),
+            Error::InvalidRadix(i) => write!(f, "Radix must be between 2 and 36: at {i}"),
+            Error::IntegerOverflow(i) => write!(f, "Integer is too large for a fixnum: at {i}"),
         }
     }
 }
@@ -52,7 +84,7 @@ impl Display for Error {
 impl std::error::Error for Error {}
 
 impl Error {
-    const fn position(&self) -> usize {
+    pub(crate) const fn position(&self) -> usize {
         match self {
             Error::MissingQuotedItem(x)
             | Error::MissingCloseParen(x)
@@ -63,6 +95,16 @@ impl Error {
             | Error::ExtraItemInCdr(x)
             | Error::UnexpectedChar(_, x)
             | Error::ParseInt(_, x)
+            | Error::InvalidStringProps(x)
+            | Error::InvalidEscape(x)
+            | Error::UnsupportedCharName(x)
+            | Error::UndefinedLabel(_, x)
+            | Error::InvalidRecord(x)
+            | Error::InvalidHashTable(x)
+            | Error::InvalidByteCode(x)
+            | Error::InvalidBoolVector(x)
+            | Error::InvalidRadix(x)
+            | Error::IntegerOverflow(x)
             | Error::UnknownMacroCharacter(_, x) => *x,
             Error::EmptyStream => 0,
         }
@@ -79,6 +121,16 @@ impl Error {
             | Error::ExtraCloseBracket(i)
             | Error::MissingQuotedItem(i)
             | Error::UnknownMacroCharacter(_, i)
+            | Error::InvalidStringProps(i)
+            | Error::InvalidEscape(i)
+            | Error::UnsupportedCharName(i)
+            | Error::UndefinedLabel(_, i)
+            | Error::InvalidRecord(i)
+            | Error::InvalidHashTable(i)
+            | Error::InvalidByteCode(i)
+            | Error::InvalidBoolVector(i)
+            | Error::InvalidRadix(i)
+            | Error::IntegerOverflow(i)
             | Error::ParseInt(_, i) => Some(i),
             Error::EmptyStream => None,
         }
@@ -91,8 +143,8 @@ impl Error {
     }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-enum Token<'a> {
+#[derive(PartialEq, Debug, Clone)]
+enum Token {
     OpenParen(usize),
     CloseParen(usize),
     OpenBracket(usize),
@@ -102,13 +154,35 @@ enum Token<'a> {
     Unquote(usize),
     Splice(usize),
     Sharp(usize),
-    QuestionMark(usize, char),
-    Ident(&'a str),
-    String(&'a str),
+    QuestionMark(usize, i64),
+    Ident(usize, String),
+    /// The contents of a string literal, and the position of its opening quote
+    String(usize, String),
     Error(Error),
 }
 
-impl<'a> Display for Token<'a> {
+impl Token {
+    /// The position where the token starts.
+    fn pos(&self) -> usize {
+        match self {
+            Token::OpenParen(x)
+            | Token::CloseParen(x)
+            | Token::OpenBracket(x)
+            | Token::CloseBracket(x)
+            | Token::Quote(x)
+            | Token::Backquote(x)
+            | Token::Unquote(x)
+            | Token::Splice(x)
+            | Token::Sharp(x)
+            | Token::QuestionMark(x, _)
+            | Token::Ident(x, _)
+            | Token::String(x, _) => *x,
+            Token::Error(e) => e.position(),
+        }
+    }
+}
+
+impl Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::OpenParen(_) => write!(f, "("),
@@ -120,66 +194,107 @@ impl<'a> Display for Token<'a> {
             Token::Unquote(_) => write!(f, ","),
             Token::Splice(_) => write!(f, ",@"),
             Token::Sharp(_) => write!(f, "#"),
-            Token::QuestionMark(_, chr) => write!(f, "?{chr}"),
-            Token::Ident(x) => write!(f, "{x}"),
-            Token::String(x) => write!(f, "\"{x}\""),
+            Token::QuestionMark(_, chr) => {
+                match u32::try_from(*chr).ok().and_then(char::from_u32) {
+                    Some(chr) => write!(f, "?{chr}"),
+                    None => write!(f, "{chr}"),
+                }
+            }
+            Token::Ident(_, x) => write!(f, "{x}"),
+            Token::String(_, x) => write!(f, "\"{x}\""),
             Token::Error(_) => write!(f, "error"),
         }
     }
 }
 
-#[derive(Clone)]
-struct Tokenizer<'a> {
-    slice: &'a str,
-    iter: Peekable<CharIndices<'a>>,
+/// A source of characters for the reader. The reader looks at most one
+/// character past the end of an object, and only peeks at it, so after reading
+/// the source is left at the character following the object.
+pub(crate) trait CharSource {
+    /// Return the next character without consuming it.
+    fn peek_char(&mut self) -> Option<char>;
+
+    /// Consume and return the next character.
+    fn next_char(&mut self) -> Option<char>;
 }
 
-impl<'a> Tokenizer<'a> {
-    fn new(slice: &'a str) -> Self {
-        Self {
-            slice,
-            iter: slice.char_indices().peekable(),
+impl<I: Iterator<Item = char>> CharSource for Peekable<I> {
+    fn peek_char(&mut self) -> Option<char> {
+        self.peek().copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        self.next()
+    }
+}
+
+impl<S: CharSource + ?Sized> CharSource for &mut S {
+    fn peek_char(&mut self) -> Option<char> {
+        (**self).peek_char()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        (**self).next_char()
+    }
+}
+
+/// The characters of a [`CharSource`] paired with their positions, counted in
+/// characters from where reading started.
+struct CharIter<S> {
+    source: S,
+    pos: usize,
+}
+
+impl<S: CharSource> CharIter<S> {
+    fn new(source: S) -> Self {
+        Self { source, pos: 0 }
+    }
+
+    fn peek(&mut self) -> Option<(usize, char)> {
+        self.source.peek_char().map(|chr| (self.pos, chr))
+    }
+
+    /// Consume and return the next character if `func` returns true for it.
+    fn next_if(&mut self, func: impl FnOnce(&(usize, char)) -> bool) -> Option<(usize, char)> {
+        match self.peek() {
+            Some(next) if func(&next) => self.next(),
+            _ => None,
         }
     }
+}
 
-    /// Given a [`Token`] calculate it's position relative to this `Tokenizer`.
-    fn relative_pos(&self, token: Token<'a>) -> usize {
-        match token {
-            Token::OpenParen(x)
-            | Token::CloseParen(x)
-            | Token::OpenBracket(x)
-            | Token::CloseBracket(x)
-            | Token::Quote(x)
-            | Token::Backquote(x)
-            | Token::Unquote(x)
-            | Token::Splice(x)
-            | Token::Sharp(x)
-            | Token::QuestionMark(x, _) => x,
-            Token::Ident(slice) | Token::String(slice) => {
-                let beg = self.slice.as_ptr() as usize;
-                let end = slice.as_ptr() as usize;
-                end - beg
-            }
-            Token::Error(e) => e.position(),
+impl<S: CharSource> Iterator for CharIter<S> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chr = self.source.next_char()?;
+        let pos = self.pos;
+        self.pos += 1;
+        Some((pos, chr))
+    }
+}
+
+struct Tokenizer<S> {
+    iter: CharIter<S>,
+}
+
+impl<S: CharSource> Tokenizer<S> {
+    fn new(source: S) -> Self {
+        Self {
+            iter: CharIter::new(source),
         }
     }
 
     /// Return the current position of the Tokenizer. This is the index of the
     /// next character.
-    fn cur_pos(&mut self) -> usize {
-        match self.iter.peek() {
-            Some((idx, _)) => *idx,
-            None => self.slice.len(),
-        }
+    fn cur_pos(&self) -> usize {
+        self.iter.pos
     }
 
     /// Skip characters until the closure returns true.
     fn skip_till(&mut self, mut func: impl FnMut(char) -> bool) -> usize {
         while self.iter.next_if(|x| !func(x.1)).is_some() {}
-        match self.iter.peek() {
-            Some((idx, _)) => *idx,
-            None => self.slice.len(),
-        }
+        self.cur_pos()
     }
 
     /// Skip whitespace and comments until the next valid read character.
@@ -203,60 +318,140 @@ impl<'a> Tokenizer<'a> {
         self.skip_till(valid_char);
     }
 
-    fn get_string(&mut self, open_delim_pos: usize) -> Token<'a> {
+    fn get_string(&mut self, open_delim_pos: usize) -> Token {
+        let mut string = String::new();
         let mut skip = false;
-        let idx_chr = self
-            .iter
-            .find(|(_, chr)| !escaped(&mut skip, *chr) && *chr == '"');
-        match idx_chr {
-            Some((end, '"')) => Token::String(&self.slice[(open_delim_pos + 1)..end]),
-            _ => Token::Error(Error::MissingStringDel(open_delim_pos)),
+        for (_, chr) in self.iter.by_ref() {
+            if !escaped(&mut skip, chr) && chr == '"' {
+                return Token::String(open_delim_pos, string);
+            }
+            string.push(chr);
         }
+        Token::Error(Error::MissingStringDel(open_delim_pos))
     }
 
-    fn get_symbol(&mut self, beg: usize, chr: char) -> Token<'a> {
+    fn get_symbol(&mut self, beg: usize, chr: char) -> Token {
+        let mut symbol = String::from(chr);
         let mut skip = chr == '\\';
-        let end = self.skip_till(|c| !escaped(&mut skip, c) && !symbol_char(c));
-        Token::Ident(&self.slice[beg..end])
+        while let Some((_, chr)) = self
+            .iter
+            .next_if(|(_, c)| escaped(&mut skip, *c) || symbol_char(*c))
+        {
+            symbol.push(chr);
+        }
+        Token::Ident(beg, symbol)
     }
 
     /// After having found a `,`, see if the next token is a `@` or not.
-    fn get_macro_char(&mut self, idx: usize) -> Token<'a> {
+    fn get_macro_char(&mut self, idx: usize) -> Token {
         match self.iter.next_if(|(_, chr)| *chr == '@') {
             Some(_) => Token::Splice(idx),
             None => Token::Unquote(idx),
         }
     }
 
-    fn read_quoted_char(&mut self, idx: usize) -> Token<'a> {
-        match self.iter.next() {
-            Some((start, item)) => {
-                if item == '\\' {
-                    // TODO implement keycode parsing
-                    self.get_symbol(start, item);
-                    Token::QuestionMark(start, '\0')
-                } else {
-                    match self.iter.peek() {
-                        // ?aa
-                        Some((i, chr)) if symbol_char(*chr) && *chr != '?' => {
-                            Token::Error(Error::UnexpectedChar(*chr, *i))
-                        }
-                        // ?a
-                        _ => Token::QuestionMark(idx, item),
-                    }
-                }
+    fn read_quoted_char(&mut self, idx: usize) -> Token {
+        let chr = match self.iter.next() {
+            Some((start, '\\')) => match read_escape(&mut self.iter, start) {
+                Ok(chr) => chr,
+                Err(e) => return Token::Error(e),
+            },
+            Some((_, chr)) => chr as i64,
+            None => return Token::Error(Error::MissingQuotedItem(idx)),
+        };
+        // a raw byte in a character literal is just the byte
+        let chr = if is_byte8(chr) { chr - BYTE8_OFFSET } else { chr };
+        match self.iter.peek() {
+            // ?aa
+            Some((i, chr)) if symbol_char(chr) && chr != '?' => {
+                Token::Error(Error::UnexpectedChar(chr, i))
             }
-            None => Token::Error(Error::MissingQuotedItem(idx)),
+            // ?a
+            _ => Token::QuestionMark(idx, chr),
         }
     }
 
     fn read_char(&mut self) -> Option<char> {
         self.iter.next().map(|x| x.1)
     }
+
+    /// Skip a docstring written as `#@COUNT`, which byte-compiled files use.
+    /// `COUNT` bytes are skipped, starting with the one after the count, and
+    /// `#@00` skips the rest of the input.
+    fn skip_docstring(&mut self) {
+        let mut count = String::new();
+        while let Some((_, chr)) = self.iter.next_if(|(_, chr)| chr.is_ascii_digit()) {
+            count.push(chr);
+        }
+        if count == "00" {
+            while self.iter.next().is_some() {}
+            return;
+        }
+        let mut bytes: usize = count.parse().unwrap_or(0);
+        while bytes > 0 {
+            let Some((_, chr)) = self.iter.next() else { break };
+            bytes = bytes.saturating_sub(chr.len_utf8());
+        }
+    }
+
+    /// Consume the rest of the object that starts with `token`, the same way
+    /// [`Reader::read_token`] would, but without reading it. Return `None` if
+    /// the source ends or the object is found to be invalid, which the reader
+    /// will report.
+    fn skip_object(&mut self, token: Token) -> Option<()> {
+        match token {
+            Token::OpenParen(_) => self.skip_elements(|x| matches!(x, Token::CloseParen(_))),
+            Token::OpenBracket(_) => self.skip_elements(|x| matches!(x, Token::CloseBracket(_))),
+            Token::Quote(_) | Token::Backquote(_) | Token::Unquote(_) | Token::Splice(_) => {
+                self.skip_next()
+            }
+            Token::Sharp(_) => self.skip_sharp(),
+            Token::CloseParen(_) | Token::CloseBracket(_) | Token::Error(_) => None,
+            Token::QuestionMark(..) | Token::Ident(..) | Token::String(..) => Some(()),
+        }
+    }
+
+    fn skip_next(&mut self) -> Option<()> {
+        let token = self.next()?;
+        self.skip_object(token)
+    }
+
+    /// Skip the elements of a list or vector, up to the token that closes it.
+    fn skip_elements(&mut self, is_close: fn(&Token) -> bool) -> Option<()> {
+        loop {
+            let token = self.next()?;
+            if is_close(&token) {
+                return Some(());
+            }
+            self.skip_object(token)?;
+        }
+    }
+
+    /// Skip a sharp syntax, like [`Reader::read_sharp`].
+    fn skip_sharp(&mut self) -> Option<()> {
+        match self.read_char()? {
+            '\'' | 's' => self.skip_next(),
+            '(' => self.skip_elements(|x| matches!(x, Token::CloseParen(_))),
+            '[' => self.skip_elements(|x| matches!(x, Token::CloseBracket(_))),
+            'b' | 'B' | 'o' | 'O' | 'x' | 'X' => self.next().map(drop),
+            '&' => self.next().and_then(|_| self.next()).map(drop),
+            '$' => Some(()),
+            '0'..='9' => {
+                while self.iter.next_if(|(_, chr)| chr.is_ascii_digit()).is_some() {}
+                match self.read_char()? {
+                    'r' | 'R' => self.next().map(drop),
+                    '#' => Some(()),
+                    '=' => self.skip_next(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
 }
 
-impl<'a> Iterator for Tokenizer<'a> {
-    type Item = Token<'a>;
+impl<S: CharSource> Iterator for Tokenizer<S> {
+    type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.skip_till_char();
@@ -269,6 +464,10 @@ impl<'a> Iterator for Tokenizer<'a> {
             '\'' => Token::Quote(idx),
             ',' => self.get_macro_char(idx),
             '`' => Token::Backquote(idx),
+            '#' if self.iter.next_if(|x| x.1 == '@').is_some() => {
+                self.skip_docstring();
+                return self.next();
+            }
             '#' => Token::Sharp(idx),
             '?' => self.read_quoted_char(idx),
             '"' => self.get_string(idx),
@@ -302,43 +501,313 @@ fn intern_symbol<'ob>(symbol: &str, cx: &'ob Context) -> Symbol<'ob> {
 
 /// Parse a symbol from a string. This will either by a true symbol or a number
 /// literal.
-fn parse_symbol<'a>(slice: &str, cx: &'a Context) -> GcObj<'a> {
-    match slice.parse::<i64>() {
-        Ok(num) => cx.add(num),
-        Err(_) => match slice.parse::<f64>() {
-            Ok(num) => cx.add(num),
-            Err(_) => cx.add(intern_symbol(slice, cx)),
+fn parse_symbol<'a>(slice: &str, start: usize, cx: &'a Context) -> Result<GcObj<'a>> {
+    match parse_number(slice, start, cx)? {
+        Some(num) => Ok(num),
+        None => Ok(cx.add(intern_symbol(slice, cx))),
+    }
+}
+
+/// The integer `int` read at `start`. It is an error if `int` is `None` or does
+/// not fit in a fixnum. GNU Emacs would make a bignum instead, but those are
+/// not supported.
+fn make_integer<'ob>(int: Option<i64>, start: usize, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    match int {
+        Some(int @ MOST_NEGATIVE_FIXNUM..=MOST_POSITIVE_FIXNUM) => Ok(cx.add(int)),
+        _ => Err(Error::IntegerOverflow(start)),
+    }
+}
+
+/// Parse `slice` as a number, using the Emacs syntax. An integer is a sequence
+/// of digits with an optional sign and trailing `.`. A float has digits after
+/// the `.`, or an exponent, which can be `+INF` or `+NaN` for the special
+/// values. Anything else, like `inf` or `1.5.`, is not a number.
+fn parse_number<'ob>(slice: &str, start: usize, cx: &'ob Context) -> Result<Option<GcObj<'ob>>> {
+    let bytes = slice.as_bytes();
+    let digits = |start: usize| {
+        let rest = bytes.get(start..).unwrap_or_default();
+        rest.iter().take_while(|x| x.is_ascii_digit()).count()
+    };
+    let negative = bytes.first() == Some(&b'-');
+    let mut pos = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let lead = digits(pos);
+    pos += lead;
+    let dot = bytes.get(pos) == Some(&b'.');
+    let trail = if dot { digits(pos + 1) } else { 0 };
+    if dot {
+        pos += 1 + trail;
+    }
+    let mantissa_end = pos;
+    let mut special = None;
+    let mut exponent = false;
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        match &slice[pos + 1..] {
+            "+INF" => special = Some(f64::INFINITY),
+            "+NaN" => special = Some(f64::NAN),
+            rest => {
+                let sign = usize::from(matches!(rest.as_bytes().first(), Some(b'+' | b'-')));
+                let count = digits(pos + 1 + sign);
+                if count > 0 {
+                    exponent = true;
+                    pos += 1 + sign + count;
+                }
+            }
+        }
+        if special.is_some() {
+            exponent = true;
+            pos = bytes.len();
+        }
+    }
+    if pos != bytes.len() {
+        return Ok(None);
+    }
+    if lead > 0 && trail == 0 && !exponent {
+        let int = slice[..mantissa_end].trim_end_matches('.');
+        return make_integer(int.parse().ok(), start, cx).map(Some);
+    }
+    if trail == 0 && !(lead > 0 && exponent) {
+        return Ok(None);
+    }
+    let float = match special {
+        Some(x) if negative => -x,
+        Some(x) => x,
+        None => match slice.parse() {
+            Ok(float) => float,
+            Err(_) => return Ok(None),
         },
+    };
+    Ok(Some(cx.add(float)))
+}
+
+// The modifier bits of a character, as used for key events
+const ALT_MODIFIER: i64 = 0x40_0000;
+const SUPER_MODIFIER: i64 = 0x80_0000;
+const HYPER_MODIFIER: i64 = 0x100_0000;
+const SHIFT_MODIFIER: i64 = 0x200_0000;
+const CTRL_MODIFIER: i64 = 0x400_0000;
+const META_MODIFIER: i64 = 0x800_0000;
+const MODIFIER_MASK: i64 = 0xFC0_0000;
+
+/// The largest character code, including the raw bytes.
+const MAX_CHAR: i64 = 0x3F_FFFF;
+
+const fn is_byte8(chr: i64) -> bool {
+    BYTE8_OFFSET + 0x80 <= chr && chr <= MAX_CHAR
+}
+
+/// Read up to `max` digits in `radix`, returning the value and the number of
+/// digits read.
+fn read_digits(chars: &mut CharIter<impl CharSource>, radix: u32, max: usize) -> (i64, usize) {
+    let mut value: i64 = 0;
+    let mut count = 0;
+    while count < max {
+        let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(radix)) else { break };
+        chars.next();
+        value = value.saturating_mul(radix.into()).saturating_add(digit.into());
+        count += 1;
     }
+    (value, count)
 }
 
-/// process escape characters in the string slice and return the resulting
-/// string.
-fn unescape_string(string: &str) -> String {
-    let mut escaped = false;
-    let unescape = |c: char| {
-        if escaped {
-            escaped = false;
-            // TODO: Handle unicode, hex, and octal escapes
-            match c {
-                'n' => Some('\n'),
-                't' => Some('\t'),
-                'r' => Some('\r'),
-                '\n' | ' ' => None,
-                _ => Some(c),
+/// Apply the control modifier to `chr`. ASCII letters and the characters
+/// `@[\]^_` have control characters and `?` becomes DEL. Other characters get
+/// the control modifier bit.
+fn control_char(chr: i64) -> i64 {
+    let (base, modifiers) = (chr & !MODIFIER_MASK, chr & MODIFIER_MASK);
+    if base == '?' as i64 {
+        0o177 | modifiers
+    } else if base < 0x80
+        && ((0o101..=0o132).contains(&(base & 0o137)) || (0o100..=0o137).contains(&base))
+    {
+        (base & 0o37) | modifiers
+    } else {
+        chr | CTRL_MODIFIER
+    }
+}
+
+/// Read the character that a modifier applies to, which can itself be an
+/// escape sequence.
+fn read_modified(chars: &mut CharIter<impl CharSource>, pos: usize) -> Result<i64> {
+    match chars.next() {
+        Some((idx, '\\')) => read_escape(chars, idx),
+        Some((_, chr)) => Ok(chr as i64),
+        None => Err(Error::InvalidEscape(pos)),
+    }
+}
+
+/// Read a character name from a `\N{NAME}` escape. Only names of the form
+/// `U+XXXX` are supported. Looking up Unicode names like `LATIN SMALL LETTER
+/// A` needs the Unicode name table, which is not included, so they are an
+/// error rather than read as some other character.
+fn read_char_name(chars: &mut CharIter<impl CharSource>, pos: usize) -> Result<i64> {
+    if chars.next_if(|(_, c)| *c == '{').is_none() {
+        return Err(Error::InvalidEscape(pos));
+    }
+    let mut name = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '}')) => break,
+            Some((_, chr)) => name.push(chr),
+            None => return Err(Error::InvalidEscape(pos)),
+        }
+    }
+    let Some(hex) = name.strip_prefix("U+") else {
+        return Err(Error::UnsupportedCharName(pos));
+    };
+    match i64::from_str_radix(hex, 16) {
+        Ok(value) if value <= 0x10_FFFF && !hex.starts_with('+') => Ok(value),
+        _ => Err(Error::InvalidEscape(pos)),
+    }
+}
+
+/// Read the escape sequence that follows the backslash at `pos` and return
+/// the character code it represents. Modifier escapes like `\C-` and `\M-`
+/// set the modifier bits of the code, and `\x` and octal escapes of the bytes
+/// 0x80 to 0xFF return a raw byte.
+fn read_escape(chars: &mut CharIter<impl CharSource>, pos: usize) -> Result<i64> {
+    let error = Error::InvalidEscape(pos);
+    let Some((_, chr)) = chars.next() else { return Err(error) };
+    let value = match chr {
+        'a' => 7,
+        'b' => 8,
+        'd' => 127,
+        'e' => 27,
+        'f' => 12,
+        'n' => 10,
+        'r' => 13,
+        't' => 9,
+        'v' => 11,
+        'x' => {
+            let (value, count) = read_digits(chars, 16, usize::MAX);
+            if count == 0 || value > MAX_CHAR {
+                return Err(error);
             }
-        } else if c == '\\' {
-            escaped = true;
-            None
+            if count < 3 && (0x80..=0xFF).contains(&value) {
+                value + BYTE8_OFFSET
+            } else {
+                value
+            }
+        }
+        'u' | 'U' => {
+            let len = if chr == 'u' { 4 } else { 8 };
+            let (value, count) = read_digits(chars, 16, len);
+            if count != len || value > 0x10_FFFF {
+                return Err(error);
+            }
+            value
+        }
+        'N' => read_char_name(chars, pos)?,
+        '0'..='7' => {
+            let (rest, count) = read_digits(chars, 8, 2);
+            let value = i64::from(chr as u8 - b'0') * 8_i64.pow(count as u32) + rest;
+            if (0x80..=0xFF).contains(&value) {
+                value + BYTE8_OFFSET
+            } else {
+                value
+            }
+        }
+        '^' => control_char(read_modified(chars, pos)?),
+        'C' | 'M' | 'S' | 'H' | 'A' => {
+            if chars.next_if(|(_, c)| *c == '-').is_none() {
+                return Err(error);
+            }
+            let base = read_modified(chars, pos)?;
+            match chr {
+                'C' => control_char(base),
+                'M' => base | META_MODIFIER,
+                'S' => base | SHIFT_MODIFIER,
+                'H' => base | HYPER_MODIFIER,
+                _ => base | ALT_MODIFIER,
+            }
+        }
+        // `\s` is a space unless it is the super modifier `\s-`
+        's' => match chars.next_if(|(_, c)| *c == '-') {
+            Some(_) => read_modified(chars, pos)? | SUPER_MODIFIER,
+            None => ' ' as i64,
+        },
+        other => other as i64,
+    };
+    Ok(value)
+}
+
+/// The contents of a string literal.
+#[derive(Debug, PartialEq)]
+enum StringLiteral {
+    Multibyte(String),
+    /// A string containing raw bytes and ASCII characters
+    Unibyte(Vec<u8>),
+    /// A string containing raw bytes and other non-ASCII characters
+    Raw(RawMultibyte),
+}
+
+/// A character of a string literal.
+enum LiteralChar {
+    Char(char),
+    RawByte(u8),
+}
+
+/// Process the escape sequences in the contents of a string literal. The
+/// string is unibyte if it contains raw bytes, either from a byte escape or
+/// from the meta modifier applied to an ASCII character, and no other
+/// non-ASCII characters. If it has both, it is multibyte and the raw bytes are
+/// raw byte characters.
+fn unescape_string(string: &str) -> Result<StringLiteral> {
+    let mut literal = Vec::with_capacity(string.len());
+    let mut chars = CharIter::new(string.chars().peekable());
+    while let Some((pos, chr)) = chars.next() {
+        if chr != '\\' {
+            literal.push(LiteralChar::Char(chr));
+            continue;
+        }
+        // an escaped newline or space is ignored
+        if chars.next_if(|(_, c)| matches!(c, '\n' | ' ')).is_some() {
+            continue;
+        }
+        let value = read_escape(&mut chars, pos)?;
+        let (base, modifiers) = (value & !MODIFIER_MASK, value & MODIFIER_MASK);
+        if is_byte8(value) {
+            literal.push(LiteralChar::RawByte((value - BYTE8_OFFSET) as u8));
+        } else if modifiers == META_MODIFIER && base < 0x80 {
+            literal.push(LiteralChar::RawByte((base | 0x80) as u8));
         } else {
-            Some(c)
+            let Some(chr) = u32::try_from(value).ok().and_then(char::from_u32) else {
+                return Err(Error::InvalidEscape(pos));
+            };
+            literal.push(LiteralChar::Char(chr));
         }
+    }
+    let raw = literal.iter().any(|x| matches!(x, LiteralChar::RawByte(_)));
+    let ascii = literal.iter().all(|x| match x {
+        LiteralChar::Char(chr) => chr.is_ascii(),
+        LiteralChar::RawByte(_) => true,
+    });
+    let string = if !raw {
+        let string = literal.into_iter().map(|x| match x {
+            LiteralChar::Char(chr) => chr,
+            LiteralChar::RawByte(_) => unreachable!(),
+        });
+        StringLiteral::Multibyte(string.collect())
+    } else if ascii {
+        let bytes = literal.into_iter().map(|x| match x {
+            LiteralChar::Char(chr) => chr as u8,
+            LiteralChar::RawByte(byte) => byte,
+        });
+        StringLiteral::Unibyte(bytes.collect())
+    } else {
+        let mut string = RawMultibyte::new();
+        for x in literal {
+            match x {
+                LiteralChar::Char(chr) => string.push(chr),
+                LiteralChar::RawByte(byte) => string.push_raw_byte(byte),
+            }
+        }
+        StringLiteral::Raw(string)
     };
-    string.chars().filter_map(unescape).collect()
+    Ok(string)
 }
 
 /// Return true if `chr` is a valid symbol character.
-const fn symbol_char(chr: char) -> bool {
+pub(crate) const fn symbol_char(chr: char) -> bool {
     !matches!(
         chr,
         '\x00'..=' ' | '(' | ')' | '[' | ']' | '#' | ',' | '`' | ';' | '"' | '\''
@@ -358,14 +827,20 @@ fn escaped(escaped: &mut bool, chr: char) -> bool {
 }
 
 /// State of the reader.
-struct Reader<'a, 'ob> {
-    /// The iterator over the tokens in the current slice.
-    tokens: Tokenizer<'a>,
+struct Reader<'ob, S> {
+    /// The iterator over the tokens in the source.
+    tokens: Tokenizer<S>,
     /// New objects are allocated in the context.
     cx: &'ob Context<'ob>,
+    /// The objects labeled with `#N=` so far.
+    labels: HashMap<usize, GcObj<'ob>>,
+    /// Where the conses were read from, if positions are being recorded.
+    positions: Option<Positions>,
+    /// The file being loaded, which `#$` reads as.
+    file: Option<&'ob str>,
 }
 
-impl<'a, 'ob> Reader<'a, 'ob> {
+impl<'ob, S: CharSource> Reader<'ob, S> {
     /// Read the cdr of a literal list.
     /// ```lisp
     /// '(1 2 3 . 45)
@@ -378,7 +853,7 @@ impl<'a, 'ob> Reader<'a, 'ob> {
                 let obj = self.read_sexp(sexp);
                 match self.tokens.next() {
                     Some(Token::CloseParen(_)) => obj.map(Some),
-                    Some(token) => Err(Error::ExtraItemInCdr(self.tokens.relative_pos(token))),
+                    Some(token) => Err(Error::ExtraItemInCdr(token.pos())),
                     None => Err(Error::MissingCloseParen(delim)),
                 }
             }
@@ -391,10 +866,10 @@ impl<'a, 'ob> Reader<'a, 'ob> {
         while let Some(token) = self.tokens.next() {
             match token {
                 Token::CloseParen(_) => return Ok(fns::slice_into_list(&objects, None, self.cx)),
-                Token::Ident(".") => {
+                Token::Ident(_, x) if x == "." => {
                     let cdr = self.read_cdr(delim)?;
                     if cdr.is_none() {
-                        objects.push(parse_symbol(".", self.cx));
+                        objects.push(parse_symbol(".", delim, self.cx)?);
                     }
                     return Ok(fns::slice_into_list(&objects, cdr, self.cx));
                 }
@@ -405,10 +880,15 @@ impl<'a, 'ob> Reader<'a, 'ob> {
     }
 
     fn read_vec(&mut self, delim: usize) -> Result<GcObj<'ob>> {
+        let objects = self.read_vec_elements(delim)?;
+        Ok(self.cx.add(objects))
+    }
+
+    fn read_vec_elements(&mut self, delim: usize) -> Result<Vec<GcObj<'ob>>> {
         let mut objects = Vec::new();
         while let Some(token) = self.tokens.next() {
             match token {
-                Token::CloseBracket(_) => return Ok(self.cx.add(objects)),
+                Token::CloseBracket(_) => return Ok(objects),
                 tok => objects.push(self.read_sexp(tok)?),
             }
         }
@@ -424,15 +904,202 @@ impl<'a, 'ob> Reader<'a, 'ob> {
         Ok(list!(symbol, obj; self.cx))
     }
 
-    /// Read number with specificed radix
+    /// Read an integer in `radix`, which has an optional sign. Integers too
+    /// large for a fixnum are an error.
     fn read_radix(&mut self, pos: usize, radix: u8) -> Result<GcObj<'ob>> {
-        match self.tokens.next() {
-            Some(Token::Ident(ident)) => match usize::from_str_radix(ident, radix.into()) {
-                Ok(x) => Ok(self.cx.add(x as i64)),
-                Err(_) => Err(Error::ParseInt(radix, pos)),
+        let error = Error::ParseInt(radix, pos);
+        let Some(Token::Ident(_, ident)) = self.tokens.next() else { return Err(error) };
+        let (negative, digits) = match ident.as_bytes().first() {
+            Some(b'-') => (true, &ident[1..]),
+            Some(b'+') => (false, &ident[1..]),
+            _ => (false, &ident[..]),
+        };
+        if digits.is_empty() {
+            return Err(error);
+        }
+        let mut int = Some(0_i64);
+        for chr in digits.chars() {
+            let digit = chr.to_digit(radix.into()).ok_or(error)?;
+            int = int.and_then(|x| x.checked_mul(radix.into())?.checked_add(digit.into()));
+        }
+        if negative {
+            make_integer(int.and_then(i64::checked_neg), pos, self.cx)
+        } else {
+            make_integer(int, pos, self.cx)
+        }
+    }
+
+    /// Read a sharp syntax that starts with a number `N`: an integer in radix
+    /// `N` written `#NrDIGITS`, a label definition `#N=OBJ`, or a reference
+    /// `#N#` to the object labeled `N`. The first digit of `N` has already been
+    /// read. A label can be used inside `OBJ`, so it is first bound to a
+    /// placeholder cons that is patched once `OBJ` has been read.
+    fn read_numbered(&mut self, pos: usize, first: char) -> Result<GcObj<'ob>> {
+        let digit = |chr: char| chr.to_digit(10).map_or(0, |x| x as usize);
+        let mut label = digit(first);
+        while let Some((_, chr)) = self.tokens.iter.next_if(|(_, chr)| chr.is_ascii_digit()) {
+            label = label.saturating_mul(10).saturating_add(digit(chr));
+        }
+        let undefined = Error::UndefinedLabel(label, pos);
+        match self.tokens.read_char() {
+            Some('r' | 'R') => match u8::try_from(label) {
+                Ok(radix @ 2..=36) => self.read_radix(pos, radix),
+                _ => Err(Error::InvalidRadix(pos)),
             },
-            _ => Err(Error::ParseInt(radix, pos)),
+            Some('#') => self.labels.get(&label).copied().ok_or(undefined),
+            Some('=') => {
+                let placeholder = cons!(nil(); self.cx);
+                self.labels.insert(label, placeholder);
+                let obj = match self.tokens.next() {
+                    Some(token) => self.read_sexp(token)?,
+                    None => return Err(Error::MissingQuotedItem(pos)),
+                };
+                if obj.ptr_eq(placeholder) {
+                    return Err(undefined);
+                }
+                if let Object::Cons(cons) = obj.untag() {
+                    // make the placeholder the object, so references to it
+                    // don't need to be replaced
+                    let placeholder = placeholder.as_cons();
+                    placeholder.set_car(cons.car()).expect("placeholder should be mutable");
+                    placeholder.set_cdr(cons.cdr()).expect("placeholder should be mutable");
+                    Ok(placeholder.into())
+                } else {
+                    substitute_placeholder(obj, placeholder, obj);
+                    self.labels.insert(label, obj);
+                    Ok(obj)
+                }
+            }
+            Some(chr) => Err(Error::UnknownMacroCharacter(chr, pos)),
+            None => Err(Error::MissingQuotedItem(pos)),
+        }
+    }
+
+    /// Read a record `#s(TYPE SLOTS...)`, or a hash table if `TYPE` is
+    /// `hash-table`.
+    fn read_record(&mut self, pos: usize) -> Result<GcObj<'ob>> {
+        let elements = match self.tokens.next() {
+            Some(Token::OpenParen(i)) => list_elements(self.read_list(i)?),
+            _ => return Err(Error::InvalidRecord(pos)),
+        };
+        match elements.split_first() {
+            Some((head, props)) if *head == sym::HASH_TABLE => self.make_hash_table(pos, props),
+            Some(_) => Ok(self.cx.add(RecordBuilder(elements))),
+            None => Err(Error::InvalidRecord(pos)),
+        }
+    }
+
+    /// Make a hash table from the properties of `#s(hash-table PROPS...)`. The
+    /// entries are given by `data` as a list of alternating keys and values.
+    /// Properties other than `test` and `data` are ignored.
+    fn make_hash_table(&self, pos: usize, props: &[GcObj<'ob>]) -> Result<GcObj<'ob>> {
+        let error = Error::InvalidHashTable(pos);
+        let mut table = HashTable::with_hasher(std::hash::BuildHasherDefault::default());
+        for prop in props.chunks(2) {
+            let [key, value] = *prop else { return Err(error) };
+            if key == sym::TEST {
+                if value != sym::EQ && value != sym::EQL && value != sym::EQUAL {
+                    return Err(error);
+                }
+            } else if key == sym::DATA {
+                let data = list_elements(value);
+                if !data.len().is_multiple_of(2) {
+                    return Err(error);
+                }
+                for entry in data.chunks(2) {
+                    table.insert(entry[0], entry[1]);
+                }
+            }
+        }
+        Ok(self.cx.add(table))
+    }
+
+    /// Read a byte-code function `#[ARGS CODE CONSTANTS DEPTH DOC INTERACTIVE]`.
+    /// The opening bracket has already been read.
+    fn read_byte_code(&mut self, pos: usize) -> Result<GcObj<'ob>> {
+        let error = Error::InvalidByteCode(pos);
+        let elements = self.read_vec_elements(pos + 1)?;
+        let [args, code, constants, depth, rest @ ..] = &elements[..] else { return Err(error) };
+        let (
+            Object::Int(args @ 0..),
+            Object::String(code),
+            Object::Vec(constants),
+            Object::Int(depth @ 0..),
+        ) = (args.untag(), code.untag(), constants.untag(), depth.untag())
+        else {
+            return Err(error);
+        };
+        let (doc, interactive) = (rest.first().copied(), rest.get(1).copied());
+        let rest = rest.get(2..).unwrap_or_default();
+        let func = alloc::make_byte_code(
+            args as u64,
+            code,
+            constants,
+            depth as usize,
+            doc,
+            interactive,
+            rest,
+            self.cx,
+        );
+        func.map(Into::into).map_err(|_| error)
+    }
+
+    /// Read a bool vector `#&LENGTH"BITS"`, where element `i` is bit `i % 8` of
+    /// byte `i / 8` of `BITS`. There is no bool vector type yet, so this is read
+    /// as a vector of `t` and `nil`.
+    fn read_bool_vector(&mut self, pos: usize) -> Result<GcObj<'ob>> {
+        let error = Error::InvalidBoolVector(pos);
+        let (Some(Token::Ident(_, len)), Some(Token::String(_, bits))) =
+            (self.tokens.next(), self.tokens.next())
+        else {
+            return Err(error);
+        };
+        let len: usize = len.parse().map_err(|_| error)?;
+        let bytes = match unescape_string(&bits)? {
+            StringLiteral::Unibyte(bytes) => bytes,
+            StringLiteral::Multibyte(string) => {
+                let bytes: std::result::Result<_, _> = string.chars().map(u8::try_from).collect();
+                bytes.map_err(|_| error)?
+            }
+            StringLiteral::Raw(_) => return Err(error),
+        };
+        if bytes.len() != len.div_ceil(8) {
+            return Err(error);
         }
+        let bit = |i: usize| bytes[i / 8] & (1 << (i % 8)) != 0;
+        let elements: Vec<GcObj> = (0..len)
+            .map(|i| if bit(i) { sym::TRUE.into() } else { nil() })
+            .collect();
+        Ok(self.cx.add(elements))
+    }
+
+    /// Read a string with text properties, `#("str" START END PLIST ...)`. The
+    /// opening paren has already been read.
+    fn read_string_props(&mut self, pos: usize) -> Result<GcObj<'ob>> {
+        let elements = list_elements(self.read_list(pos + 1)?);
+        let error = Error::InvalidStringProps(pos);
+        let Some((string, props)) = elements.split_first() else { return Err(error) };
+        let Object::String(string) = string.untag() else { return Err(error) };
+        if !props.len().is_multiple_of(3) {
+            return Err(error);
+        }
+        for prop in props.chunks(3) {
+            let (Object::Int(beg), Object::Int(end)) = (prop[0].untag(), prop[1].untag()) else {
+                return Err(error);
+            };
+            if beg < 0 || end < beg || end as usize > string.len() {
+                return Err(error);
+            }
+            let mut plist = Vec::new();
+            let mut tail = prop[2];
+            while let Object::Cons(cons) = tail.untag() {
+                let Object::Cons(next) = cons.cdr().untag() else { return Err(error) };
+                plist.push((cons.car(), next.car()));
+                tail = next.cdr();
+            }
+            string.props_mut().set(beg as usize, end as usize, &plist);
+        }
+        Ok(string.into())
     }
 
     /// read a sharp quoted character. This could be used for reader macro's in
@@ -450,15 +1117,34 @@ impl<'a, 'ob> Reader<'a, 'ob> {
                 }
                 None => Err(Error::MissingQuotedItem(pos)),
             },
-            Some('b') => self.read_radix(pos, 2),
-            Some('o') => self.read_radix(pos, 8),
-            Some('x') => self.read_radix(pos, 16),
+            Some('(') => self.read_string_props(pos),
+            Some('b' | 'B') => self.read_radix(pos, 2),
+            Some('o' | 'O') => self.read_radix(pos, 8),
+            Some('x' | 'X') => self.read_radix(pos, 16),
+            Some('s') => self.read_record(pos),
+            Some('[') => self.read_byte_code(pos),
+            Some('&') => self.read_bool_vector(pos),
+            Some('$') => Ok(self.file.map_or_else(nil, |x| self.cx.add(x))),
+            Some(chr @ '0'..='9') => self.read_numbered(pos, chr),
             Some(chr) => Err(Error::UnknownMacroCharacter(chr, pos)),
             None => Err(Error::MissingQuotedItem(pos)),
         }
     }
 
-    fn read_sexp(&mut self, token: Token<'a>) -> Result<GcObj<'ob>> {
+    fn read_sexp(&mut self, token: Token) -> Result<GcObj<'ob>> {
+        let pos = token.pos();
+        let obj = self.read_token(token)?;
+        if let (Some(positions), Object::Cons(cons)) = (&mut self.positions, obj.untag()) {
+            // a list nested in this one has already recorded its own start
+            positions
+                .conses
+                .entry(std::ptr::from_ref(cons).addr())
+                .or_insert(pos);
+        }
+        Ok(obj)
+    }
+
+    fn read_token(&mut self, token: Token) -> Result<GcObj<'ob>> {
         match token {
             Token::OpenParen(i) => self.read_list(i),
             Token::CloseParen(i) => Err(Error::ExtraCloseParen(i)),
@@ -469,23 +1155,151 @@ impl<'a, 'ob> Reader<'a, 'ob> {
             Token::Splice(i) => self.quote_item(i, sym::SPLICE),
             Token::Backquote(i) => self.quote_item(i, sym::BACKQUOTE),
             Token::Sharp(i) => self.read_sharp(i),
-            Token::QuestionMark(_, c) => Ok((c as i64).into()),
-            Token::Ident(x) => Ok(parse_symbol(x, self.cx)),
-            Token::String(x) => Ok(self.cx.add(unescape_string(x))),
+            Token::QuestionMark(_, c) => Ok(c.into()),
+            Token::Ident(pos, x) => parse_symbol(&x, pos, self.cx),
+            Token::String(pos, x) => match unescape_string(&x) {
+                Ok(StringLiteral::Multibyte(string)) => Ok(self.cx.add(string)),
+                Ok(StringLiteral::Unibyte(bytes)) => Ok(self.cx.add(bytes)),
+                Ok(StringLiteral::Raw(string)) => Ok(self.cx.add(string)),
+                Err(mut e) => {
+                    // the contents start after the opening quote
+                    e.update_pos(pos + 1);
+                    Err(e)
+                }
+            },
             Token::Error(e) => Err(e),
         }
     }
 }
 
+/// The elements of the proper part of `list`.
+fn list_elements(list: GcObj) -> Vec<GcObj> {
+    let mut elements = Vec::new();
+    let mut tail = list;
+    while let Object::Cons(cons) = tail.untag() {
+        elements.push(cons.car());
+        tail = cons.cdr();
+    }
+    elements
+}
+
+/// Replace every reference to `placeholder` inside of `obj` with `value`.
+fn substitute_placeholder(obj: GcObj, placeholder: GcObj, value: GcObj) {
+    let replace = |cell: &MutObjCell| {
+        if cell.get().ptr_eq(placeholder) {
+            cell.set(value);
+        }
+    };
+    let mut seen = HashSet::new();
+    let mut stack = vec![obj];
+    while let Some(obj) = stack.pop() {
+        let vec: &LispVec = match obj.untag() {
+            Object::Cons(cons) => {
+                if !seen.insert(std::ptr::from_ref(cons).cast::<()>()) {
+                    continue;
+                }
+                if cons.car().ptr_eq(placeholder) {
+                    cons.set_car(value).expect("read objects should be mutable");
+                }
+                if cons.cdr().ptr_eq(placeholder) {
+                    cons.set_cdr(value).expect("read objects should be mutable");
+                }
+                stack.extend([cons.car(), cons.cdr()]);
+                continue;
+            }
+            Object::Vec(vec) => vec,
+            Object::Record(record) => record,
+            _ => continue,
+        };
+        if !seen.insert(std::ptr::from_ref(vec).cast::<()>()) {
+            continue;
+        }
+        let cells = vec.try_mut().expect("read objects should be mutable");
+        cells.iter().for_each(replace);
+        stack.extend(vec.iter().map(ObjCell::get));
+    }
+}
+
+/// The positions in the source of the conses created by the reader, keyed by
+/// their address. Objects are never moved by the garbage collector, so the
+/// addresses stay valid as long as the object that was read is alive.
+#[derive(Debug, Default)]
+pub(crate) struct Positions {
+    /// The position of the first token of the object that was read.
+    start: usize,
+    conses: HashMap<usize, usize>,
+}
+
+impl Positions {
+    pub(crate) fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The position of the text that `cons` was read from.
+    pub(crate) fn get(&self, cons: usize) -> Option<usize> {
+        self.conses.get(&cons).copied()
+    }
+}
+
 /// read a lisp object from `slice`. Return the object and index of next
-/// remaining character in the slice.
+/// remaining character in the slice. Positions are counted in characters.
 pub(crate) fn read<'ob>(slice: &str, cx: &'ob Context) -> Result<(GcObj<'ob>, usize)> {
+    read_source(slice.chars().peekable(), cx)
+}
+
+/// Read a lisp object from `source`. Return the object and the number of
+/// characters that were consumed. The source is left just after the object.
+pub(crate) fn read_source<'ob>(
+    source: impl CharSource,
+    cx: &'ob Context,
+) -> Result<(GcObj<'ob>, usize)> {
+    read_internal(source, None, None, cx).map(|(obj, pos, _)| (obj, pos))
+}
+
+/// Consume the characters of one object from `source` without reading it. This
+/// stops where reading would, so the consumed text can then be read. It is for
+/// sources that call Lisp to get characters, which can't be done while the
+/// reader is allocating.
+pub(crate) fn skip_object(source: impl CharSource) {
+    let mut tokens = Tokenizer::new(source);
+    if let Some(token) = tokens.next() {
+        tokens.skip_object(token);
+    }
+}
+
+/// Like [`read_source`], but for reading from `file` while it is loaded. This
+/// also returns where in the source every cons that was read came from, and
+/// `#$` is read as the name of the file.
+pub(crate) fn read_with_positions<'ob>(
+    source: impl CharSource,
+    file: &'ob str,
+    cx: &'ob Context,
+) -> Result<(GcObj<'ob>, usize, Positions)> {
+    read_internal(source, Some(Positions::default()), Some(file), cx)
+        .map(|(obj, pos, positions)| (obj, pos, positions.unwrap_or_default()))
+}
+
+fn read_internal<'ob>(
+    source: impl CharSource,
+    positions: Option<Positions>,
+    file: Option<&'ob str>,
+    cx: &'ob Context,
+) -> Result<(GcObj<'ob>, usize, Option<Positions>)> {
     let mut reader = Reader {
-        tokens: Tokenizer::new(slice),
+        tokens: Tokenizer::new(source),
         cx,
+        labels: HashMap::new(),
+        positions,
+        file,
     };
     match reader.tokens.next() {
-        Some(t) => reader.read_sexp(t).map(|x| (x, reader.tokens.cur_pos())),
+        Some(t) => {
+            if let Some(positions) = &mut reader.positions {
+                positions.start = t.pos();
+            }
+            let obj = reader.read_sexp(t)?;
+            Ok((obj, reader.tokens.cur_pos(), reader.positions))
+        }
         None => Err(Error::EmptyStream),
     }
 }
@@ -493,18 +1307,21 @@ pub(crate) fn read<'ob>(slice: &str, cx: &'ob Context) -> Result<(GcObj<'ob>, us
 #[cfg(test)]
 mod test {
     use crate::core::gc::RootSet;
+    use crate::core::object::{print_to_string, PrintOptions};
+    use crate::interpreter::test::check_interpreter;
 
     use super::*;
 
     #[test]
     fn tokens() {
-        let mut iter = Tokenizer::new("1 foo (\"bar\" . 1.3)");
-        assert_eq!(iter.next(), Some(Token::Ident("1")));
-        assert_eq!(iter.next(), Some(Token::Ident("foo")));
+        let mut iter = Tokenizer::new("1 foo (\"bar\" . 1.3)".chars().peekable());
+        let ident = |pos, x: &str| Some(Token::Ident(pos, x.to_owned()));
+        assert_eq!(iter.next(), ident(0, "1"));
+        assert_eq!(iter.next(), ident(2, "foo"));
         assert_eq!(iter.next(), Some(Token::OpenParen(6)));
-        assert_eq!(iter.next(), Some(Token::String("bar")));
-        assert_eq!(iter.next(), Some(Token::Ident(".")));
-        assert_eq!(iter.next(), Some(Token::Ident("1.3")));
+        assert_eq!(iter.next(), Some(Token::String(7, "bar".to_owned())));
+        assert_eq!(iter.next(), ident(13, "."));
+        assert_eq!(iter.next(), ident(15, "1.3"));
         assert_eq!(iter.next(), Some(Token::CloseParen(18)));
         assert_eq!(iter.next(), None);
     }
@@ -536,6 +1353,52 @@ mod test {
         check_reader!(0x1, "#x001", cx);
         check_reader!(0x10, "#x10", cx);
         check_reader!(0xdead_beef_i64, "#xDeAdBeEf", cx);
+        check_reader!(-255, "#x-ff", cx);
+        check_reader!(0x10, "#X10", cx);
+        check_reader!(44, "#24r1k", cx);
+        check_reader!(35, "#36rZ", cx);
+        assert_error("#x", Error::ParseInt(16, 0), cx);
+        assert_error("#b102", Error::ParseInt(2, 0), cx);
+        assert_error("#37r1", Error::InvalidRadix(0), cx);
+        assert_error("#1r1", Error::InvalidRadix(0), cx);
+    }
+
+    #[test]
+    fn test_read_number_syntax() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        check_reader!(1, "1.", cx);
+        check_reader!(-12, "-12.", cx);
+        check_reader!(0.5, ".5", cx);
+        check_reader!(-0.5, "-.5", cx);
+        check_reader!(100.0, "1e2", cx);
+        check_reader!(100.0, "1.e2", cx);
+        check_reader!(0.015, "1.5E-2", cx);
+        check_reader!(f64::INFINITY, "1.0e+INF", cx);
+        check_reader!(f64::NEG_INFINITY, "-1.0e+INF", cx);
+        check_reader!(f64::INFINITY, "5e+INF", cx);
+        let nan = read("0.0e+NaN", cx).unwrap().0;
+        assert!(matches!(nan.untag(), Object::Float(x) if x.is_nan()));
+        assert_eq!(nan.to_string(), "0.0e+NaN");
+        assert_eq!(read("-1.0e+INF", cx).unwrap().0.to_string(), "-1.0e+INF");
+
+        // these are symbols in Emacs
+        let symbols = [
+            "inf", "NaN", "+", "-", ".", "1.5.", "1e", "1e+", "e5", "1.0e+inf", "0x10",
+        ];
+        for symbol in symbols {
+            let obj = read(symbol, cx).unwrap().0;
+            assert!(matches!(obj.untag(), Object::Symbol(_)), "{symbol}");
+        }
+
+        // integers that are too large for a fixnum are an error
+        check_reader!(MOST_POSITIVE_FIXNUM, "36028797018963967", cx);
+        check_reader!(MOST_NEGATIVE_FIXNUM, "-36028797018963968", cx);
+        assert_error("36028797018963968", Error::IntegerOverflow(0), cx);
+        assert_error(" -36028797018963969", Error::IntegerOverflow(1), cx);
+        assert_error("(1 100000000000000000000.)", Error::IntegerOverflow(3), cx);
+        assert_error("#x10000000000000000", Error::IntegerOverflow(0), cx);
+        check_reader!(1e20, "100000000000000000000.0", cx);
     }
 
     #[test]
@@ -552,6 +1415,51 @@ mod test {
         assert_error("?", Error::MissingQuotedItem(0), cx);
     }
 
+    #[test]
+    fn test_read_char_escapes() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        check_reader!(7, "?\\a", cx);
+        check_reader!(127, "?\\d", cx);
+        check_reader!(27, "?\\e", cx);
+        check_reader!(32, "?\\s", cx);
+        check_reader!(40, "?\\(", cx);
+        check_reader!(92, "?\\\\", cx);
+        check_reader!(0x41, "?\\x41", cx);
+        check_reader!(0xff, "?\\xff", cx);
+        check_reader!(0x3b1, "?\\x3b1", cx);
+        check_reader!(0x3b1, "?\\u03B1", cx);
+        check_reader!(0x1_F600, "?\\U0001F600", cx);
+        check_reader!(0x1_F600, "?\\N{U+1F600}", cx);
+        check_reader!(0, "?\\0", cx);
+        check_reader!(0o177, "?\\177", cx);
+        check_reader!(0o377, "?\\377", cx);
+        check_reader!(1, "?\\C-a", cx);
+        check_reader!(1, "?\\^A", cx);
+        check_reader!(127, "?\\C-?", cx);
+        check_reader!(0, "?\\^@", cx);
+        check_reader!(0x400_0031, "?\\C-1", cx);
+        check_reader!(0x800_0061, "?\\M-a", cx);
+        check_reader!(0x800_0001, "?\\M-\\C-a", cx);
+        check_reader!(0x800_0001, "?\\C-\\M-a", cx);
+        check_reader!(0x200_0061, "?\\S-a", cx);
+        check_reader!(0x100_0061, "?\\H-a", cx);
+        check_reader!(0x40_0061, "?\\A-a", cx);
+        check_reader!(0x80_0061, "?\\s-a", cx);
+        check_reader!(list!(1, 2; cx), "(?\\C-a ?\\C-b)", cx);
+        assert_error("?\\C", Error::InvalidEscape(1), cx);
+        assert_error("?\\x", Error::InvalidEscape(1), cx);
+        assert_error("?\\u12", Error::InvalidEscape(1), cx);
+        assert_error("?\\U00110000", Error::InvalidEscape(1), cx);
+        assert_error(
+            "?\\N{LATIN SMALL LETTER A}",
+            Error::UnsupportedCharName(1),
+            cx,
+        );
+        assert_error("?\\N{U+1F600", Error::InvalidEscape(1), cx);
+        assert_error("?\\C-ab", Error::UnexpectedChar('b', 5), cx);
+    }
+
     #[test]
     fn read_bool() {
         let roots = &RootSet::default();
@@ -591,6 +1499,24 @@ mod test {
 baz""#,
             cx
         );
+        check_reader!("\x07\x1b\x7f \x0b", r#""\a\e\d\s\v""#, cx);
+        check_reader!("AB\u{3b1}", r#""\x41\102\u03b1""#, cx);
+        check_reader!("\u{3b1}x", r#""\x3b1\ x""#, cx);
+        check_reader!("\u{1F600}", r#""\N{U+1F600}""#, cx);
+        check_reader!("\x01\x00\x1a", r#""\C-a\^@\^z""#, cx);
+        check_reader!(vec![b'a', 0xff, 0x80], r#""a\xff\200""#, cx);
+        check_reader!(vec![0xe1], r#""\M-a""#, cx);
+        let mut raw = RawMultibyte::new();
+        raw.push_raw_byte(0x80);
+        raw.push('\u{e9}');
+        check_reader!(raw, r#""\200\u00e9""#, cx);
+        let string = read(r#""\200\u00e9""#, cx).unwrap().0;
+        assert_eq!(string.to_string(), "\"\\200\u{e9}\"");
+        assert_error(r#""\C-1""#, Error::InvalidEscape(1), cx);
+        assert_error(r#""ab\H-a""#, Error::InvalidEscape(3), cx);
+        assert_error(r#""\u12""#, Error::InvalidEscape(1), cx);
+        let name = r#""a\N{GREEK SMALL LETTER ALPHA}""#;
+        assert_error(name, Error::UnsupportedCharName(2), cx);
     }
 
     #[test]
@@ -639,6 +1565,103 @@ baz""#,
         assert_error("#a", Error::UnknownMacroCharacter('a', 0), cx);
     }
 
+    #[test]
+    fn read_labels() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let obj = read("(#1=(a) #1# #2=[b] #2#)", cx).unwrap().0;
+        let list: anyhow::Result<Vec<GcObj>> = obj.as_cons().elements().collect();
+        let list = list.unwrap();
+        assert!(list[0].ptr_eq(list[1]));
+        assert!(list[2].ptr_eq(list[3]));
+        assert_eq!(obj.to_string(), "((a) (a) [b] [b])");
+
+        let obj = read("#1=(a . #1#)", cx).unwrap().0;
+        assert!(obj.as_cons().cdr().ptr_eq(obj));
+        let obj = read("#1=(a #1#)", cx).unwrap().0;
+        assert_eq!(obj.to_string(), "(a #0)");
+        let obj = read("#10=[a #10# (#10#)]", cx).unwrap().0;
+        assert_eq!(obj.to_string(), "[a #0 (#0)]");
+
+        // printing with labels reads back to the same structure
+        let circle = PrintOptions {
+            circle: true,
+            ..PrintOptions::default()
+        };
+        let input = "#1=(#2=[#1# #3=(x)] #2# #3# . #1#)";
+        let obj = read(input, cx).unwrap().0;
+        assert_eq!(print_to_string(obj, circle), input);
+
+        assert_error("#1#", Error::UndefinedLabel(1, 0), cx);
+        assert_error("(#1=a #2#)", Error::UndefinedLabel(2, 6), cx);
+        assert_error("#1=#1#", Error::UndefinedLabel(1, 0), cx);
+        assert_error("#1x", Error::UnknownMacroCharacter('x', 0), cx);
+        assert_error("#1=", Error::MissingQuotedItem(0), cx);
+    }
+
+    #[test]
+    fn read_sharp_objects() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let input = "#s(foo 1 \"a\\\"b\\\\\")";
+        let obj = read(input, cx).unwrap().0;
+        assert!(matches!(obj.untag(), Object::Record(_)));
+        assert_eq!(obj.to_string(), input);
+
+        let input = "#s(hash-table size 2 test eq rehash-size 1.5 data (a 1 b 2))";
+        let obj = read(input, cx).unwrap().0;
+        let Object::HashTable(table) = obj.untag() else { unreachable!() };
+        let key: GcObj = intern("b", cx).into();
+        assert_eq!(table.borrow().get(&key).map(ObjCell::get), Some(2.into()));
+        let input = "#s(hash-table test equal data (\"a\" [1]))";
+        assert_eq!(read(input, cx).unwrap().0.to_string(), input);
+        let empty = read("#s(hash-table)", cx).unwrap().0;
+        assert_eq!(empty.to_string(), "#s(hash-table test equal)");
+
+        let input = "#[257 \"\\300\\207\" [nil] 2]";
+        let obj = read(input, cx).unwrap().0;
+        assert!(matches!(obj.untag(), Object::ByteFn(_)));
+        assert_eq!(obj.to_string(), input);
+        assert!(read("#[257 \"\\300\\207\" [nil] 2 \"doc\" (interactive)]", cx).is_ok());
+
+        let vec: Vec<GcObj> = vec![sym::TRUE.into(), nil(), sym::TRUE.into()];
+        check_reader!(vec, "#&3\"\\5\"", cx);
+        check_reader!(Vec::<GcObj>::new(), "#&0\"\"", cx);
+
+        check_reader!(list![1, 2; cx], "(1 #@3 ab 2)", cx);
+        check_reader!(intern("foo", cx), "#@5 ab\u{1f}\nfoo", cx);
+        assert_error("#@00 foo", Error::EmptyStream, cx);
+        check_reader!(cons!(false, 5; cx), "(#$ . 5)", cx);
+        let source = "(#$ . 5)".chars().peekable();
+        let obj = read_with_positions(source, "foo.elc", cx).unwrap().0;
+        assert_eq!(obj, cons!("foo.elc", 5; cx));
+
+        assert_error("#s()", Error::InvalidRecord(0), cx);
+        assert_error("#s[1]", Error::InvalidRecord(0), cx);
+        assert_error("#s(hash-table data (a))", Error::InvalidHashTable(0), cx);
+        assert_error("#s(hash-table test foo)", Error::InvalidHashTable(0), cx);
+        assert_error("#[257 \"\" [nil]]", Error::InvalidByteCode(0), cx);
+        assert_error("#[\"\" 257 [nil] 2]", Error::InvalidByteCode(0), cx);
+        assert_error("#&10\"a\"", Error::InvalidBoolVector(0), cx);
+    }
+
+    #[test]
+    fn read_string_props() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let input = "#(\"foo bar\" 0 3 (face bold) 4 7 (face italic weight 1))";
+        let obj = read(input, cx).unwrap().0;
+        assert_eq!(obj.to_string(), input);
+        assert_eq!(obj, cx.add("foo bar"));
+        assert_error(
+            "#(\"foo\" 0 4 (face bold))",
+            Error::InvalidStringProps(0),
+            cx,
+        );
+        assert_error("#(\"foo\" 0 2)", Error::InvalidStringProps(0), cx);
+        assert_error("#(foo)", Error::InvalidStringProps(0), cx);
+    }
+
     #[test]
     fn test_read_vec() {
         let roots = &RootSet::default();
@@ -679,4 +1702,15 @@ baz""#,
         assert_error(" ; comment ", Error::EmptyStream, cx);
         check_reader!(1, "; comment \n  1", cx);
     }
+
+    #[test]
+    fn test_print_read_objects() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_interpreter(
+            "(let ((h (make-hash-table :test 'equal))) (puthash 'k (record 'foo 1 [2]) h) (equal (car (read-from-string (prin1-to-string h))) h))",
+            true,
+            cx,
+        );
+    }
 }
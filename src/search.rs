@@ -1,7 +1,7 @@
 use crate::core::{
     env::Env,
     gc::{Context, Rt},
-    object::{nil, Gc, GcObj, List},
+    object::{nil, Gc, GcObj, List, Object},
 };
 use anyhow::{ensure, Result};
 use fancy_regex::Regex;
@@ -71,36 +71,70 @@ fn match_data<'ob>(
 }
 
 #[defun]
-fn set_match_data<'ob>(list: Gc<List>, _reseat: Option<()>, env: &mut Rt<Env>) -> GcObj<'ob> {
-    // TODO: add reseat when markers implemented
-    let obj: GcObj = list.into();
-    env.match_data.set(obj);
-    nil()
+fn set_match_data<'ob>(
+    list: Gc<List>,
+    reseat: Option<()>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    // markers are stored as their positions, and are made to point nowhere if
+    // `reseat` is non-nil
+    let mut data = Vec::new();
+    for elem in list.elements() {
+        let elem = elem?;
+        match elem.untag() {
+            Object::Marker(marker) => {
+                data.push(match marker.position() {
+                    Some(pos) => (pos as i64 + 1).into(),
+                    None => nil(),
+                });
+                if reseat.is_some() {
+                    marker.detach();
+                }
+            }
+            _ => data.push(elem),
+        }
+    }
+    let match_data = crate::fns::slice_into_list(&data, None, cx);
+    env.match_data.set(match_data);
+    Ok(nil())
 }
 
+/// The match data holds the start and end of each subexpression in turn.
 #[defun]
-fn match_beginning<'ob>(subexp: usize, env: &Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
+pub(crate) fn match_beginning<'ob>(
+    subexp: usize,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
     env.match_data
         .bind(cx)
         .as_list()?
-        .nth(subexp)
+        .nth(subexp * 2)
         .unwrap_or_else(|| Ok(nil()))
 }
 
 #[defun]
-fn match_end<'ob>(subexp: usize, env: &Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
+pub(crate) fn match_end<'ob>(subexp: usize, env: &Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
     env.match_data
         .bind(cx)
         .as_list()?
-        .nth(subexp + 1)
+        .nth(subexp * 2 + 1)
         .unwrap_or_else(|| Ok(nil()))
 }
 
 #[defun]
-fn string_equal(s1: &str, s2: &str) -> bool {
+pub(crate) fn string_equal(s1: &str, s2: &str) -> bool {
     s1 == s2
 }
 
+/// True if `string1` sorts before `string2`, comparing them by character.
+#[defun]
+pub(crate) fn string_lessp(string1: &str, string2: &str) -> bool {
+    // UTF-8 sorts bytewise in the same order as the characters it encodes
+    string1 < string2
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
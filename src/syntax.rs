@@ -0,0 +1,241 @@
+use crate::core::{
+    env::Env,
+    gc::{Context, Rt},
+};
+use anyhow::{bail, Result};
+use fn_macros::defun;
+
+/// Return the syntax class designator of `chr` in the standard syntax table.
+fn standard_syntax(chr: char) -> char {
+    match chr {
+        ' ' | '\t' | '\n' | '\r' | '\x0c' => ' ',
+        '(' | '[' | '{' => '(',
+        ')' | ']' | '}' => ')',
+        '"' => '"',
+        '\\' => '\\',
+        '_' | '-' | '+' | '*' | '/' | '&' | '|' | '<' | '>' | '=' => '_',
+        '.' | ',' | ';' | ':' | '?' | '!' | '#' | '@' | '~' | '^' | '\'' | '`' => '.',
+        c if c.is_alphanumeric() || !c.is_ascii() => 'w',
+        c if c.is_ascii_control() => ' ',
+        _ => '.',
+    }
+}
+
+fn is_word(chr: char) -> bool {
+    standard_syntax(chr) == 'w'
+}
+
+#[defun]
+pub(crate) fn char_syntax(character: i64) -> Result<i64> {
+    let Some(chr) = u32::try_from(character).ok().and_then(char::from_u32) else {
+        bail!("Invalid character: {character}")
+    };
+    Ok(standard_syntax(chr) as i64)
+}
+
+/// A parsed character set from the argument to `skip-chars-forward`.
+struct CharSet {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+    classes: Vec<fn(char) -> bool>,
+}
+
+impl CharSet {
+    fn new(spec: &str) -> Result<Self> {
+        let (negated, spec) = match spec.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+        let mut ranges = Vec::new();
+        let mut classes = Vec::new();
+        let mut rest = spec;
+        while let Some(chr) = rest.chars().next() {
+            rest = &rest[chr.len_utf8()..];
+            if chr == '[' && rest.starts_with(':') {
+                if let Some(end) = rest.find(":]") {
+                    classes.push(Self::char_class(&rest[1..end])?);
+                    rest = &rest[end + 2..];
+                    continue;
+                }
+            }
+            let start = match chr {
+                '\\' => match rest.chars().next() {
+                    Some(quoted) => {
+                        rest = &rest[quoted.len_utf8()..];
+                        quoted
+                    }
+                    None => chr,
+                },
+                _ => chr,
+            };
+            let mut chars = rest.chars();
+            let end = match (chars.next(), chars.next()) {
+                (Some('-'), Some(end)) => {
+                    rest = &rest[1 + end.len_utf8()..];
+                    end
+                }
+                _ => start,
+            };
+            ranges.push((start, end));
+        }
+        Ok(Self {
+            negated,
+            ranges,
+            classes,
+        })
+    }
+
+    fn char_class(name: &str) -> Result<fn(char) -> bool> {
+        Ok(match name {
+            "alpha" => char::is_alphabetic,
+            "alnum" => char::is_alphanumeric,
+            "digit" => |c: char| c.is_ascii_digit(),
+            "xdigit" => |c: char| c.is_ascii_hexdigit(),
+            "upper" => char::is_uppercase,
+            "lower" => char::is_lowercase,
+            "space" => |c: char| standard_syntax(c) == ' ',
+            "blank" => |c: char| c == ' ' || c == '\t',
+            "word" => is_word,
+            "punct" => |c: char| c.is_ascii_punctuation(),
+            "cntrl" => char::is_control,
+            "ascii" => |c: char| c.is_ascii(),
+            "nonascii" => |c: char| !c.is_ascii(),
+            _ => bail!("Invalid ISO C character class: {name}"),
+        })
+    }
+
+    fn contains(&self, chr: char) -> bool {
+        let found = self
+            .ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&chr))
+            || self.classes.iter().any(|class| class(chr));
+        found != self.negated
+    }
+}
+
+fn skip_chars(
+    string: &str,
+    lim: Option<i64>,
+    forward: bool,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<i64> {
+    let set = CharSet::new(string)?;
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let point = text.point();
+    let (min, max) = (text.begv(), text.zv());
+    let lim = lim.map_or(if forward { max } else { min }, |x| {
+        (x - 1).clamp(min as i64, max as i64) as usize
+    });
+    let moved = if forward && lim > point {
+        let (front, back) = text.slice(point, lim)?;
+        front
+            .chars()
+            .chain(back.chars())
+            .take_while(|&c| set.contains(c))
+            .count() as i64
+    } else if !forward && lim < point {
+        let (front, back) = text.slice(lim, point)?;
+        let count = back
+            .chars()
+            .rev()
+            .chain(front.chars().rev())
+            .take_while(|&c| set.contains(c));
+        -(count.count() as i64)
+    } else {
+        0
+    };
+    text.set_point((point as i64 + moved) as usize)?;
+    Ok(moved)
+}
+
+#[defun]
+pub(crate) fn skip_chars_forward(
+    string: &str,
+    lim: Option<i64>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<i64> {
+    skip_chars(string, lim, true, env, cx)
+}
+
+#[defun]
+pub(crate) fn skip_chars_backward(
+    string: &str,
+    lim: Option<i64>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<i64> {
+    skip_chars(string, lim, false, env, cx)
+}
+
+/// Count the characters at the start of `chars` that are not part of a word,
+/// followed by the characters of the word after them. Returns `None` if there
+/// is no word.
+fn next_word(chars: impl Iterator<Item = char>) -> Option<usize> {
+    let mut chars = chars.peekable();
+    let mut count = 0;
+    while chars.next_if(|&c| !is_word(c)).is_some() {
+        count += 1;
+    }
+    chars.peek()?;
+    while chars.next_if(|&c| is_word(c)).is_some() {
+        count += 1;
+    }
+    Some(count)
+}
+
+#[defun]
+pub(crate) fn forward_word(arg: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let text = &mut buffer.text;
+    let arg = arg.unwrap_or(1);
+    let mut pos = text.point();
+    for _ in 0..arg.unsigned_abs() {
+        let moved = if arg > 0 {
+            let (front, back) = text.slice(pos, text.zv())?;
+            next_word(front.chars().chain(back.chars())).map(|x| pos + x)
+        } else {
+            let (front, back) = text.slice(text.begv(), pos)?;
+            next_word(back.chars().rev().chain(front.chars().rev())).map(|x| pos - x)
+        };
+        match moved {
+            Some(new_pos) => pos = new_pos,
+            None => {
+                let edge = if arg > 0 { text.zv() } else { text.begv() };
+                text.set_point(edge)?;
+                return Ok(false);
+            }
+        }
+    }
+    text.set_point(pos)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_char_set() {
+        let set = CharSet::new("a-c_").unwrap();
+        assert!(set.contains('b'));
+        assert!(set.contains('_'));
+        assert!(!set.contains('d'));
+        let set = CharSet::new("^[:space:]\\\\^").unwrap();
+        assert!(!set.contains(' '));
+        assert!(!set.contains('\\'));
+        assert!(!set.contains('^'));
+        assert!(set.contains('x'));
+        assert!(CharSet::new("[:foo:]").is_err());
+    }
+
+    #[test]
+    fn test_next_word() {
+        assert_eq!(next_word("  foo bar".chars()), Some(5));
+        assert_eq!(next_word("foo".chars()), Some(3));
+        assert_eq!(next_word("  ".chars()), None);
+    }
+}
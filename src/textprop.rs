@@ -0,0 +1,308 @@
+//! Text properties of strings and buffers.
+use crate::core::{
+    env::{sym, Env},
+    error::{LispError, Type, TypeError},
+    gc::{Context, Rt},
+    object::{nil, Gc, GcObj, IntervalTree, LispBuffer, LispString, List, Object},
+};
+use crate::fns::slice_into_list;
+use crate::marker::position_arg;
+use anyhow::{bail, ensure, Result};
+use fn_macros::defun;
+
+/// The object whose text properties are used. This is the optional OBJECT
+/// argument of the text property functions, where nil means the current
+/// buffer.
+enum Text<'ob> {
+    Buffer(&'ob LispBuffer),
+    String(&'ob LispString),
+}
+
+impl<'ob> Text<'ob> {
+    fn new(object: Option<GcObj<'ob>>, env: &mut Rt<Env>, cx: &'ob Context) -> Result<Self> {
+        match object {
+            None => Ok(Text::Buffer(env.current_buffer(cx))),
+            Some(obj) => match obj.untag() {
+                Object::NIL => Ok(Text::Buffer(env.current_buffer(cx))),
+                Object::Buffer(buffer) => Ok(Text::Buffer(buffer)),
+                Object::String(string) => Ok(Text::String(string)),
+                _ => Err(TypeError::new(Type::BufferOrString, obj).into()),
+            },
+        }
+    }
+
+    /// The lisp position of the first character, and the accessible range of
+    /// indices. Buffer positions start at 1 and string positions at 0.
+    fn bounds(&self) -> Result<(i64, usize, usize)> {
+        match self {
+            Text::Buffer(buffer) => {
+                let data = buffer.lock()?;
+                Ok((1, data.text.begv(), data.text.zv()))
+            }
+            Text::String(string) => Ok((0, 0, string.len())),
+        }
+    }
+
+    /// Convert a lisp position into an index into the text.
+    fn index(&self, position: GcObj) -> Result<usize> {
+        let pos = position_arg(position)?;
+        let (origin, min, max) = self.bounds()?;
+        let idx = pos - origin;
+        ensure!(
+            min as i64 <= idx && idx <= max as i64,
+            LispError::new(sym::ARGS_OUT_OF_RANGE, [position.into()])
+        );
+        Ok(idx as usize)
+    }
+
+    /// Convert a region given in lisp positions into ordered indices.
+    fn region(&self, start: GcObj, end: GcObj) -> Result<(usize, usize)> {
+        let (start, end) = (position_arg(start)?, position_arg(end)?);
+        let (start, end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        let (origin, min, max) = self.bounds()?;
+        ensure!(
+            min as i64 <= start - origin && end - origin <= max as i64,
+            LispError::new(sym::ARGS_OUT_OF_RANGE, [start.into(), end.into()])
+        );
+        Ok(((start - origin) as usize, (end - origin) as usize))
+    }
+
+    fn with_props<T>(&self, f: impl FnOnce(&mut IntervalTree) -> T) -> Result<T> {
+        match self {
+            Text::Buffer(buffer) => Ok(f(&mut buffer.lock()?.text.props)),
+            Text::String(string) => Ok(f(&mut string.props_mut())),
+        }
+    }
+}
+
+/// Split a property list into property and value pairs.
+fn plist_pairs(plist: Gc<List>) -> Result<Vec<(GcObj, GcObj)>> {
+    let elements = plist.elements().collect::<Result<Vec<_>>>()?;
+    ensure!(
+        elements.len().is_multiple_of(2),
+        "Odd length text property list"
+    );
+    Ok(elements.chunks(2).map(|x| (x[0], x[1])).collect())
+}
+
+#[defun]
+fn put_text_property<'ob>(
+    start: GcObj,
+    end: GcObj,
+    property: GcObj,
+    value: GcObj,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let text = Text::new(object, env, cx)?;
+    let (beg, end) = text.region(start, end)?;
+    text.with_props(|props| props.put(beg, end, property, value))?;
+    Ok(nil())
+}
+
+#[defun]
+fn get_text_property<'ob>(
+    position: GcObj,
+    prop: GcObj,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let text = Text::new(object, env, cx)?;
+    let pos = text.index(position)?;
+    let value = text.with_props(|props| props.get(pos, prop, cx))?;
+    Ok(value.unwrap_or_default())
+}
+
+#[defun]
+fn text_properties_at<'ob>(
+    position: GcObj,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let text = Text::new(object, env, cx)?;
+    let pos = text.index(position)?;
+    let plist = text.with_props(|props| props.properties_at(pos, cx))?;
+    let plist: Vec<_> = plist.into_iter().flat_map(|(k, v)| [k, v]).collect();
+    Ok(slice_into_list(&plist, None, cx))
+}
+
+#[defun]
+fn add_text_properties(
+    start: GcObj,
+    end: GcObj,
+    properties: Gc<List>,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<bool> {
+    let text = Text::new(object, env, cx)?;
+    let (beg, end) = text.region(start, end)?;
+    let properties = plist_pairs(properties)?;
+    text.with_props(|props| props.add(beg, end, &properties))
+}
+
+#[defun]
+fn set_text_properties(
+    start: GcObj,
+    end: GcObj,
+    properties: Gc<List>,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<bool> {
+    let text = Text::new(object, env, cx)?;
+    let (beg, end) = text.region(start, end)?;
+    let properties = plist_pairs(properties)?;
+    text.with_props(|props| props.set(beg, end, &properties))?;
+    Ok(true)
+}
+
+/// Remove the properties named in the plist `properties`. The values are
+/// ignored.
+#[defun]
+fn remove_text_properties(
+    start: GcObj,
+    end: GcObj,
+    properties: Gc<List>,
+    object: Option<GcObj>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<bool> {
+    let text = Text::new(object, env, cx)?;
+    let (beg, end) = text.region(start, end)?;
+    let names: Vec<_> = plist_pairs(properties)?.into_iter().map(|x| x.0).collect();
+    text.with_props(|props| props.remove(beg, end, &names))
+}
+
+#[defun]
+fn next_single_property_change<'ob>(
+    position: GcObj,
+    prop: GcObj,
+    object: Option<GcObj>,
+    limit: Option<GcObj<'ob>>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let text = Text::new(object, env, cx)?;
+    let pos = text.index(position)?;
+    let (origin, _, max) = text.bounds()?;
+    let end = match limit {
+        Some(limit) => (position_arg(limit)? - origin).clamp(pos as i64, max as i64) as usize,
+        None => max,
+    };
+    match text.with_props(|props| props.next_change(pos, prop, end))? {
+        Some(change) => Ok((change as i64 + origin).into()),
+        None => Ok(limit.unwrap_or_default()),
+    }
+}
+
+#[defun]
+fn propertize<'ob>(
+    string: &LispString,
+    properties: &[GcObj],
+    cx: &'ob Context,
+) -> Result<&'ob LispString> {
+    let Ok(text) = <&str>::try_from(string) else {
+        bail!("propertize only supports multibyte strings")
+    };
+    ensure!(
+        properties.len().is_multiple_of(2),
+        "Wrong number of arguments: propertize, {}",
+        properties.len() + 1
+    );
+    let new: &LispString = cx.add_as(text.to_owned()).untag();
+    let properties: Vec<_> = properties.chunks(2).map(|x| (x[0], x[1])).collect();
+    let mut props = new.props_mut();
+    props.clone_from(&string.props());
+    props.add(0, new.len(), &properties);
+    drop(props);
+    Ok(new)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::intern;
+    use crate::core::gc::RootSet;
+    use crate::editfns::{buffer_substring, goto_char, insert};
+    use crate::root;
+
+    #[test]
+    fn test_string_properties() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let face: GcObj = intern("face", cx).into();
+        let bold: GcObj = intern("bold", cx).into();
+        let string: &LispString = cx.add_as("hello world").untag();
+        let obj = Some(string.into());
+        put_text_property(0.into(), 5.into(), face, bold, obj, env, cx).unwrap();
+        assert_eq!(string.to_string(), "#(\"hello world\" 0 5 (face bold))");
+        assert_eq!(
+            get_text_property(4.into(), face, obj, env, cx).unwrap(),
+            bold
+        );
+        assert_eq!(
+            get_text_property(5.into(), face, obj, env, cx).unwrap(),
+            nil()
+        );
+        assert!(get_text_property(12.into(), face, obj, env, cx).is_err());
+
+        let change = next_single_property_change(0.into(), face, obj, None, env, cx);
+        assert_eq!(change.unwrap(), 5);
+        let change = next_single_property_change(5.into(), face, obj, None, env, cx);
+        assert_eq!(change.unwrap(), nil());
+        let change = next_single_property_change(5.into(), face, obj, Some(8.into()), env, cx);
+        assert_eq!(change.unwrap(), 8);
+
+        let plist = list![face, nil(); cx];
+        root!(plist, cx);
+        let plist = plist.bind(cx).try_into().unwrap();
+        assert!(remove_text_properties(0.into(), 2.into(), plist, obj, env, cx).unwrap());
+        assert!(!remove_text_properties(0.into(), 2.into(), plist, obj, env, cx).unwrap());
+        let plist = text_properties_at(3.into(), obj, env, cx).unwrap();
+        assert_eq!(plist, list![face, bold; cx]);
+
+        let new = propertize(string, &[face, nil()], cx).unwrap();
+        assert_eq!(new.to_string(), "#(\"hello world\" 0 11 (face nil))");
+        assert_eq!(string.to_string(), "#(\"hello world\" 2 5 (face bold))");
+    }
+
+    #[test]
+    fn test_buffer_properties() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        let face: GcObj = intern("face", cx).into();
+        let bold: GcObj = intern("bold", cx).into();
+        let string = cx.add("world");
+        put_text_property(0.into(), 5.into(), face, bold, Some(string), env, cx).unwrap();
+        insert(&[cx.add("hello "), string], env, cx).unwrap();
+        let change = next_single_property_change(1.into(), face, None, None, env, cx);
+        assert_eq!(change.unwrap(), 7);
+        assert_eq!(
+            get_text_property(7.into(), face, None, env, cx).unwrap(),
+            bold
+        );
+
+        // the inserted text does not have the properties of its neighbors
+        goto_char(9.into(), env, cx).unwrap();
+        insert(&[cx.add("--")], env, cx).unwrap();
+        assert_eq!(
+            get_text_property(9.into(), face, None, env, cx).unwrap(),
+            nil()
+        );
+        let substring = buffer_substring(6.into(), 12.into(), env, cx).unwrap();
+        assert_eq!(
+            substring.to_string(),
+            "#(\" wo--r\" 1 3 (face bold) 5 6 (face bold))"
+        );
+    }
+}
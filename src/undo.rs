@@ -0,0 +1,139 @@
+use crate::core::{
+    cons::Cons,
+    env::{sym, Env},
+    gc::{Context, Rt},
+    object::{nil, Buffer, GcObj, Object},
+};
+use anyhow::{bail, ensure, Result};
+use fn_macros::defun;
+
+defvar!(BUFFER_UNDO_LIST);
+
+#[defun]
+fn undo_boundary<'ob>(env: &mut Rt<Env>, cx: &'ob Context) -> Result<GcObj<'ob>> {
+    let buffer = env.current_buffer(cx);
+    let list = buffer.undo_list(cx);
+    if let Object::Cons(cons) = list.untag() {
+        if !cons.car().nil() {
+            buffer.set_undo_list(cons!(nil(), list; cx));
+        }
+    }
+    buffer.lock()?.text.undo_boundary();
+    Ok(nil())
+}
+
+/// Undo `n` change groups from the front of `list`, and return the rest of the
+/// list. The changes made while undoing are recorded themselves, so they can
+/// be redone.
+#[defun]
+fn primitive_undo<'ob>(
+    n: i64,
+    list: GcObj<'ob>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<GcObj<'ob>> {
+    let mut buffer = env.current_buffer(cx).lock()?;
+    let mut list = list;
+    for _ in 0..n {
+        while let Object::Cons(cons) = list.untag() {
+            list = cons.cdr();
+            let entry = cons.car();
+            if entry.nil() {
+                break;
+            }
+            undo_entry(entry, &mut buffer.text)?;
+        }
+    }
+    Ok(list)
+}
+
+fn undo_entry(entry: GcObj, text: &mut Buffer) -> Result<()> {
+    let check_region = |text: &Buffer, beg: i64, end: i64| {
+        let (min, max) = (text.begv() as i64 + 1, text.zv() as i64 + 1);
+        ensure!(
+            min <= beg && beg <= end && end <= max,
+            "Changes to be undone are outside visible portion of buffer"
+        );
+        Ok(((beg - 1) as usize, (end - 1) as usize))
+    };
+    match entry.untag() {
+        Object::Int(pos) => {
+            let (min, max) = (text.begv() as i64 + 1, text.zv() as i64 + 1);
+            text.set_point((pos.clamp(min, max) - 1) as usize)?;
+        }
+        Object::Cons(cons) => undo_change(cons, text, check_region)?,
+        _ => bail!("Unrecognized entry in undo list {entry}"),
+    }
+    Ok(())
+}
+
+fn undo_change(
+    change: &Cons,
+    text: &mut Buffer,
+    check_region: impl Fn(&Buffer, i64, i64) -> Result<(usize, usize)>,
+) -> Result<()> {
+    match (change.car().untag(), change.cdr().untag()) {
+        // (BEG . END) text was inserted
+        (Object::Int(beg), Object::Int(end)) => {
+            let (beg, end) = check_region(text, beg, end)?;
+            text.set_point(beg)?;
+            text.delete_range(beg, end)?;
+        }
+        // (TEXT . POSITION) text was deleted
+        (Object::String(string), Object::Int(pos)) => {
+            let (start, _) = check_region(text, pos.abs(), pos.abs())?;
+            text.set_point(start)?;
+            text.insert(string.try_into()?);
+            // a negative position means point was at the end of the text
+            if pos > 0 {
+                text.set_point(start)?;
+            }
+        }
+        // (t . TIME) the buffer was unmodified. Modification state is not
+        // tracked yet.
+        (Object::Symbol(sym::TRUE), _) => {}
+        _ => bail!("Unrecognized entry in undo list {change}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+    use crate::editfns::{buffer_string, delete_region, insert, point};
+    use crate::root;
+
+    #[test]
+    fn test_undo() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, Env::default(), cx);
+        insert(&[cx.add("hello world")], env, cx).unwrap();
+        undo_boundary(env, cx).unwrap();
+        delete_region(1.into(), 7.into(), env, cx).unwrap();
+        undo_boundary(env, cx).unwrap();
+        let list = env.var(sym::BUFFER_UNDO_LIST, cx).unwrap();
+        let expect = list![nil(), cons!("hello ", 1; cx), 12, nil(), cons!(1, 12; cx); cx];
+        assert_eq!(list, expect);
+
+        // undo the deletion, skipping the leading boundary
+        let rest = primitive_undo(2, list, env, cx).unwrap();
+        assert_eq!(rest, list![cons!(1, 12; cx); cx]);
+        assert_eq!(buffer_string(env, cx).unwrap(), "hello world");
+        assert_eq!(point(env, cx).unwrap(), 12);
+
+        // redo it
+        undo_boundary(env, cx).unwrap();
+        let list = env.var(sym::BUFFER_UNDO_LIST, cx).unwrap();
+        primitive_undo(2, list, env, cx).unwrap();
+        assert_eq!(buffer_string(env, cx).unwrap(), "world");
+
+        // undo is not recorded while the list is t
+        let list = env.var(sym::BUFFER_UNDO_LIST, cx).unwrap();
+        env.varbind(sym::BUFFER_UNDO_LIST, sym::TRUE.into(), cx);
+        insert(&[cx.add("!")], env, cx).unwrap();
+        env.unbind(1, cx);
+        assert_eq!(env.var(sym::BUFFER_UNDO_LIST, cx).unwrap(), list);
+    }
+}